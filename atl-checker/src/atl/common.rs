@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 pub(crate) type Proposition = usize;
 pub(crate) type Player = usize;
@@ -8,7 +8,7 @@ pub(crate) type State = usize;
 pub(crate) type Action = usize;
 
 /// Implements Vec of Vecs in arbitrary runtime determined depth.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum DynVec {
     NEST(Vec<Arc<DynVec>>),
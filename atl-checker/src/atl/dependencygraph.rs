@@ -1,5 +1,6 @@
-use crate::common::{Edge, HyperEdge, NegationEdge};
+use crate::common::{Edge, HyperEdge, NegationEdge, VertexAssignment};
 use crate::edg::{ExtendedDependencyGraph, Vertex};
+use crate::global::global_fixed_point;
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -308,6 +309,27 @@ impl<G: GameStructure> ATLDependencyGraph<G> {
     }
 }
 
+impl<G: GameStructure> ATLDependencyGraph<G> {
+    /// Returns the given states where the formula holds, in the same order. The states are
+    /// checked together using [global_fixed_point], such that work is shared between them.
+    pub fn satisfaction_set(&self, states: &[State], formula: Arc<Phi>) -> Vec<State> {
+        let roots: Vec<ATLVertex> = states
+            .iter()
+            .map(|&state| ATLVertex::FULL {
+                state,
+                formula: formula.clone(),
+            })
+            .collect();
+        let assignments = global_fixed_point(self, &roots);
+        states
+            .iter()
+            .zip(&roots)
+            .filter(|(_, root)| assignments[root] == VertexAssignment::TRUE)
+            .map(|(state, _)| *state)
+            .collect()
+    }
+}
+
 impl<G: GameStructure> ExtendedDependencyGraph<ATLVertex> for ATLDependencyGraph<G> {
     /// Produce the edges of the given vertex
     /// Where possible, the smallest edge will be the first in the produced vector,
@@ -657,9 +679,16 @@ impl<G: GameStructure> ExtendedDependencyGraph<ATLVertex> for ATLDependencyGraph
 mod test {
     use crate::atl::common::DynVec;
     use crate::atl::dependencygraph::{
-        DeltaIterator, PartialMoveChoice, PartialMoveIterator, PmovesIterator,
+        ATLDependencyGraph, ATLVertex, DeltaIterator, PartialMoveChoice, PartialMoveIterator,
+        PmovesIterator,
     };
-    use crate::atl::gamestructure::EagerGameStructure;
+    use crate::atl::formula::parse_phi;
+    use crate::atl::gamestructure::{reachable_states, EagerGameStructure, GameStructure};
+    use crate::common::VertexAssignment;
+    use crate::edg::distributed_certain_zero;
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::parse::parse_lcgs;
+    use crate::search_strategy::bfs::BreadthFirstSearchBuilder;
     use std::collections::HashSet;
     use std::sync::Arc;
 
@@ -830,6 +859,11 @@ mod test {
             labeling: vec![],
             transitions: vec![transitions],
             moves: vec![],
+            initial_state: 0,
+            player_names: None,
+            label_names: None,
+            state_names: None,
+            action_names: None,
         };
         let state = 0;
         let partial_move = vec![
@@ -861,4 +895,61 @@ mod test {
         let value = iter.next();
         assert_eq!(value, None);
     }
+
+    #[test]
+    fn test_satisfaction_set_01() {
+        // The satisfaction set contains the same states as checking each state on its own
+        let input = "
+        x : [0 .. 3] init 0;
+        x' = min(x + p1.inc, 3);
+        label at_two = x == 2;
+        player p1 = one;
+        player p2 = two;
+        template one
+            [inc] 1;
+            [wait] 1;
+        endtemplate
+        template two
+            [wait] 1;
+            [act] x < 2;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let graph = ATLDependencyGraph {
+            game_structure: lcgs,
+        };
+        let states = reachable_states(
+            &graph.game_structure,
+            &[graph.game_structure.initial_state()],
+        );
+        assert_eq!(states.len(), 4);
+
+        let formulas = [
+            ("<<p1>> F at_two", 3),
+            ("<<p2>> F at_two", 1),
+            ("!<<p1>> G !at_two", 1),
+            ("<<p1>> G !at_two", 3),
+            ("<<p1>> X at_two", 2),
+        ];
+        for (formula, count) in formulas.iter() {
+            let phi = Arc::new(parse_phi(&graph.game_structure, formula).unwrap());
+            let satisfying = graph.satisfaction_set(&states, phi.clone());
+            assert_eq!(satisfying.len(), *count, "{}", formula);
+            for &state in &states {
+                let v0 = ATLVertex::FULL {
+                    state,
+                    formula: phi.clone(),
+                };
+                let result =
+                    distributed_certain_zero(graph.clone(), v0, 2, BreadthFirstSearchBuilder);
+                assert_eq!(
+                    result == VertexAssignment::TRUE,
+                    satisfying.contains(&state),
+                    "{} in {}",
+                    formula,
+                    graph.game_structure.state_name(state)
+                );
+            }
+        }
+    }
 }
@@ -105,6 +105,71 @@ impl Phi {
         }
     }
 
+    /// Returns the propositions used in the formula, in the order they appear. Propositions
+    /// used several times are included several times.
+    pub fn propositions(&self) -> Vec<Proposition> {
+        match self {
+            Phi::True => vec![],
+            Phi::False => vec![],
+            Phi::Proposition(prop) => vec![*prop],
+            Phi::Not(formula) => formula.propositions(),
+            Phi::Or(formula1, formula2) | Phi::And(formula1, formula2) => {
+                let mut props = formula1.propositions();
+                props.extend(formula2.propositions());
+                props
+            }
+            Phi::DespiteNext { formula, .. } => formula.propositions(),
+            Phi::EnforceNext { formula, .. } => formula.propositions(),
+            Phi::DespiteUntil { pre, until, .. } | Phi::EnforceUntil { pre, until, .. } => {
+                let mut props = pre.propositions();
+                props.extend(until.propositions());
+                props
+            }
+            Phi::DespiteEventually { formula, .. } => formula.propositions(),
+            Phi::EnforceEventually { formula, .. } => formula.propositions(),
+            Phi::DespiteInvariant { formula, .. } => formula.propositions(),
+            Phi::EnforceInvariant { formula, .. } => formula.propositions(),
+        }
+    }
+
+    /// Returns the coalitions of the formula, in the order they appear
+    pub fn coalitions(&self) -> Vec<Vec<Player>> {
+        match self {
+            Phi::True | Phi::False | Phi::Proposition(_) => vec![],
+            Phi::Not(formula) => formula.coalitions(),
+            Phi::Or(formula1, formula2) | Phi::And(formula1, formula2) => {
+                let mut coalitions = formula1.coalitions();
+                coalitions.extend(formula2.coalitions());
+                coalitions
+            }
+            Phi::DespiteNext { players, formula }
+            | Phi::EnforceNext { players, formula }
+            | Phi::DespiteEventually { players, formula }
+            | Phi::EnforceEventually { players, formula }
+            | Phi::DespiteInvariant { players, formula }
+            | Phi::EnforceInvariant { players, formula } => {
+                let mut coalitions = vec![players.clone()];
+                coalitions.extend(formula.coalitions());
+                coalitions
+            }
+            Phi::DespiteUntil {
+                players,
+                pre,
+                until,
+            }
+            | Phi::EnforceUntil {
+                players,
+                pre,
+                until,
+            } => {
+                let mut coalitions = vec![players.clone()];
+                coalitions.extend(pre.coalitions());
+                coalitions.extend(until.coalitions());
+                coalitions
+            }
+        }
+    }
+
     /// Returns the depth of the formula. This is equivalent to the longest branch in the
     /// phi structure
     pub fn depth(&self) -> u32 {
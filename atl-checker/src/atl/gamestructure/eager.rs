@@ -1,25 +1,215 @@
 use std::collections::hash_map::RandomState;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use crate::atl::common::{transition_lookup, DynVec, Player, Proposition, State};
-use crate::atl::formula::{number, ATLExpressionParser};
+use crate::atl::formula::{identifier, number, ATLExpressionParser};
 use crate::atl::gamestructure::GameStructure;
-use pom::parser::Parser;
+use crate::lcgs::parse::player_name;
+use pom::parser::{sym, Parser};
+use serde::Serializer;
 use std::str::{self};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EagerGameStructure {
     /// K, number of players
     pub player_count: usize,
     /// Maps states to Vec of atomic proposition, aka the labeling function
+    #[serde(serialize_with = "serialize_labeling")]
     pub labeling: Vec<HashSet<Proposition>>,
     /// Maps states, then players recursively
     pub transitions: Vec<DynVec>,
     /// available moves for a player in a given state
     pub moves: Vec<Vec<usize>>,
+    /// The state where the game starts. Defaults to 0
+    #[serde(default)]
+    pub initial_state: State,
+    /// Optional names of the players. If present, players can be referred to by name in ATL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_names: Option<Vec<String>>,
+    /// Optional names of the propositions. If present, propositions can be referred to by
+    /// name in ATL, e.g. "`goal`" or "`p1.alive`"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_names: Option<Vec<String>>,
+    /// Optional names of the states
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_names: Option<Vec<String>>,
+    /// Optional names of the actions, indexed by state, then player, then action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action_names: Option<Vec<Vec<Vec<String>>>>,
 }
 
 impl EagerGameStructure {
+    /// Deserializes an [EagerGameStructure] from JSON and checks that it is well-formed.
+    /// See [EagerGameStructure::validate].
+    pub fn from_json(json: &str) -> Result<EagerGameStructure, String> {
+        let game: EagerGameStructure = serde_json::from_str(json)
+            .map_err(|err| format!("Failed to deserialize game structure.\n{}", err))?;
+        game.validate()?;
+        Ok(game)
+    }
+
+    /// Checks that the game structure is well-formed, such that no lookups will panic.
+    /// That is, all states have labels, moves, and transitions, the transitions of each state
+    /// are nested once per player with one entry per move, all target states exist, and
+    /// the name tables have the right sizes. The error describes the JSON path of the problem.
+    pub fn validate(&self) -> Result<(), String> {
+        let state_count = self.transitions.len();
+        if self.labeling.len() != state_count {
+            return Err(format!(
+                "'labeling' has {} entries, but 'transitions' has {} states.",
+                self.labeling.len(),
+                state_count
+            ));
+        }
+        if self.moves.len() != state_count {
+            return Err(format!(
+                "'moves' has {} entries, but 'transitions' has {} states.",
+                self.moves.len(),
+                state_count
+            ));
+        }
+        if self.initial_state >= state_count {
+            return Err(format!(
+                "'initial_state' is {}, but there are only {} states.",
+                self.initial_state, state_count
+            ));
+        }
+
+        for (state, moves) in self.moves.iter().enumerate() {
+            if moves.len() != self.player_count {
+                return Err(format!(
+                    "'moves[{}]' has {} entries, but 'player_count' is {}.",
+                    state,
+                    moves.len(),
+                    self.player_count
+                ));
+            }
+            if let Some(player) = moves.iter().position(|&count| count == 0) {
+                return Err(format!(
+                    "'moves[{}][{}]' is 0, but every player must have at least one move.",
+                    state, player
+                ));
+            }
+            let mut path = format!("transitions[{}]", state);
+            validate_transitions(&self.transitions[state], moves, state_count, &mut path)?;
+        }
+
+        if let Some(names) = &self.player_names {
+            if names.len() != self.player_count {
+                return Err(format!(
+                    "'player_names' has {} entries, but 'player_count' is {}.",
+                    names.len(),
+                    self.player_count
+                ));
+            }
+        }
+        if let Some(names) = &self.state_names {
+            if names.len() != state_count {
+                return Err(format!(
+                    "'state_names' has {} entries, but there are {} states.",
+                    names.len(),
+                    state_count
+                ));
+            }
+        }
+        if let Some(names) = &self.action_names {
+            if names.len() != state_count {
+                return Err(format!(
+                    "'action_names' has {} entries, but there are {} states.",
+                    names.len(),
+                    state_count
+                ));
+            }
+            for (state, players) in names.iter().enumerate() {
+                if players.len() != self.player_count {
+                    return Err(format!(
+                        "'action_names[{}]' has {} entries, but 'player_count' is {}.",
+                        state,
+                        players.len(),
+                        self.player_count
+                    ));
+                }
+                for (player, actions) in players.iter().enumerate() {
+                    if actions.len() != self.moves[state][player] {
+                        return Err(format!(
+                            "'action_names[{}][{}]' has {} entries, but 'moves[{}][{}]' is {}.",
+                            state,
+                            player,
+                            actions.len(),
+                            state,
+                            player,
+                            self.moves[state][player]
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates an [EagerGameStructure] of the states reachable from the initial state of the
+    /// given game structure. States are numbered densely in breadth-first order, such that
+    /// the initial state is 0. The names of players, states, and actions are included, but
+    /// label names are not, since a game structure cannot list its labels.
+    pub fn from_game_structure<G: GameStructure>(game: &G, initial: State) -> EagerGameStructure {
+        let mut indexes = HashMap::new();
+        let mut states = vec![initial];
+        indexes.insert(initial, 0);
+
+        let mut labeling = vec![];
+        let mut transitions = vec![];
+        let mut moves = vec![];
+        let mut state_names = vec![];
+        let mut action_names = vec![];
+        // States are appended while they are visited, so this loop visits all reachable states
+        let mut next = 0;
+        while next < states.len() {
+            let state = states[next];
+            let move_count = game.move_count(state);
+            let mut choices = vec![];
+            let mut successor = |choices: &[usize]| {
+                let successor = game.transitions(state, choices.to_vec());
+                *indexes.entry(successor).or_insert_with(|| {
+                    states.push(successor);
+                    states.len() - 1
+                })
+            };
+            transitions.push(nest_transitions(&move_count, &mut choices, &mut successor));
+            labeling.push(game.labels(state));
+            state_names.push(game.state_name(state));
+            action_names.push(
+                move_count
+                    .iter()
+                    .enumerate()
+                    .map(|(player, &count)| {
+                        (0..count)
+                            .map(|action| game.action_name(state, player, action))
+                            .collect()
+                    })
+                    .collect(),
+            );
+            moves.push(move_count);
+            next += 1;
+        }
+
+        EagerGameStructure {
+            player_count: game.max_player(),
+            labeling,
+            transitions,
+            moves,
+            initial_state: 0,
+            player_names: Some(
+                (0..game.max_player())
+                    .map(|p| game.player_name(p))
+                    .collect(),
+            ),
+            label_names: None,
+            state_names: Some(state_names),
+            action_names: Some(action_names),
+        }
+    }
+
     /// Returns the number of moves `player` can take when the game is in `state`.
     pub fn available_moves(&self, state: State, player: Player) -> usize {
         *self
@@ -36,11 +226,101 @@ impl EagerGameStructure {
     }
 }
 
+/// Checks that the transitions of a state are nested once per player with one entry per move,
+/// and that all target states exist. `path` is the JSON path of `transitions`.
+fn validate_transitions(
+    transitions: &DynVec,
+    moves: &[usize],
+    state_count: usize,
+    path: &mut String,
+) -> Result<(), String> {
+    match (transitions, moves.split_first()) {
+        (DynVec::BASE(target), None) => {
+            if *target < state_count {
+                Ok(())
+            } else {
+                Err(format!(
+                    "'{}' is state {}, but there are only {} states.",
+                    path, target, state_count
+                ))
+            }
+        }
+        (DynVec::BASE(_), Some(_)) => Err(format!(
+            "'{}' is a state, but a list of the moves of {} more player(s) was expected.",
+            path,
+            moves.len()
+        )),
+        (DynVec::NEST(_), None) => Err(format!(
+            "'{}' is a list, but a state was expected, since all players have chosen a move.",
+            path
+        )),
+        (DynVec::NEST(nested), Some((&count, rest))) => {
+            if nested.len() != count {
+                return Err(format!(
+                    "'{}' has {} entries, but the number of moves is {}.",
+                    path,
+                    nested.len(),
+                    count
+                ));
+            }
+            let len = path.len();
+            for (choice, next) in nested.iter().enumerate() {
+                path.push_str(&format!("[{}]", choice));
+                validate_transitions(next, rest, state_count, path)?;
+                path.truncate(len);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Builds the nested [DynVec] of the transitions from a state, given the number of moves of
+/// each player and a function returning the successor of a combination of moves
+fn nest_transitions<F: FnMut(&[usize]) -> State>(
+    move_count: &[usize],
+    choices: &mut Vec<usize>,
+    successor: &mut F,
+) -> DynVec {
+    match move_count.get(choices.len()) {
+        None => DynVec::BASE(successor(choices)),
+        Some(&count) => {
+            let mut nested = Vec::with_capacity(count);
+            for choice in 0..count {
+                choices.push(choice);
+                nested.push(Arc::new(nest_transitions(move_count, choices, successor)));
+                choices.pop();
+            }
+            DynVec::NEST(nested)
+        }
+    }
+}
+
+/// Serializes the labels of each state in ascending order, such that the output is deterministic
+fn serialize_labeling<S: Serializer>(
+    labeling: &[HashSet<Proposition>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(labeling.iter().map(|labels| {
+        let mut labels: Vec<&Proposition> = labels.iter().collect();
+        labels.sort();
+        labels
+    }))
+}
+
+/// Returns the index of the given name in the optional list of names
+fn find_name(names: &Option<Vec<String>>, name: &str) -> Option<usize> {
+    names.as_ref()?.iter().position(|other| other == name)
+}
+
 impl GameStructure for EagerGameStructure {
     fn max_player(&self) -> usize {
         self.player_count
     }
 
+    fn initial_state(&self) -> State {
+        self.initial_state
+    }
+
     fn labels(&self, state: State) -> HashSet<Proposition, RandomState> {
         self.labeling
             .get(state)
@@ -66,37 +346,271 @@ impl GameStructure for EagerGameStructure {
     }
 
     fn state_name(&self, state: State) -> String {
-        state.to_string()
+        match self.state_names.as_ref().and_then(|names| names.get(state)) {
+            Some(name) => name.clone(),
+            None => state.to_string(),
+        }
     }
 
     fn label_name(&self, proposition: Proposition) -> String {
-        proposition.to_string()
+        // Propositions can be used in formulas even if they do not have a name
+        match self
+            .label_names
+            .as_ref()
+            .and_then(|names| names.get(proposition))
+        {
+            Some(name) => name.clone(),
+            None => proposition.to_string(),
+        }
     }
 
     fn player_name(&self, player: Player) -> String {
-        player.to_string()
+        match self
+            .player_names
+            .as_ref()
+            .and_then(|names| names.get(player))
+        {
+            Some(name) => name.clone(),
+            None => player.to_string(),
+        }
     }
 
-    fn action_name(&self, _state: usize, _player: usize, action: usize) -> String {
-        action.to_string()
+    fn action_name(&self, state: usize, player: usize, action: usize) -> String {
+        match self
+            .action_names
+            .as_ref()
+            .and_then(|names| names.get(state)?.get(player)?.get(action))
+        {
+            Some(name) => name.clone(),
+            None => action.to_string(),
+        }
     }
 }
 
 impl ATLExpressionParser for EagerGameStructure {
     fn player_parser(&self) -> Parser<u8, Player> {
-        // In ATL, players are just their index
-        number().convert(move |i| {
-            if i <= self.max_player() {
+        // In ATL, players are their index, or their name if the players have names
+        let index = number().convert(move |i| {
+            if i < self.max_player() {
                 Ok(i)
             } else {
                 Err(format!("Player index '{}' out of bounds.", i))
             }
-        })
+        });
+        let name = player_name().convert(move |name| {
+            find_name(&self.player_names, &name).ok_or_else(|| {
+                format!(
+                    "The game structure does not contain any player named '{}'.",
+                    name
+                )
+            })
+        });
+        index | name
     }
 
     fn proposition_parser(&self) -> Parser<u8, Proposition> {
-        // In ATL, proposition are just their index. All numbers are valid propositions,
-        // but they might not be true anywhere.
+        // In ATL, proposition are their index, or their name if the propositions have names.
+        // All numbers are valid propositions, but they might not be true anywhere. Names are
+        // written like labels in LCGS, e.g. "goal" or "p1.alive".
+        let name =
+            (player_name() + (sym(b'.') * identifier()).opt()).map(|(owner, name)| match name {
+                Some(name) => format!("{}.{}", owner, name),
+                None => owner,
+            });
         number()
+            | name.convert(move |name| {
+                find_name(&self.label_names, &name).ok_or_else(|| {
+                    format!(
+                        "The game structure does not contain any proposition named '{}'.",
+                        name
+                    )
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::atl::formula::parser::parse_phi;
+    use crate::atl::formula::Phi;
+    use crate::atl::gamestructure::{EagerGameStructure, GameStructure};
+    use crate::lcgs::ir::intermediate::{DeadlockPolicy, IntermediateLCGS};
+    use crate::lcgs::parse::parse_lcgs;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_from_game_structure_01() {
+        // The exported game has the same transitions and labels along all paths, and survives
+        // a round trip through JSON
+        let input = "
+        player p1 = shooter [target=p2];
+        player p2 = shooter [target=p1];
+        template shooter
+            health : [0 .. 2] init 2;
+            health' = max(health - target.shoot, 0);
+            label alive = health > 0;
+            [wait] 1;
+            [shoot] health > 0;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let eager = EagerGameStructure::from_game_structure(&lcgs, lcgs.initial_state_index());
+        let json = serde_json::to_string(&eager).unwrap();
+        let eager = EagerGameStructure::from_json(&json).unwrap();
+        assert_eq!(eager.player_count, 2);
+        assert_eq!(eager.moves.len(), 9);
+
+        let mut pairs = vec![(lcgs.initial_state_index(), 0)];
+        let mut visited = std::collections::HashSet::new();
+        while let Some((state, eager_state)) = pairs.pop() {
+            if !visited.insert(state) {
+                continue;
+            }
+            assert_eq!(lcgs.labels(state), eager.labels(eager_state));
+            assert_eq!(lcgs.move_count(state), eager.move_count(eager_state));
+            let moves = lcgs.move_count(state);
+            for a in 0..moves[0] {
+                for b in 0..moves[1] {
+                    pairs.push((
+                        lcgs.transitions(state, vec![a, b]),
+                        eager.transitions(eager_state, vec![a, b]),
+                    ));
+                }
+            }
+        }
+        assert_eq!(visited.len(), 9);
+    }
+
+    #[test]
+    fn test_from_game_structure_02() {
+        // Players without actions idle in the exported game, such that it can be loaded again.
+        // When deadlocks are allowed, the exported game is rejected.
+        let input = "
+        player p = counter;
+        template counter
+            x : [0 .. 3] init 0;
+            x' = inc ? x + 1 : x;
+            [inc] x < 2;
+        endtemplate
+        ";
+        let mut lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let eager = EagerGameStructure::from_game_structure(&lcgs, lcgs.initial_state_index());
+        let json = serde_json::to_string(&eager).unwrap();
+        let eager = EagerGameStructure::from_json(&json).unwrap();
+        assert_eq!(eager.moves, vec![vec![1], vec![1], vec![1]]);
+        assert_eq!(eager.transitions(2, vec![0]), 2);
+
+        lcgs.set_deadlock_policy(DeadlockPolicy::Allow);
+        let eager = EagerGameStructure::from_game_structure(&lcgs, lcgs.initial_state_index());
+        let json = serde_json::to_string(&eager).unwrap();
+        let err = EagerGameStructure::from_json(&json).unwrap_err();
+        assert!(err.contains("'moves[2][0]' is 0"), "{}", err);
+    }
+
+    #[test]
+    fn test_names_01() {
+        // Players and propositions can be referred to by name when the JSON contains names
+        let json = r#"{
+            "player_count": 2,
+            "labeling": [[], [0, 1]],
+            "transitions": [[[0, 1]], [[1]]],
+            "moves": [[1, 2], [1, 1]],
+            "initial_state": 1,
+            "player_names": ["alice", "bob"],
+            "label_names": ["goal", "bob.happy"],
+            "state_names": ["start", "end"]
+        }"#;
+        let eager: EagerGameStructure = serde_json::from_str(json).unwrap();
+        assert_eq!(eager.initial_state, 1);
+        assert_eq!(eager.state_name(0), "start");
+        assert_eq!(eager.action_name(0, 1, 1), "1");
+
+        let phi = parse_phi(&eager, "<<bob>> F goal").unwrap();
+        let expected = Phi::EnforceEventually {
+            players: vec![1],
+            formula: Arc::new(Phi::Proposition(0)),
+        };
+        assert_eq!(phi, expected);
+        assert_eq!(parse_phi(&eager, "<<1>> F 0").unwrap(), expected);
+        assert_eq!(
+            parse_phi(&eager, "<<alice>> G bob.happy").unwrap(),
+            Phi::EnforceInvariant {
+                players: vec![0],
+                formula: Arc::new(Phi::Proposition(1)),
+            }
+        );
+        assert!(parse_phi(&eager, "<<carol>> F goal").is_err());
+        assert!(parse_phi(&eager, "<<alice>> F alice.happy").is_err());
+
+        // Indexes out of range are rejected by the parser and shown as numbers otherwise
+        assert!(parse_phi(&eager, "<<2>> F 0").is_err());
+        assert_eq!(eager.player_name(2), "2");
+        assert_eq!(eager.state_name(2), "2");
+        assert_eq!(eager.action_name(2, 0, 0), "0");
+    }
+
+    #[test]
+    fn test_validate_01() {
+        let valid = r#"{
+            "player_count": 2,
+            "labeling": [[], [0]],
+            "transitions": [[[0, 1]], [[1]]],
+            "moves": [[1, 2], [1, 1]]
+        }"#;
+        assert!(EagerGameStructure::from_json(valid).is_ok());
+
+        // The nesting depth does not match the player count
+        let err = EagerGameStructure::from_json(&valid.replace("[[1]]]", "[1]]")).unwrap_err();
+        assert!(err.contains("'transitions[1][0]' is a state"), "{}", err);
+
+        // The number of entries does not match the number of moves
+        let err = EagerGameStructure::from_json(&valid.replace("[[0, 1]]", "[[0]]")).unwrap_err();
+        assert!(err.contains("'transitions[0][0]' has 1 entries"), "{}", err);
+
+        // The target state does not exist
+        let err =
+            EagerGameStructure::from_json(&valid.replace("[[0, 1]]", "[[0, 2]]")).unwrap_err();
+        assert!(err.contains("'transitions[0][0][1]' is state 2"), "{}", err);
+
+        // The moves do not match the player count
+        let err = EagerGameStructure::from_json(&valid.replace("[1, 1]]", "[1]]")).unwrap_err();
+        assert!(err.contains("'moves[1]'"), "{}", err);
+
+        // A player has no moves
+        let err = EagerGameStructure::from_json(&valid.replace("[1, 1]]", "[1, 0]]")).unwrap_err();
+        assert!(err.contains("'moves[1][1]' is 0"), "{}", err);
+
+        // The player names do not match the player count
+        let err = EagerGameStructure::from_json(&valid.replace(
+            "\"player_count\": 2,",
+            "\"player_count\": 2, \"player_names\": [\"a\"],",
+        ))
+        .unwrap_err();
+        assert!(err.contains("'player_names' has 1 entries"), "{}", err);
+
+        // The action names do not match the number of moves
+        let with_action_names = |action_names: &str| {
+            valid.replace(
+                "\"player_count\": 2,",
+                &format!("\"player_count\": 2, \"action_names\": {},", action_names),
+            )
+        };
+        let names = r#"[[["a"], ["b", "c"]], [["d"], ["e"]]]"#;
+        assert!(EagerGameStructure::from_json(&with_action_names(names)).is_ok());
+        let err = EagerGameStructure::from_json(&with_action_names(r#"[[["a"], ["b", "c"]]]"#))
+            .unwrap_err();
+        assert!(err.contains("'action_names' has 1 entries"), "{}", err);
+        let err = EagerGameStructure::from_json(&with_action_names(r#"[[["a"]], [["d"], ["e"]]]"#))
+            .unwrap_err();
+        assert!(err.contains("'action_names[0]' has 1 entries"), "{}", err);
+        let err = EagerGameStructure::from_json(&with_action_names(
+            r#"[[["a"], ["b"]], [["d"], ["e"]]]"#,
+        ))
+        .unwrap_err();
+        assert!(
+            err.contains("'action_names[0][1]' has 1 entries"),
+            "{}",
+            err
+        );
     }
 }
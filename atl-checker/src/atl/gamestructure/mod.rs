@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
 #[allow(unused_imports)]
 pub use eager::EagerGameStructure;
@@ -6,10 +6,20 @@ pub use eager::EagerGameStructure;
 use crate::atl::common::{Action, Player, Proposition, State};
 
 mod eager;
+pub mod statistics;
 
 pub trait GameStructure {
     fn max_player(&self) -> usize;
 
+    /// Returns the state where the game starts
+    fn initial_state(&self) -> State;
+
+    /// Returns all states where the game can start. Formulas are checked in each of them.
+    /// By default, this is just the initial state.
+    fn initial_states(&self) -> Vec<State> {
+        vec![self.initial_state()]
+    }
+
     fn labels(&self, state: State) -> HashSet<Proposition>;
 
     fn transitions(&self, state: State, choices: Vec<usize>) -> State;
@@ -29,3 +39,62 @@ pub trait GameStructure {
     /// Returns the human-readable name of the given action by the given player in the given state
     fn action_name(&self, state: State, player: Player, action: Action) -> String;
 }
+
+/// Returns the states reachable from the given initial states in breadth-first order,
+/// starting with the initial states
+pub fn reachable_states<G: GameStructure>(game: &G, initial: &[State]) -> Vec<State> {
+    let mut visited = HashSet::new();
+    let mut queue: VecDeque<State> = initial
+        .iter()
+        .cloned()
+        .filter(|state| visited.insert(*state))
+        .collect();
+    let mut states = vec![];
+    while let Some(state) = queue.pop_front() {
+        states.push(state);
+        for choices in MoveIterator::new(game.move_count(state)) {
+            let next = game.transitions(state, choices);
+            if visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    states
+}
+
+/// An iterator over all combinations of actions, given the number of actions of each player.
+/// There are no combinations if a player has no actions.
+pub(crate) struct MoveIterator {
+    move_count: Vec<usize>,
+    next: Option<Vec<usize>>,
+}
+
+impl MoveIterator {
+    pub fn new(move_count: Vec<usize>) -> MoveIterator {
+        let next = if move_count.contains(&0) {
+            None
+        } else {
+            Some(vec![0; move_count.len()])
+        };
+        MoveIterator { move_count, next }
+    }
+}
+
+impl Iterator for MoveIterator {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        // Count like an odometer, where each digit has its own base
+        let mut next = current.clone();
+        for (digit, base) in next.iter_mut().zip(&self.move_count) {
+            *digit += 1;
+            if *digit < *base {
+                self.next = Some(next);
+                break;
+            }
+            *digit = 0;
+        }
+        Some(current)
+    }
+}
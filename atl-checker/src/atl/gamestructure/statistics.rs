@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::atl::common::{Proposition, State};
+use crate::atl::gamestructure::{GameStructure, MoveIterator};
+
+/// Statistics of the states reachable from the initial states of a game structure
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Statistics {
+    /// The number of reachable states
+    pub states: usize,
+    /// The number of combinations of moves in all reachable states. Several combinations
+    /// can lead to the same successor.
+    pub transitions: usize,
+    /// The number of distinct pairs of a reachable state and one of its successors
+    pub edges: usize,
+    /// The length of the longest shortest path from an initial state to a reachable state
+    pub depth: usize,
+    /// The number of reachable states where some player has no moves
+    pub deadlocks: usize,
+    /// The number of moves of each player, indexed by player
+    pub moves: Vec<MoveStatistics>,
+    /// The number of reachable states with each label. Labels that never hold are absent.
+    pub labels: HashMap<Proposition, usize>,
+}
+
+/// The number of moves a player has in the reachable states
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MoveStatistics {
+    pub min: usize,
+    pub max: usize,
+    /// The sum over all reachable states
+    pub total: usize,
+}
+
+impl MoveStatistics {
+    /// The average number of moves per reachable state
+    pub fn average(&self, states: usize) -> f64 {
+        self.total as f64 / states as f64
+    }
+}
+
+impl Statistics {
+    /// Explores all states reachable from the given initial states in breadth-first order
+    pub fn explore<G: GameStructure>(game: &G, initial: &[State]) -> Statistics {
+        let mut stats = Statistics {
+            states: 0,
+            transitions: 0,
+            edges: 0,
+            depth: 0,
+            deadlocks: 0,
+            moves: vec![
+                MoveStatistics {
+                    min: usize::MAX,
+                    max: 0,
+                    total: 0,
+                };
+                game.max_player()
+            ],
+            labels: HashMap::new(),
+        };
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        for &state in initial {
+            if visited.insert(state) {
+                queue.push_back((state, 0));
+            }
+        }
+
+        while let Some((state, depth)) = queue.pop_front() {
+            stats.states += 1;
+            stats.depth = stats.depth.max(depth);
+            for label in game.labels(state) {
+                *stats.labels.entry(label).or_insert(0) += 1;
+            }
+
+            let move_count = game.move_count(state);
+            for (player, &count) in move_count.iter().enumerate() {
+                let moves = &mut stats.moves[player];
+                moves.min = moves.min.min(count);
+                moves.max = moves.max.max(count);
+                moves.total += count;
+            }
+            if move_count.contains(&0) {
+                stats.deadlocks += 1;
+            }
+
+            let mut successors = HashSet::new();
+            for choices in MoveIterator::new(move_count) {
+                stats.transitions += 1;
+                let next = game.transitions(state, choices);
+                if successors.insert(next) && visited.insert(next) {
+                    queue.push_back((next, depth + 1));
+                }
+            }
+            stats.edges += successors.len();
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::atl::gamestructure::statistics::{MoveStatistics, Statistics};
+    use crate::atl::gamestructure::GameStructure;
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::parse::parse_lcgs;
+
+    #[test]
+    fn test_statistics_01() {
+        // A counter that p1 can increment until 3, while p2 can only wait after it is 2
+        let input = "
+        x : [0 .. 3] init 0;
+        x' = min(x + p1.inc, 3);
+        label at_two = x == 2;
+        player p1 = one;
+        player p2 = two;
+        template one
+            [inc] 1;
+            [wait] 1;
+        endtemplate
+        template two
+            [wait] 1;
+            [act] x < 2;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let stats = Statistics::explore(&lcgs, &lcgs.initial_states());
+        assert_eq!(stats.states, 4);
+        assert_eq!(stats.transitions, 2 * 2 + 2 * 2 + 2 + 2);
+        assert_eq!(stats.edges, 2 + 2 + 2 + 1);
+        assert_eq!(stats.depth, 3);
+        assert_eq!(stats.deadlocks, 0);
+        assert_eq!(
+            stats.moves,
+            vec![
+                MoveStatistics {
+                    min: 2,
+                    max: 2,
+                    total: 8
+                },
+                MoveStatistics {
+                    min: 1,
+                    max: 2,
+                    total: 6
+                },
+            ]
+        );
+        assert_eq!(stats.labels.get(&0), Some(&1));
+    }
+}
@@ -52,11 +52,13 @@ pub struct ChannelBroker<V: Hash + Eq + PartialEq + Clone> {
 impl<V: Hash + Eq + PartialEq + Clone> Broker<V> for ChannelBroker<V> {
     fn send(&self, to: WorkerId, msg: Message<V>) {
         debug!("send");
-        self.workers
+        // Ignore send error, because the error means the worker have already terminated, which
+        // happens when another worker has found the result
+        let _ = self
+            .workers
             .get(to as usize)
             .expect("receiver id out of bounds")
-            .send(msg)
-            .expect(&*format!("Send to worker {} failed", to));
+            .send(msg);
     }
 
     fn return_result(&self, assignment: VertexAssignment) {
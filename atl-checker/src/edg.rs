@@ -40,6 +40,7 @@ pub fn distributed_certain_zero<
 
     let (mut brokers, manager_broker) = ChannelBroker::new(worker_count);
 
+    let mut handles = Vec::with_capacity(worker_count as usize);
     for i in (0..worker_count).rev() {
         let mut worker = Worker::new(
             i,
@@ -49,15 +50,19 @@ pub fn distributed_certain_zero<
             edg.clone(),
             ss_builder.build(),
         );
-        thread::spawn(move || {
+        handles.push(thread::spawn(move || {
             trace!("worker thread start");
             worker.run();
-        });
+        }));
     }
 
     let assignment = manager_broker
         .receive_result()
         .expect("Error receiving final assigment on termination");
+    // Wait for the workers to terminate, such that they do not take time from the caller
+    for handle in handles {
+        handle.join().expect("Worker thread panicked");
+    }
     trace!(v0_assignment = ?assignment, "Found assignment of v0");
     assignment
 }
@@ -0,0 +1,236 @@
+use crate::common::{Edge, VertexAssignment};
+use crate::edg::{ExtendedDependencyGraph, Vertex};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The global_fixed_point algorithm finds the minimum fixed-point assignment of every vertex
+/// reachable from the given roots. Contrary to distributed_certain_zero, which stops as soon
+/// as the assignment of a single root is known, all reachable vertices are explored. Vertices
+/// shared between the roots are only explored and assigned once, so this is much faster than
+/// running a local algorithm for each root, when the assignment of many roots is needed.
+/// The resulting assignments are either TRUE or FALSE.
+pub fn global_fixed_point<G: ExtendedDependencyGraph<V>, V: Vertex>(
+    edg: &G,
+    roots: &[V],
+) -> HashMap<V, VertexAssignment> {
+    let mut assignments = HashMap::new();
+    solve_component(edg, roots.to_vec(), &mut assignments);
+    assignments
+}
+
+/// Assigns all unassigned vertices reachable from the given vertices through hyper-edges.
+/// The targets of negation edges are assigned first using recursion, which terminates since
+/// EDGs are negation safe, i.e. no vertex can reach itself through a negation edge.
+fn solve_component<G: ExtendedDependencyGraph<V>, V: Vertex>(
+    edg: &G,
+    roots: Vec<V>,
+    assignments: &mut HashMap<V, VertexAssignment>,
+) {
+    // Explore the vertices of the component and give them ids
+    let mut vertices: Vec<V> = vec![];
+    let mut ids: HashMap<V, usize> = HashMap::new();
+    let mut edges: Vec<Vec<Edge<V>>> = vec![];
+    let mut negated = vec![];
+    let mut queue: VecDeque<V> = roots.into_iter().collect();
+    while let Some(vertex) = queue.pop_front() {
+        if assignments.contains_key(&vertex) || ids.contains_key(&vertex) {
+            continue;
+        }
+        let succ = edg.succ(&vertex);
+        for edge in &succ {
+            match edge {
+                Edge::HYPER(hyper) => queue.extend(hyper.targets.iter().cloned()),
+                Edge::NEGATION(negation) => negated.push(negation.target.clone()),
+            }
+        }
+        ids.insert(vertex.clone(), vertices.len());
+        vertices.push(vertex);
+        edges.push(succ);
+    }
+
+    if !negated.is_empty() {
+        solve_component(edg, negated, assignments);
+    }
+
+    // Find the minimum fixed-point. All vertices start as false. A vertex becomes true when all
+    // targets of one of its hyper-edges are true, or when the target of one of its negation
+    // edges is false. For each hyper-edge we count the targets that are not yet true, and each
+    // vertex knows the hyper-edges it is a target of.
+    let mut is_true = vec![false; vertices.len()];
+    let mut remaining: Vec<usize> = vec![];
+    let mut dependents: Vec<Vec<(usize, usize)>> = vec![vec![]; vertices.len()];
+    let mut became_true = vec![];
+    for (source, succ) in edges.iter().enumerate() {
+        for edge in succ {
+            let fires = match edge {
+                Edge::HYPER(hyper) => {
+                    // Targets outside this component were assigned earlier, e.g. if they
+                    // were reached from a negation edge too. A hyper-edge with a false target
+                    // can never fire.
+                    let targets: HashSet<&V> = hyper.targets.iter().collect();
+                    let possible = targets.iter().all(|target| {
+                        ids.contains_key(*target) || assignments[*target] == VertexAssignment::TRUE
+                    });
+                    if !possible {
+                        continue;
+                    }
+                    let edge_id = remaining.len();
+                    let mut count = 0;
+                    for target in targets {
+                        if let Some(&target) = ids.get(target) {
+                            count += 1;
+                            dependents[target].push((source, edge_id));
+                        }
+                    }
+                    remaining.push(count);
+                    count == 0
+                }
+                Edge::NEGATION(negation) => {
+                    assignments[&negation.target] == VertexAssignment::FALSE
+                }
+            };
+            if fires && !is_true[source] {
+                is_true[source] = true;
+                became_true.push(source);
+            }
+        }
+    }
+
+    while let Some(vertex) = became_true.pop() {
+        for &(source, edge_id) in &dependents[vertex] {
+            remaining[edge_id] -= 1;
+            if remaining[edge_id] == 0 && !is_true[source] {
+                is_true[source] = true;
+                became_true.push(source);
+            }
+        }
+    }
+
+    for (vertex, is_true) in vertices.into_iter().zip(is_true) {
+        let assignment = if is_true {
+            VertexAssignment::TRUE
+        } else {
+            VertexAssignment::FALSE
+        };
+        assignments.insert(vertex, assignment);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::VertexAssignment;
+    use crate::global::global_fixed_point;
+
+    /// Asserts the assignments found by the global algorithm from the given roots.
+    /// Meant to be used in conjunction with `simple_edg`.
+    macro_rules! assert_global {
+        ( roots=[$( $root:ident ),*], $( $v:ident => $assignment:ident, )* ) => {
+            let assignments = global_fixed_point(&SimpleEDG, &[$( SimpleVertex::$root ),*]);
+            $( assert_eq!(
+                assignments.get(&SimpleVertex::$v),
+                Some(&VertexAssignment::$assignment),
+                stringify!($v)
+            ); )*
+        };
+    }
+
+    #[test]
+    fn test_global_basic_01() {
+        simple_edg![
+            A => -> {B, C} -> {D};
+            B => ;
+            C => .> D;
+            D => -> {};
+        ];
+        assert_global!(
+            roots=[A],
+            A => TRUE,
+            B => FALSE,
+            C => FALSE,
+            D => TRUE,
+        );
+    }
+
+    #[test]
+    fn test_global_cycle_01() {
+        // Cycles without a way out are false
+        simple_edg![
+            A => -> {B};
+            B => -> {C} -> {A};
+            C => -> {B, D};
+            D => -> {A};
+        ];
+        assert_global!(
+            roots=[A],
+            A => FALSE,
+            B => FALSE,
+            C => FALSE,
+            D => FALSE,
+        );
+    }
+
+    #[test]
+    fn test_global_negation_01() {
+        simple_edg![
+            A => .> B;
+            B => -> {C};
+            C => -> {B} .> D;
+            D => -> {E};
+            E => -> {D};
+        ];
+        assert_global!(
+            roots=[A],
+            A => FALSE,
+            B => TRUE,
+            C => TRUE,
+            D => FALSE,
+            E => FALSE,
+        );
+    }
+
+    #[test]
+    fn test_global_negation_02() {
+        simple_edg![
+            A => .> B;
+            B => .> C;
+            C => .> D;
+            D => .> E;
+            E => .> F;
+            F => -> {F};
+        ];
+        assert_global!(
+            roots=[A],
+            A => TRUE,
+            B => FALSE,
+            C => TRUE,
+            D => FALSE,
+            E => TRUE,
+            F => FALSE,
+        );
+    }
+
+    #[test]
+    fn test_global_multiple_roots_01() {
+        // Roots can share vertices, and negation targets can be reachable from hyper-edges too
+        simple_edg![
+            A => .> B .> E;
+            B => -> {C};
+            C => -> {D};
+            D => .> E;
+            E => -> {F};
+            F => -> {G};
+            G => -> {};
+            H => -> {E, C} -> {A};
+        ];
+        assert_global!(
+            roots=[H, D, A],
+            A => TRUE,
+            B => FALSE,
+            C => FALSE,
+            D => FALSE,
+            E => TRUE,
+            F => TRUE,
+            G => TRUE,
+            H => TRUE,
+        );
+    }
+}
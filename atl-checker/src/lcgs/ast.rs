@@ -1,6 +1,7 @@
 use core::fmt;
+use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
-use std::ops::{Add, Div, Mul, RangeInclusive, Sub};
+use std::ops::{Add, Mul, RangeInclusive, Sub};
 
 use crate::lcgs::ast::BinaryOpKind::*;
 use crate::lcgs::ir::symbol_table::Owner;
@@ -11,14 +12,85 @@ pub struct Root {
     /// Declarations in the global scope. The parser ensures that only
     /// the allowed declaration types are present (e.g. not transitions).
     pub decls: Vec<Decl>,
+    /// Imports of other LCGS files. They must be resolved using the
+    /// [loader](crate::lcgs::loader) before an intermediate representation can be created.
+    pub imports: Vec<ImportDecl>,
 }
 
-/// A declaration
+impl Root {
+    /// Replaces the definition of the global constant of the given name with the given value.
+    /// This must happen before the program is turned into an intermediate representation,
+    /// since constants are inlined in that process. Returns an error if no such constant
+    /// is declared.
+    pub fn override_const(&mut self, name: &str, value: i32) -> Result<(), String> {
+        for decl in &mut self.decls {
+            if decl.kind.ident().name() != name {
+                continue;
+            }
+            return if let DeclKind::Const(con) = &mut decl.kind {
+                con.definition = Expr {
+                    span: con.definition.span,
+                    kind: ExprKind::Number(value),
+                };
+                Ok(())
+            } else {
+                Err(format!(
+                    "Cannot override '{}', since it is not a constant.",
+                    name
+                ))
+            };
+        }
+        Err(format!(
+            "Cannot override the constant '{}', since it is not declared.",
+            name
+        ))
+    }
+}
+
+/// An import of another LCGS file, e.g. "`import "common.lcgs";`". The declarations of the
+/// imported file are included at the position of the import.
 #[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ImportDecl {
+    /// The path of the imported file, relative to the importing file
+    pub path: String,
+    /// The number of declarations before the import in the importing file
+    pub position: usize,
+    pub span: Span,
+}
+
+/// A `Span` describes the position of a slice of text in the original program.
+/// Usually used to describe what text an AST node was created from.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default, Hash)]
+pub struct Span {
+    pub begin: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the smallest span that covers both spans
+    pub fn merge(self, other: Span) -> Span {
+        Span {
+            begin: self.begin.min(other.begin),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// A declaration
+#[derive(Debug, Eq, Clone)]
 pub struct Decl {
+    pub span: Span,
     pub kind: DeclKind,
 }
 
+impl PartialEq for Decl {
+    /// Declarations are equal if they have the same structure, regardless of where
+    /// in the program they are
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
 /// Every kind of declaration
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum DeclKind {
@@ -28,6 +100,7 @@ pub enum DeclKind {
     Player(Box<PlayerDecl>),
     Template(Box<TemplateDecl>),
     Transition(Box<TransitionDecl>),
+    Define(Box<DefineDecl>),
 }
 
 impl DeclKind {
@@ -41,6 +114,7 @@ impl DeclKind {
             DeclKind::Player(decl) => &decl.name,
             DeclKind::Template(decl) => &decl.name,
             DeclKind::Transition(decl) => &decl.name,
+            DeclKind::Define(decl) => &decl.name,
         }
     }
 }
@@ -95,6 +169,17 @@ pub struct PlayerDecl {
     pub name: Identifier,
     pub template: Identifier,
     pub relabeling: Relabeling,
+    /// Present if the declaration declares a player for each value of an index.
+    pub repeat: Option<PlayerLoop>,
+}
+
+/// Declares a player for each value of an index in a range. The players are named
+/// "`p[0]`", "`p[1]`", etc. and the index can be used in the relabeling.
+/// E.g. "`[i in 0 .. n - 1]`" in "`player p[i in 0 .. n - 1] = shooter [target1=p[(i + 1) % n]]`"
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PlayerLoop {
+    pub index_name: String,
+    pub range: TypeRange,
 }
 
 /// A list of relabeling cases. Relabeling means replacing name with another name or a
@@ -140,13 +225,100 @@ pub struct TemplateDecl {
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct StateVarDecl {
     pub name: Identifier,
+    pub var_type: VarType,
+    /// For booleans and enums the range is implied by the type.
     pub range: TypeRange,
     /// The range is evaluated during symbol checking. Its value has no meaning before that.
     pub ir_range: RangeInclusive<i32>,
     pub initial_value: Expr,
+    /// Present if the variable has a range of initial values, e.g. "`init 1 .. 3`", in which
+    /// case `initial_value` is the smallest initial value and this is the largest.
+    pub initial_max: Option<Expr>,
     /// The initial value is evaluated during symbol checking. Its value has no meaning before that.
     pub ir_initial_value: i32,
+    /// The largest initial value, evaluated during symbol checking. It is equal to
+    /// `ir_initial_value`, unless the variable has a range of initial values.
+    pub ir_initial_max: i32,
+    /// What happens when the update expression evaluates to a value outside the range
+    pub range_policy: RangePolicy,
     pub next_value: Expr,
+    /// Present if the variable is an array. Arrays are expanded to one variable per element
+    /// named "`board[0]`", "`board[1]`", etc. when registered.
+    pub array: Option<ArrayDecl>,
+}
+
+/// Determines how a state variable handles updates to values outside its range, e.g.
+/// "`clamp`" in "`health : [0 .. 3] init 3 overflow clamp`".
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum RangePolicy {
+    /// Out-of-range updates are errors. They are detected by
+    /// [IntermediateLCGS::check_ranges](crate::lcgs::ir::intermediate::IntermediateLCGS::check_ranges).
+    #[default]
+    Error,
+    /// Out-of-range updates are replaced by the nearest value in the range
+    Clamp,
+    /// Out-of-range updates wrap around, such that the value after the max is the min
+    Wrap,
+}
+
+impl RangePolicy {
+    /// Applies the policy to the given value. Values out of range are returned as is,
+    /// if the policy is [RangePolicy::Error].
+    pub fn apply(&self, value: i32, range: &RangeInclusive<i32>) -> i32 {
+        match self {
+            RangePolicy::Error => value,
+            RangePolicy::Clamp => int_clamp(value, *range.start(), *range.end()),
+            RangePolicy::Wrap => {
+                let size = *range.end() as i64 - *range.start() as i64 + 1;
+                (*range.start() as i64 + (value as i64 - *range.start() as i64).rem_euclid(size))
+                    as i32
+            }
+        }
+    }
+}
+
+/// A macro, e.g. "`define alive(p) = p.health > 0`". Calls of the macro are expanded during
+/// symbol checking by replacing the parameters in the body with the arguments of the call,
+/// like relabeling of templates. The expanded body is checked in the scope of the call.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DefineDecl {
+    pub name: Identifier,
+    pub params: Vec<String>,
+    pub body: Expr,
+}
+
+/// The size of an array variable and the name of its index in the update expression.
+/// E.g. "`[9]`" and "`c`" in "`board : [0..2][9] init 0; board[c]' = board[c]`"
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ArrayDecl {
+    pub size: Expr,
+    /// The name that refers to the index of the element being updated
+    pub index_name: String,
+}
+
+/// The type of a state variable. All variables are integers internally. Booleans have
+/// the range `[0 .. 1]` and the variants of an enum are numbered from 0 in the order
+/// they are declared. E.g. "`flag : bool`" or "`phase : {idle, trying, critical}`"
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum VarType {
+    Int,
+    Bool,
+    /// The names of the enum's variants
+    Enum(Vec<String>),
+}
+
+impl VarType {
+    /// Returns the symbolic name of the given value of this type, if it has one.
+    pub fn value_name(&self, value: i32) -> Option<String> {
+        match self {
+            VarType::Int => None,
+            VarType::Bool => Some((value != 0).to_string()),
+            VarType::Enum(variants) => usize::try_from(value)
+                .ok()
+                .and_then(|i| variants.get(i))
+                .cloned(),
+        }
+    }
 }
 
 /// A range for state variables.
@@ -164,24 +336,71 @@ pub struct TypeRange {
 pub struct TransitionDecl {
     pub name: Identifier,
     pub condition: Expr,
+    /// Updates of variables performed when the action is taken, e.g. "`choice' = 1`" in
+    /// "`[one] q0 -> choice' = 1`". They are desugared into the update expressions of the
+    /// variables when the intermediate representation is created.
+    pub updates: Vec<UpdateDecl>,
+}
+
+/// An update of a variable performed by an action. See [TransitionDecl].
+#[derive(Debug, Eq, Clone)]
+pub struct UpdateDecl {
+    /// The updated variable. It may be owned by another player, e.g. "`enemy.health`".
+    pub target: Identifier,
+    pub value: Expr,
+    pub span: Span,
+}
+
+impl PartialEq for UpdateDecl {
+    /// Updates are equal if they have the same structure, regardless of where
+    /// in the program they are
+    fn eq(&self, other: &Self) -> bool {
+        self.target == other.target && self.value == other.value
+    }
 }
 
 /// An expression. Expressions are always of type integer.
-#[derive(Debug, Eq, PartialEq, Clone)]
+///
+/// Integer division and modulo by zero are reported as errors when the divisor is known
+/// during symbol checking. If the divisor is only known during evaluation, the result is 0,
+/// and such divisions are found by
+/// [check_division_by_zero](crate::lcgs::ir::intermediate::IntermediateLCGS::check_division_by_zero).
+#[derive(Debug, Eq, Clone)]
 pub struct Expr {
+    pub span: Span,
     pub kind: ExprKind,
 }
 
+impl PartialEq for Expr {
+    /// Expressions are equal if they have the same structure, regardless of where
+    /// in the program they are
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
 /// Every kind of expression
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum ExprKind {
     Number(i32),
     OwnedIdent(Box<Identifier>),
+    /// An element of an array variable, e.g. "`board[i + 1]`". Indexes are resolved to
+    /// the variables of the elements during symbol checking.
+    Index(Box<Identifier>, Box<Expr>),
     UnaryOp(UnaryOpKind, Box<Expr>),
     BinaryOp(BinaryOpKind, Box<Expr>, Box<Expr>),
     TernaryIf(Box<Expr>, Box<Expr>, Box<Expr>),
     Min(Vec<Expr>),
     Max(Vec<Expr>),
+    /// Absolute value, e.g. "`abs(x)`"
+    Abs(Box<Expr>),
+    /// Integer power, e.g. "`pow(2, x)`". See [int_pow].
+    Pow(Box<Expr>, Box<Expr>),
+    /// Restricts a value to a range, e.g. "`clamp(x, 0, 5)`". See [int_clamp].
+    Clamp(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// A call of a macro, e.g. "`alive(p1)`". Calls are expanded during symbol checking.
+    /// See [DefineDecl].
+    Call(Box<Identifier>, Vec<Expr>),
 }
 
 /// Unary operators
@@ -198,6 +417,14 @@ impl UnaryOpKind {
             UnaryOpKind::Negation => |e| -e,
         }
     }
+
+    /// Applies the operator, returning `None` if the result overflows
+    pub fn checked_apply(&self, e: i32) -> Option<i32> {
+        match self {
+            UnaryOpKind::Negation => e.checked_neg(),
+            _ => Some(self.as_fn()(e)),
+        }
+    }
 }
 
 /// Binary operators
@@ -207,6 +434,7 @@ pub enum BinaryOpKind {
     Multiplication,
     Subtraction,
     Division,
+    Modulo,   // Euclidean, so the result is never negative
     Equality, // Also serves as bi-implication
     Inequality,
     GreaterThan,
@@ -225,7 +453,8 @@ impl BinaryOpKind {
             BinaryOpKind::Addition => i32::add,
             BinaryOpKind::Multiplication => i32::mul,
             BinaryOpKind::Subtraction => i32::sub,
-            BinaryOpKind::Division => i32::div,
+            BinaryOpKind::Division => |e1, e2| e1.checked_div(e2).unwrap_or(0),
+            BinaryOpKind::Modulo => |e1, e2| e1.checked_rem_euclid(e2).unwrap_or(0),
             BinaryOpKind::Equality => |e1, e2| (e1 == e2) as i32,
             BinaryOpKind::Inequality => |e1, e2| (e1 != e2) as i32,
             BinaryOpKind::GreaterThan => |e1, e2| (e1 > e2) as i32,
@@ -238,6 +467,43 @@ impl BinaryOpKind {
             BinaryOpKind::Implication => |e1, e2| (e1 == 0 || e2 != 0) as i32,
         }
     }
+
+    /// Applies the operator, returning `None` if the result overflows
+    pub fn checked_apply(&self, e1: i32, e2: i32) -> Option<i32> {
+        match self {
+            BinaryOpKind::Addition => e1.checked_add(e2),
+            BinaryOpKind::Multiplication => e1.checked_mul(e2),
+            BinaryOpKind::Subtraction => e1.checked_sub(e2),
+            _ => Some(self.as_fn()(e1, e2)),
+        }
+    }
+
+    /// Returns true if the operator is undefined when the right hand side is zero
+    pub fn divides(&self) -> bool {
+        matches!(self, BinaryOpKind::Division | BinaryOpKind::Modulo)
+    }
+}
+
+/// Integer power. A negative exponent `-e` is treated as `1 / pow(base, e)` with integer
+/// division, i.e. the result is 0 unless the base is 1 or -1. Results that do not fit in
+/// an `i32` are saturated.
+pub fn int_pow(base: i32, exp: i32) -> i32 {
+    if exp >= 0 {
+        base.saturating_pow(exp as u32)
+    } else {
+        match base {
+            1 => 1,
+            -1 if exp % 2 == 0 => 1,
+            -1 => -1,
+            _ => 0,
+        }
+    }
+}
+
+/// Restricts `value` to the range `[lo, hi]`. If `lo` is greater than `hi`, the upper
+/// bound takes priority and `hi` is returned.
+pub fn int_clamp(value: i32, lo: i32, hi: i32) -> i32 {
+    value.max(lo).min(hi)
 }
 
 impl From<&[u8]> for BinaryOpKind {
@@ -247,6 +513,7 @@ impl From<&[u8]> for BinaryOpKind {
             b"*" => Multiplication,
             b"-" => Subtraction,
             b"/" => Division,
+            b"%" => Modulo,
             b"==" => Equality,
             b"!=" => Inequality,
             b">" => GreaterThan,
@@ -275,6 +542,7 @@ impl Display for BinaryOpKind {
                 Multiplication => "*",
                 Subtraction => "-",
                 Division => "/",
+                Modulo => "%",
                 Equality => "==",
                 Inequality => "!=",
                 GreaterThan => ">",
@@ -0,0 +1,240 @@
+use crate::lcgs::ast::Span;
+
+/// Returns the line and column number (both starting from 1) of the given byte position
+/// in the source code.
+pub fn line_col(source: &str, position: usize) -> (usize, usize) {
+    let position = position.min(source.len());
+    let before = &source[..position];
+    let line = 1 + before.matches('\n').count();
+    let line_begin = before.rfind('\n').map_or(0, |i| i + 1);
+    let col = 1 + before[line_begin..].chars().count();
+    (line, col)
+}
+
+/// Renders an error message in a style similar to rustc. The message is followed by the file,
+/// line, and column of the span, the offending source line, and a caret underline of the span.
+/// Spans covering multiple lines are underlined to the end of the first line. An empty span
+/// means that the location is unknown, in which case only the message is rendered.
+/// ```text
+/// error: Unknown identifier 'foo', neither declared in player's scope or globally
+///  --> model.lcgs:3:11
+///   |
+/// 3 | label a = foo > 2;
+///   |           ^^^
+/// ```
+pub fn render(file_name: &str, source: &str, span: Span, msg: &str) -> String {
+    render_labeled("error", file_name, source, span, msg)
+}
+
+/// Renders a warning in the same style as errors. See [render].
+pub fn render_warning(file_name: &str, source: &str, span: Span, msg: &str) -> String {
+    render_labeled("warning", file_name, source, span, msg)
+}
+
+fn render_labeled(label: &str, file_name: &str, source: &str, span: Span, msg: &str) -> String {
+    if span.begin >= span.end || span.begin >= source.len() {
+        return format!("{}: {}\n --> {}\n", label, msg, file_name);
+    }
+
+    let (line, col) = line_col(source, span.begin);
+    let line_begin = source[..span.begin].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[span.begin..]
+        .find('\n')
+        .map_or(source.len(), |i| span.begin + i);
+    let source_line = source[line_begin..line_end].trim_end_matches('\r');
+
+    // Tabs are kept in the underline, so the carets line up with the source line
+    let padding: String = source[line_begin..span.begin]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underline_len = source[span.begin..span.end.min(line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{label}: {msg}\n{gutter}--> {file}:{line}:{col}\n{gutter} |\n{line} | {src}\n{gutter} | {pad}{carets}\n",
+        label = label,
+        msg = msg,
+        gutter = gutter,
+        file = file_name,
+        line = line,
+        col = col,
+        src = source_line,
+        pad = padding,
+        carets = "^".repeat(underline_len),
+    )
+}
+
+/// Renders an error from parsing an LCGS program. See [render].
+pub fn render_parse_error(file_name: &str, source: &str, err: &pom::Error) -> String {
+    let (msg, position) = parse_error_location(err, source);
+    render(file_name, source, point(source, position), msg)
+}
+
+/// Returns the message and position of an error from parsing the given source code
+fn parse_error_location<'a>(err: &'a pom::Error, source: &str) -> (&'a str, usize) {
+    match err {
+        pom::Error::Custom {
+            message, position, ..
+        }
+        | pom::Error::Expect {
+            message, position, ..
+        } => (message, *position),
+        pom::Error::Mismatch { message, position }
+        | pom::Error::Conversion { message, position } => (message, *position),
+        pom::Error::Incomplete => ("Unexpected end of program", source.len().saturating_sub(1)),
+    }
+}
+
+/// The source code of an LCGS program, which may consist of several files due to imports.
+/// The files are concatenated, such that spans of the program refer to positions in the
+/// concatenation. A [SourceMap] maps such spans back to the file they originate from, which
+/// allows diagnostics to name the right file.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    text: String,
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    name: String,
+    begin: usize,
+    end: usize,
+}
+
+impl SourceMap {
+    /// Creates a source map of a program consisting of a single file
+    pub fn single(file_name: &str, source: &str) -> SourceMap {
+        let mut map = SourceMap::default();
+        map.add_file(file_name, source);
+        map
+    }
+
+    /// Appends the given file to the source map and returns the position at which it begins
+    pub fn add_file(&mut self, file_name: &str, source: &str) -> usize {
+        if !self.text.is_empty() {
+            // Separates the files, such that no span touches two files
+            self.text.push('\n');
+        }
+        let begin = self.text.len();
+        self.text.push_str(source);
+        self.files.push(SourceFile {
+            name: file_name.to_string(),
+            begin,
+            end: self.text.len(),
+        });
+        begin
+    }
+
+    /// Returns the concatenation of all files
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Renders an error message with the location of the span. See [render].
+    pub fn render(&self, span: Span, msg: &str) -> String {
+        self.render_labeled("error", span, msg)
+    }
+
+    /// Renders a warning with the location of the span. See [render].
+    pub fn render_warning(&self, span: Span, msg: &str) -> String {
+        self.render_labeled("warning", span, msg)
+    }
+
+    /// Renders an error from parsing one of the files. See [render_parse_error].
+    pub fn render_parse_error(&self, err: &pom::Error) -> String {
+        let (msg, position) = parse_error_location(err, &self.text);
+        self.render(point(&self.text, position), msg)
+    }
+
+    fn render_labeled(&self, label: &str, span: Span, msg: &str) -> String {
+        let file = self.file_at(span.begin);
+        let local = Span {
+            begin: span.begin.saturating_sub(file.begin),
+            end: span.end.min(file.end).saturating_sub(file.begin),
+        };
+        render_labeled(
+            label,
+            &file.name,
+            &self.text[file.begin..file.end],
+            local,
+            msg,
+        )
+    }
+
+    /// Returns the file containing the given position. Positions outside all files belong to
+    /// the first file, which is the file of the main program.
+    fn file_at(&self, position: usize) -> &SourceFile {
+        self.files
+            .iter()
+            .find(|file| file.begin <= position && position < file.end)
+            .or_else(|| self.files.first())
+            .expect("Source map has no files")
+    }
+}
+
+/// Returns a span of the single character at the given byte position
+fn point(source: &str, position: usize) -> Span {
+    let len = source[position.min(source.len())..]
+        .chars()
+        .next()
+        .map_or(1, char::len_utf8);
+    Span {
+        begin: position,
+        end: position + len,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lcgs::ast::Span;
+    use crate::lcgs::diagnostics::{line_col, render, SourceMap};
+
+    #[test]
+    fn test_line_col_01() {
+        let source = "const a = 1;\nlabel b = a;\n";
+        assert_eq!(line_col(source, 0), (1, 1));
+        assert_eq!(line_col(source, 6), (1, 7));
+        assert_eq!(line_col(source, 13), (2, 1));
+        assert_eq!(line_col(source, 23), (2, 11));
+    }
+
+    #[test]
+    fn test_render_01() {
+        let source = "const a = 1;\nlabel b = foo > 2;\n";
+        let span = Span { begin: 23, end: 26 };
+        let expected = "error: Unknown identifier 'foo'\n --> model.lcgs:2:11\n  |\n2 | label b = foo > 2;\n  |           ^^^\n";
+        assert_eq!(
+            render("model.lcgs", source, span, "Unknown identifier 'foo'"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_render_02() {
+        // Spans across multiple lines are underlined to the end of the first line
+        let source = "\tx : [0 .. 2]\n\t  init 0;";
+        let span = Span { begin: 5, end: 20 };
+        let expected = "error: msg\n --> m:1:6\n  |\n1 | \tx : [0 .. 2]\n  | \t    ^^^^^^^^\n";
+        assert_eq!(render("m", source, span, "msg"), expected);
+    }
+
+    #[test]
+    fn test_source_map_01() {
+        let mut map = SourceMap::single("main.lcgs", "const a = 1;\n");
+        let begin = map.add_file("common.lcgs", "label b = foo;");
+        assert_eq!(begin, 14);
+        assert_eq!(map.text(), "const a = 1;\n\nlabel b = foo;");
+        let span = Span {
+            begin: begin + 10,
+            end: begin + 13,
+        };
+        let expected =
+            "error: msg\n --> common.lcgs:1:11\n  |\n1 | label b = foo;\n  |           ^^^\n";
+        assert_eq!(map.render(span, "msg"), expected);
+    }
+}
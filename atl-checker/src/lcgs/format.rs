@@ -0,0 +1,414 @@
+use std::mem::discriminant;
+
+use crate::lcgs::ast::*;
+use crate::lcgs::ir::symbol_table::Owner;
+use crate::lcgs::precedence::{precedence, Associativity, Precedence};
+
+/// The indentation of declarations in templates
+const INDENT: &str = "    ";
+
+/// Formats the LCGS program in a canonical way. The result parses to a program equivalent to
+/// the given one, where parentheses are only used where required by the precedence of the
+/// operators. Comments are not part of the AST and are therefore not preserved.
+pub fn format_lcgs(root: &Root) -> String {
+    let mut out = String::new();
+    let mut imports = root.imports.iter().peekable();
+    let mut prev: Option<&DeclKind> = None;
+    for (i, decl) in root.decls.iter().enumerate() {
+        let mut imported = false;
+        while let Some(import) = imports.next_if(|import| import.position == i) {
+            out.push_str(&format!("import \"{}\";\n", import.path));
+            imported = true;
+        }
+        if imported || prev.is_some_and(|prev| separate(prev, &decl.kind)) {
+            out.push('\n');
+        }
+        out.push_str(&format_decl(decl));
+        out.push('\n');
+        prev = Some(&decl.kind);
+    }
+    if imports.peek().is_some() && !root.decls.is_empty() {
+        out.push('\n');
+    }
+    for import in imports {
+        out.push_str(&format!("import \"{}\";\n", import.path));
+    }
+    out
+}
+
+/// Returns true if consecutive declarations should be separated by a blank line. Declarations
+/// of the same kind are grouped, while templates are always separated from other declarations.
+pub(crate) fn separate(prev: &DeclKind, next: &DeclKind) -> bool {
+    matches!(prev, DeclKind::Template(_))
+        || matches!(next, DeclKind::Template(_))
+        || discriminant(prev) != discriminant(next)
+}
+
+/// Formats a single declaration including its terminating semicolon. The update of a state
+/// variable is placed on its own line and the declarations of a template are indented.
+pub fn format_decl(decl: &Decl) -> String {
+    match &decl.kind {
+        DeclKind::Const(con) => format!(
+            "const {} = {};",
+            con.name.name(),
+            format_expr(&con.definition)
+        ),
+        DeclKind::Label(label) => format!(
+            "label {} = {};",
+            label.name.name(),
+            format_expr(&label.condition)
+        ),
+        DeclKind::StateVar(var) => format_state_var(var),
+        DeclKind::Player(player) => format_player(player),
+        DeclKind::Template(template) => {
+            let mut out = format!("template {}\n", template.name.name());
+            let mut prev: Option<&DeclKind> = None;
+            for decl in &template.decls {
+                if prev.is_some_and(|prev| separate(prev, &decl.kind)) {
+                    out.push('\n');
+                }
+                for line in format_decl(decl).lines() {
+                    out.push_str(INDENT);
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                prev = Some(&decl.kind);
+            }
+            out.push_str("endtemplate");
+            out
+        }
+        DeclKind::Transition(tran) => format_transition(tran),
+        DeclKind::Define(define) => format!(
+            "define {}({}) = {};",
+            define.name.name(),
+            define.params.join(", "),
+            format_expr(&define.body)
+        ),
+    }
+}
+
+fn format_state_var(var: &StateVarDecl) -> String {
+    let name = var.name.name();
+    let var_type = match &var.var_type {
+        VarType::Int => format_range(&var.range),
+        VarType::Bool => "bool".to_string(),
+        VarType::Enum(variants) => format!("{{{}}}", variants.join(", ")),
+    };
+    let mut out = format!("{} : {}", name, var_type);
+    if let Some(array) = &var.array {
+        out.push_str(&format!("[{}]", format_expr(&array.size)));
+    }
+    // Literals are indistinguishable from booleans in the AST, but the type tells us
+    let init = |value: &Expr| match (&var.var_type, &value.kind) {
+        (VarType::Bool, ExprKind::Number(0)) => "false".to_string(),
+        (VarType::Bool, ExprKind::Number(1)) => "true".to_string(),
+        _ => format_expr(value),
+    };
+    out.push_str(&format!(" init {}", init(&var.initial_value)));
+    if let Some(max) = &var.initial_max {
+        out.push_str(&format!(" .. {}", init(max)));
+    }
+    match var.range_policy {
+        RangePolicy::Error => {}
+        RangePolicy::Clamp => out.push_str(" overflow clamp"),
+        RangePolicy::Wrap => out.push_str(" overflow wrap"),
+    }
+    out.push(';');
+
+    // The update is omitted if the variable keeps its value, which is what the parser assumes
+    // when the update is omitted
+    let unchanged = ExprKind::OwnedIdent(Box::new(Identifier::OptionalOwner {
+        owner: None,
+        name: name.to_string(),
+    }));
+    match &var.array {
+        Some(array) => out.push_str(&format!(
+            "\n{}[{}]' = {};",
+            name,
+            array.index_name,
+            format_expr(&var.next_value)
+        )),
+        None if var.next_value.kind == unchanged => {}
+        None => out.push_str(&format!("\n{}' = {};", name, format_expr(&var.next_value))),
+    }
+    out
+}
+
+fn format_player(player: &PlayerDecl) -> String {
+    let mut out = format!("player {}", player.name.name());
+    if let Some(repeat) = &player.repeat {
+        out.push_str(&format!(
+            "[{} in {} .. {}]",
+            repeat.index_name,
+            format_expr(&repeat.range.min),
+            format_expr(&repeat.range.max)
+        ));
+    }
+    out.push_str(&format!(" = {}", player.template.name()));
+    if !player.relabeling.relabellings.is_empty() {
+        let cases: Vec<String> = player
+            .relabeling
+            .relabellings
+            .iter()
+            .map(|case| format!("{}={}", case.prev, format_expr(&case.new)))
+            .collect();
+        out.push_str(&format!(" [{}]", cases.join(", ")));
+    }
+    out.push(';');
+    out
+}
+
+fn format_transition(tran: &TransitionDecl) -> String {
+    let name = tran.name.name();
+    if tran.updates.is_empty() {
+        return format!("[{}] {};", name, format_expr(&tran.condition));
+    }
+    let updates: Vec<String> = tran
+        .updates
+        .iter()
+        .map(|update| {
+            format!(
+                "{}' = {}",
+                format_ident(&update.target),
+                format_expr(&update.value)
+            )
+        })
+        .collect();
+    match &tran.condition.kind {
+        // A guard that is always true can be omitted
+        ExprKind::Number(1) => format!("[{}] {};", name, updates.join(" & ")),
+        // A ternary guard would otherwise swallow the target of the first update
+        ExprKind::TernaryIf(..) => format!(
+            "[{}] ({}) -> {};",
+            name,
+            format_expr(&tran.condition),
+            updates.join(" & ")
+        ),
+        _ => format!(
+            "[{}] {} -> {};",
+            name,
+            format_expr(&tran.condition),
+            updates.join(" & ")
+        ),
+    }
+}
+
+fn format_range(range: &TypeRange) -> String {
+    format!(
+        "[{} .. {}]",
+        format_expr(&range.min),
+        format_expr(&range.max)
+    )
+}
+
+/// Formats an identifier. Resolved identifiers are qualified by their owner, unless they are
+/// global.
+pub fn format_ident(ident: &Identifier) -> String {
+    match ident {
+        Identifier::Simple { name } => name.clone(),
+        Identifier::OptionalOwner { owner: None, name } => name.clone(),
+        Identifier::OptionalOwner {
+            owner: Some(owner),
+            name,
+        } => format!("{}.{}", owner, name),
+        Identifier::Resolved {
+            owner: Owner::Global,
+            name,
+        } => name.clone(),
+        Identifier::Resolved {
+            owner: Owner::Player(owner),
+            name,
+        } => format!("{}.{}", owner, name),
+    }
+}
+
+/// Formats an expression with as few parentheses as possible
+pub fn format_expr(expr: &Expr) -> String {
+    match &expr.kind {
+        // The smallest integer has no positive counterpart that can be negated
+        ExprKind::Number(i32::MIN) => format!("({} - 1)", i32::MIN + 1),
+        ExprKind::Number(n) => n.to_string(),
+        ExprKind::OwnedIdent(ident) => format_ident(ident),
+        ExprKind::Index(ident, index) => format!("{}[{}]", format_ident(ident), format_expr(index)),
+        ExprKind::UnaryOp(op, operand) => {
+            let op = match op {
+                UnaryOpKind::Not => "!",
+                UnaryOpKind::Negation => "-",
+            };
+            format!("{}{}", op, format_primary(operand))
+        }
+        ExprKind::BinaryOp(op, lhs, rhs) => {
+            let Precedence(prec, assoc) = precedence(op);
+            // An operand needs parentheses if its operator binds weaker than this operator,
+            // or equally weak on the side the operator does not associate to
+            let needs_par = |operand: &Expr, assoc_side: Associativity| match &operand.kind {
+                ExprKind::BinaryOp(inner, ..) => {
+                    let Precedence(inner_prec, _) = precedence(inner);
+                    inner_prec < prec || (inner_prec == prec && assoc != assoc_side)
+                }
+                ExprKind::TernaryIf(..) => true,
+                _ => false,
+            };
+            let lhs = if needs_par(lhs, Associativity::LeftToRight) {
+                format!("({})", format_expr(lhs))
+            } else {
+                format_expr(lhs)
+            };
+            let rhs = if needs_par(rhs, Associativity::RightToLeft) {
+                format!("({})", format_expr(rhs))
+            } else {
+                format_expr(rhs)
+            };
+            format!("{} {} {}", lhs, op, rhs)
+        }
+        ExprKind::TernaryIf(cond, then, els) => {
+            // The parts of a ternary expression cannot be ternary expressions themselves
+            let part = |e: &Expr| match e.kind {
+                ExprKind::TernaryIf(..) => format!("({})", format_expr(e)),
+                _ => format_expr(e),
+            };
+            format!("{} ? {} : {}", part(cond), part(then), part(els))
+        }
+        ExprKind::Min(es) => format!("min({})", format_list(es)),
+        ExprKind::Max(es) => format!("max({})", format_list(es)),
+        ExprKind::Abs(e) => format!("abs({})", format_expr(e)),
+        ExprKind::Pow(base, exp) => format!("pow({}, {})", format_expr(base), format_expr(exp)),
+        ExprKind::Clamp(e, lo, hi) => format!(
+            "clamp({}, {}, {})",
+            format_expr(e),
+            format_expr(lo),
+            format_expr(hi)
+        ),
+        ExprKind::Call(ident, args) => format!("{}({})", format_ident(ident), format_list(args)),
+    }
+}
+
+/// Formats the operand of a unary operator, which must be a primary expression
+fn format_primary(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::BinaryOp(..) | ExprKind::TernaryIf(..) => format!("({})", format_expr(expr)),
+        _ => format_expr(expr),
+    }
+}
+
+fn format_list(es: &[Expr]) -> String {
+    es.iter()
+        .map(format_expr)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lcgs::ast::{BinaryOpKind, Expr, ExprKind, Root, Span};
+    use crate::lcgs::format::{format_expr, format_lcgs};
+    use crate::lcgs::parse::parse_lcgs;
+
+    /// Asserts that the formatted program parses to the same program
+    fn assert_round_trip(input: &str) {
+        let root = parse_lcgs(input).unwrap();
+        let formatted = format_lcgs(&root);
+        let reparsed = parse_lcgs(&formatted)
+            .unwrap_or_else(|err| panic!("{}\nin formatted program:\n{}", err, formatted));
+        assert_eq!(
+            root.decls, reparsed.decls,
+            "formatted program:\n{}",
+            formatted
+        );
+        let imports = |root: &Root| -> Vec<(String, usize)> {
+            root.imports
+                .iter()
+                .map(|import| (import.path.clone(), import.position))
+                .collect()
+        };
+        assert_eq!(imports(&root), imports(&reparsed));
+        // Formatting is idempotent
+        assert_eq!(formatted, format_lcgs(&reparsed));
+    }
+
+    fn number(n: i32) -> Expr {
+        Expr {
+            span: Span::default(),
+            kind: ExprKind::Number(n),
+        }
+    }
+
+    fn binop(op: BinaryOpKind, lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            span: Span::default(),
+            kind: ExprKind::BinaryOp(op, Box::new(lhs), Box::new(rhs)),
+        }
+    }
+
+    #[test]
+    fn test_format_expr_01() {
+        // Parentheses are only kept where the precedence requires them
+        let input = "const a = (1 + 2) * 3 - (4 - 5) + (6 * 7) - -(8 % 2) > 1 == !(0 || 1);";
+        let root = parse_lcgs(input).unwrap();
+        assert_eq!(
+            format_lcgs(&root),
+            "const a = (1 + 2) * 3 - (4 - 5) + 6 * 7 - -(8 % 2) > 1 == !(0 || 1);\n"
+        );
+        assert_round_trip(input);
+    }
+
+    #[test]
+    fn test_format_expr_02() {
+        // Ternary expressions are parenthesized when nested
+        assert_round_trip("const a = (1 ? 2 : 3) ? (4 ? 5 : 6) : 7 + (8 ? 9 : 10);");
+        assert_round_trip("label a = (x -> y) -> z && -(p1.x) + min(1, abs(-2), b[i + 1]);");
+    }
+
+    #[test]
+    fn test_format_expr_03() {
+        // Negative numbers and right-nested operators, which the parser never produces
+        assert_eq!(format_expr(&number(-4)), "-4");
+        assert_eq!(format_expr(&number(i32::MIN)), "(-2147483647 - 1)");
+        let e = binop(
+            BinaryOpKind::Subtraction,
+            number(1),
+            binop(BinaryOpKind::Subtraction, number(2), number(3)),
+        );
+        assert_eq!(format_expr(&e), "1 - (2 - 3)");
+    }
+
+    #[test]
+    fn test_format_decls_01() {
+        let input = "
+        import \"common.lcgs\";
+        const n = 2;
+        define alive(p) = p.health > 0;
+        player p[i in 0 .. n - 1] = robot [other=p[(i + 1) % n], speed=i+1];
+        player q = robot;
+        template robot
+            label home = x == 0;
+            x : [0..n][2] init 0 overflow wrap; x[j]' = x[j] + speed;
+            flag : bool init true;
+            y : [0 .. 3] init 1 .. n;
+            phase : {idle, moving} init 0;
+            [stay] 1;
+            [go] flag -> phase' = 1 & other.flag' = 0;
+            [reset] phase' = 0;
+            [odd] (x[0] ? 1 : 0) -> flag' = !flag;
+        endtemplate
+        import \"end.lcgs\";
+        ";
+        assert_round_trip(input);
+    }
+
+    #[test]
+    fn test_format_examples_01() {
+        // All example programs are preserved by formatting
+        let examples = [
+            include_str!("../../../lcgs-examples/mexican_standoff/mexican_standoff.lcgs"),
+            include_str!("../../../lcgs-examples/tic_tac_toe/tic_tac_toe.lcgs"),
+            include_str!("../../../lcgs-examples/robot_grid/robot_grid_N3.lcgs"),
+            include_str!("../../../lcgs-examples/rock_paper_scissors/rock_paper_scissors.lcgs"),
+            include_str!("../../../lcgs-examples/matching_pennies/matching_pennies_game.lcgs"),
+            include_str!("../../../lcgs-examples/gossipping_girls/gossipping_girls_circular.lcgs"),
+        ];
+        for example in examples.iter() {
+            assert_round_trip(example);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::lcgs::ast::Span;
+use crate::lcgs::diagnostics::SourceMap;
 use crate::lcgs::ir::relabeling::RelabelError;
 use crate::lcgs::ir::symbol_checker::SymbolError;
 use std::fmt::{Display, Formatter};
@@ -7,13 +9,115 @@ use std::fmt::{Display, Formatter};
 pub enum Error {
     Symbol(SymbolError),
     Relabel(RelabelError),
+    /// A declaration has the same name as an earlier declaration in the same scope
+    DuplicateDeclaration {
+        name: String,
+        span: Span,
+    },
+    /// A player declaration refers to a template that is not declared
+    UnknownTemplate {
+        name: String,
+        span: Span,
+    },
+    /// A player declaration refers to a declaration that is not a template
+    NotATemplate {
+        name: String,
+        span: Span,
+    },
+    /// An import has not been resolved, i.e. the program was not loaded with the
+    /// [loader](crate::lcgs::loader)
+    UnresolvedImport {
+        path: String,
+        span: Span,
+    },
+    /// Several errors found in the same program, ordered by their position in the program
+    Multiple(Vec<Error>),
+}
+
+impl Error {
+    /// Combines the given errors into one. Nested [Error::Multiple]s are flattened and the
+    /// errors are sorted by their position in the program. Errors with the same message and
+    /// position are only kept once, which happens when multiple players use a faulty template.
+    pub fn from_errors(errors: Vec<Error>) -> Error {
+        let mut flat = vec![];
+        for error in errors {
+            match error {
+                Error::Multiple(inner) => flat.extend(inner),
+                error => flat.push(error),
+            }
+        }
+        flat.sort_by_key(|error| error.span().map(|span| span.begin));
+        flat.dedup_by(|a, b| a.span() == b.span() && a.message() == b.message());
+        if flat.len() == 1 {
+            flat.pop().unwrap()
+        } else {
+            Error::Multiple(flat)
+        }
+    }
+
+    /// Returns the span of the program that caused the error, if it is a single error
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Symbol(SymbolError { span, .. }) => Some(*span),
+            Error::Relabel(RelabelError { span, .. }) => Some(*span),
+            Error::DuplicateDeclaration { span, .. } => Some(*span),
+            Error::UnknownTemplate { span, .. } => Some(*span),
+            Error::NotATemplate { span, .. } => Some(*span),
+            Error::UnresolvedImport { span, .. } => Some(*span),
+            Error::Multiple(_) => None,
+        }
+    }
+
+    /// Returns the message of the error. The messages of multiple errors are put on
+    /// separate lines.
+    pub fn message(&self) -> String {
+        match self {
+            Error::Symbol(SymbolError { msg, .. }) => msg.clone(),
+            Error::Relabel(RelabelError { msg, .. }) => msg.clone(),
+            Error::DuplicateDeclaration { name, .. } => {
+                format!("'{}' is already declared.", name)
+            }
+            Error::UnknownTemplate { name, .. } => format!("Unknown template '{}'.", name),
+            Error::NotATemplate { name, .. } => format!("'{}' is not a template.", name),
+            Error::UnresolvedImport { path, .. } => format!(
+                "Cannot import '{}'. Imports are only supported when loading programs from files.",
+                path
+            ),
+            Error::Multiple(errors) => errors
+                .iter()
+                .map(|error| error.message())
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+
+    /// Renders the error(s) with the location and the offending line of the program that
+    /// caused the error.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        match self {
+            Error::Multiple(errors) => errors
+                .iter()
+                .map(|error| error.render(sources))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            error => sources.render(error.span().unwrap_or_default(), &error.message()),
+        }
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match &self {
-            Error::Symbol(SymbolError { msg }) => write!(f, "Error: {}", msg),
-            Error::Relabel(RelabelError { msg }) => write!(f, "Error: {}", msg),
+            Error::Multiple(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            error => write!(f, "Error: {}", error.message()),
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::lcgs::ast::{BinaryOpKind, Expr, ExprKind, Identifier, UnaryOpKind};
+use crate::lcgs::ast::{int_clamp, int_pow, BinaryOpKind, Expr, ExprKind, Identifier, UnaryOpKind};
 use crate::lcgs::ir::intermediate::State;
 use crate::lcgs::ir::symbol_table::SymbolIdentifier;
 
@@ -15,11 +15,54 @@ impl<'a> Evaluator<'a> {
         match &expr.kind {
             ExprKind::Number(n) => *n,
             ExprKind::OwnedIdent(id) => self.eval_ident(id),
+            ExprKind::Index(..) => {
+                panic!("Unresolved array index. Something went wrong in symbol checking.")
+            }
+            ExprKind::Call(..) => {
+                panic!("Unexpanded macro call. Something went wrong in symbol checking.")
+            }
             ExprKind::UnaryOp(op, e) => self.eval_unop(op, e),
             ExprKind::BinaryOp(op, e1, e2) => self.eval_binop(op, e1, e2),
             ExprKind::TernaryIf(c, e1, e2) => self.eval_if(c, e1, e2),
             ExprKind::Max(exprs) => self.eval_max(exprs),
             ExprKind::Min(exprs) => self.eval_min(exprs),
+            ExprKind::Abs(e) => self.eval(e).saturating_abs(),
+            ExprKind::Pow(base, exp) => int_pow(self.eval(base), self.eval(exp)),
+            ExprKind::Clamp(e, lo, hi) => int_clamp(self.eval(e), self.eval(lo), self.eval(hi)),
+        }
+    }
+
+    /// Returns the first division or modulo by zero, or power of 0 with a negative exponent,
+    /// that is performed when evaluating the given expression. Such operations evaluate to 0.
+    /// Like [Evaluator::eval], only the chosen branch of a ternary if is considered.
+    pub fn find_division_by_zero<'e>(&self, expr: &'e Expr) -> Option<&'e Expr> {
+        match &expr.kind {
+            ExprKind::Number(_)
+            | ExprKind::OwnedIdent(_)
+            | ExprKind::Index(..)
+            | ExprKind::Call(..) => None,
+            ExprKind::UnaryOp(_, e) | ExprKind::Abs(e) => self.find_division_by_zero(e),
+            ExprKind::BinaryOp(op, e1, e2) => self
+                .find_division_by_zero(e1)
+                .or_else(|| self.find_division_by_zero(e2))
+                .or_else(|| (op.divides() && self.eval(e2) == 0).then_some(expr)),
+            ExprKind::Pow(base, exp) => self
+                .find_division_by_zero(base)
+                .or_else(|| self.find_division_by_zero(exp))
+                .or_else(|| (self.eval(base) == 0 && self.eval(exp) < 0).then_some(expr)),
+            ExprKind::TernaryIf(c, e1, e2) => self.find_division_by_zero(c).or_else(|| {
+                if self.eval(c) != 0 {
+                    self.find_division_by_zero(e1)
+                } else {
+                    self.find_division_by_zero(e2)
+                }
+            }),
+            ExprKind::Min(exprs) | ExprKind::Max(exprs) => {
+                exprs.iter().find_map(|e| self.find_division_by_zero(e))
+            }
+            ExprKind::Clamp(e, lo, hi) => [e, lo, hi]
+                .iter()
+                .find_map(|e| self.find_division_by_zero(e)),
         }
     }
 
@@ -68,7 +111,7 @@ impl<'a> Evaluator<'a> {
 
 #[cfg(test)]
 mod test {
-    use crate::lcgs::ast::{Expr, ExprKind};
+    use crate::lcgs::ast::{BinaryOpKind, Expr, ExprKind, Span};
     use crate::lcgs::ir::eval::Evaluator;
     use crate::lcgs::ir::intermediate::State;
     use std::collections::HashMap;
@@ -76,14 +119,18 @@ mod test {
     #[test]
     fn test_max() {
         let expr = Expr {
+            span: Span::default(),
             kind: ExprKind::Max(vec![
                 Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(1),
                 },
                 Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(3),
                 },
                 Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(2),
                 },
             ]),
@@ -93,17 +140,89 @@ mod test {
         assert_eq!(evaluator.eval(&expr), 3);
     }
 
+    #[test]
+    fn test_division_by_zero() {
+        // Division and modulo by zero evaluates to 0 when not caught during symbol checking.
+        // Such divisions are found by IntermediateLCGS::check_division_by_zero.
+        let zero = Expr {
+            span: Span::default(),
+            kind: ExprKind::Number(0),
+        };
+        let seven = Expr {
+            span: Span::default(),
+            kind: ExprKind::Number(7),
+        };
+        let state = State(HashMap::new());
+        let evaluator = Evaluator::new(&state);
+        for op in [BinaryOpKind::Division, BinaryOpKind::Modulo] {
+            let expr = Expr {
+                span: Span::default(),
+                kind: ExprKind::BinaryOp(op, Box::new(seven.clone()), Box::new(zero.clone())),
+            };
+            assert_eq!(evaluator.eval(&expr), 0);
+        }
+    }
+
+    #[test]
+    fn test_modulo_negative() {
+        let expr = Expr {
+            span: Span::default(),
+            kind: ExprKind::BinaryOp(
+                BinaryOpKind::Modulo,
+                Box::new(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(-1),
+                }),
+                Box::new(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(3),
+                }),
+            ),
+        };
+        let state = State(HashMap::new());
+        let evaluator = Evaluator::new(&state);
+        assert_eq!(evaluator.eval(&expr), 2);
+    }
+
+    #[test]
+    fn test_pow() {
+        let pow = |b, e| Expr {
+            span: Span::default(),
+            kind: ExprKind::Pow(
+                Box::new(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(b),
+                }),
+                Box::new(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(e),
+                }),
+            ),
+        };
+        let state = State(HashMap::new());
+        let evaluator = Evaluator::new(&state);
+        assert_eq!(evaluator.eval(&pow(2, 10)), 1024);
+        assert_eq!(evaluator.eval(&pow(5, 0)), 1);
+        assert_eq!(evaluator.eval(&pow(2, -1)), 0);
+        assert_eq!(evaluator.eval(&pow(-1, -3)), -1);
+        assert_eq!(evaluator.eval(&pow(10, 20)), i32::MAX);
+    }
+
     #[test]
     fn test_min() {
         let expr = Expr {
+            span: Span::default(),
             kind: ExprKind::Min(vec![
                 Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(1),
                 },
                 Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(3),
                 },
                 Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(2),
                 },
             ]),
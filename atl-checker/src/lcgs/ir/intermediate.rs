@@ -1,18 +1,28 @@
 use std::borrow::BorrowMut;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::atl::common;
 use crate::atl::common::{Action, Proposition};
-use crate::atl::formula::{identifier, ATLExpressionParser};
-use crate::atl::gamestructure::GameStructure;
-use crate::lcgs::ast::{ConstDecl, Decl, DeclKind, ExprKind, Identifier, Root};
+use crate::atl::formula::{identifier, ATLExpressionParser, Phi};
+use crate::atl::gamestructure::{GameStructure, MoveIterator};
+use crate::lcgs::ast::{
+    ConstDecl, Decl, DeclKind, Expr, ExprKind, Identifier, PlayerDecl, RelabelCase, Relabeling,
+    Root, Span, StateVarDecl, UpdateDecl, VarType,
+};
+use crate::lcgs::format::{format_decl, separate};
 use crate::lcgs::ir::error::Error;
 use crate::lcgs::ir::eval::Evaluator;
+use crate::lcgs::ir::interval::RangeAnalysis;
+use crate::lcgs::ir::lint::{collect_idents, Lint, Linter};
 use crate::lcgs::ir::relabeling::Relabeler;
 use crate::lcgs::ir::symbol_checker::{CheckMode, SymbolChecker, SymbolError};
 use crate::lcgs::ir::symbol_table::{Owner, SymbolIdentifier, SymbolTable};
+use crate::lcgs::ir::symmetry::SymmetryFinder;
+use crate::lcgs::ir::type_checker::{TypeChecker, TypeDiagnostic};
+use crate::lcgs::parse::player_name;
 use pom::parser::{sym, Parser};
 use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
 
 /// A struct that holds information about players for the intermediate representation
 /// of the lazy game structure
@@ -41,6 +51,10 @@ impl Player {
         Owner::Player(self.name.clone())
     }
 
+    pub(crate) fn actions(&self) -> &[SymbolIdentifier] {
+        &self.actions
+    }
+
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -54,6 +68,41 @@ pub struct IntermediateLCGS {
     labels: Vec<SymbolIdentifier>,
     vars: Vec<SymbolIdentifier>,
     players: Vec<Player>,
+    /// Updates performed by actions. They are also part of the update expressions of the
+    /// variables, but are kept to detect conflicting updates.
+    updates: Vec<ActionUpdate>,
+    /// Symbols referenced by expressions. See [SymbolTable::used].
+    used: HashSet<SymbolIdentifier>,
+    deadlock_policy: DeadlockPolicy,
+    /// Permutations of the state variables that map the game onto itself, excluding the
+    /// identity. See [IntermediateLCGS::symmetry_reduction].
+    symmetries: Vec<Vec<usize>>,
+}
+
+/// Determines what happens in states where a player has no available actions, i.e. all
+/// conditions of the player's transitions are false. A game structure requires every player
+/// to have at least one action in every state.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum DeadlockPolicy {
+    /// The player has no actions, i.e. a move count of 0. This does not give a well-formed
+    /// game structure, so formulas cannot be checked, but deadlocks can be inspected, e.g. by
+    /// [IntermediateLCGS::check_deadlocks] or the statistics of the game.
+    Allow,
+    /// The player has a single implicit action that updates nothing. This is the default.
+    #[default]
+    Idle,
+    /// Reachable deadlocks are errors. They are detected by
+    /// [IntermediateLCGS::check_deadlocks].
+    Error,
+}
+
+/// An update of a variable performed by an action. See [UpdateDecl].
+#[derive(Clone, Debug)]
+struct ActionUpdate {
+    action: SymbolIdentifier,
+    variable: SymbolIdentifier,
+    /// The checked value expression
+    value: Expr,
 }
 
 impl IntermediateLCGS {
@@ -62,20 +111,43 @@ impl IntermediateLCGS {
     pub fn create(root: Root) -> Result<IntermediateLCGS, Error> {
         let mut symbols = SymbolTable::new();
 
-        // Register global decls. Then check and optimize them
-        let (players, labels, vars) = register_decls(&mut symbols, root)?;
-        check_and_optimize_decls(&symbols)?;
+        // Register global decls. Then check and optimize them. The registered declarations
+        // are checked even if some failed to register, such that all errors are reported.
+        let registered = register_decls(&mut symbols, root);
+        let checked = check_and_optimize_decls(&symbols);
+        let (players, labels, vars, updates) = match (registered, checked) {
+            (Ok(names), Ok(())) => names,
+            (registered, checked) => {
+                let mut errors: Vec<Error> = checked
+                    .err()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Error::from)
+                    .collect();
+                errors.extend(registered.err());
+                return Err(Error::from_errors(errors));
+            }
+        };
 
         let ilcgs = IntermediateLCGS {
+            used: symbols.used(),
             symbols: symbols.solidify(),
+            deadlock_policy: DeadlockPolicy::default(),
+            symmetries: vec![],
             labels,
             vars,
             players,
+            updates,
         };
 
         Ok(ilcgs)
     }
 
+    /// Sets the [DeadlockPolicy] of the game
+    pub fn set_deadlock_policy(&mut self, policy: DeadlockPolicy) {
+        self.deadlock_policy = policy;
+    }
+
     pub fn get_decl(&self, symbol: &SymbolIdentifier) -> Option<&Decl> {
         self.symbols.get(symbol)
     }
@@ -151,8 +223,497 @@ impl IntermediateLCGS {
             .collect()
     }
 
-    /// Returns the initial state of the LCGS game
-    pub fn initial_state(&self) -> State {
+    /// Returns the successor of the given state given an action for each player. The
+    /// [RangePolicy](crate::lcgs::ast::RangePolicy) of each variable is applied to its new
+    /// value, so only variables with the `Error` policy can have values outside their range.
+    pub(crate) fn successor(&self, state: &State, choices: &[usize]) -> State {
+        let state = self.assign_actions(state, choices);
+
+        // Now we can evaluate the next state based on previous state and the actions taken
+        let evaluator = Evaluator::new(&state);
+        let mut next_state = State(HashMap::new());
+        for symb_id in &self.vars {
+            let symb = self.symbols.get(symb_id).unwrap();
+            if let DeclKind::StateVar(var) = &symb.kind {
+                let mut val = evaluator.eval(&var.next_value);
+                if var.var_type == VarType::Bool {
+                    // Any non-zero value is true
+                    val = (val != 0) as i32;
+                }
+                val = var.range_policy.apply(val, &var.ir_range);
+                next_state.0.insert(symb_id.clone(), val);
+            }
+        }
+        next_state
+    }
+
+    /// Returns a copy of the given state where each action is assigned 1 if it is the chosen
+    /// action of its player, and 0 otherwise. Update expressions are evaluated in such a state.
+    fn assign_actions(&self, state: &State, choices: &[usize]) -> State {
+        let mut state = state.clone();
+        for (p_index, player) in self.players.iter().enumerate() {
+            // The `choices` vector only considers available actions, so we do those first
+            let moves = self.available_actions(&state, p_index);
+            debug_assert!(
+                choices[p_index] < moves.len()
+                    || (moves.is_empty() && self.deadlock_policy == DeadlockPolicy::Idle),
+                "Unknown action {} chosen for player {} in state {:?}",
+                choices[p_index],
+                p_index,
+                state
+            );
+            for (a_index, a_symb_id) in moves.iter().enumerate() {
+                let val = if choices[p_index] == a_index { 1 } else { 0 };
+                state.0.insert(a_symb_id.clone(), val);
+            }
+
+            // Some actions might not be available. These should also be set to 0.
+            for action in &player.actions {
+                if !state.0.contains_key(action) {
+                    state.0.insert(action.clone(), 0);
+                }
+            }
+        }
+        state
+    }
+
+    /// Explores all states reachable from the initial states in breadth-first order. The given
+    /// function is called for each transition with the index of the state, the state, the
+    /// actions chosen by the players, and the successor. The exploration stops at the first
+    /// error returned by the function.
+    fn explore<E, F>(&self, mut visit: F) -> Result<(), E>
+    where
+        F: FnMut(usize, &State, &[usize], &State) -> Result<(), E>,
+    {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        for initial in self.initial_states() {
+            visited.insert(initial);
+            queue.push_back(initial);
+        }
+
+        while let Some(index) = queue.pop_front() {
+            let state = self.state_from_index(index);
+            for choices in MoveIterator::new(self.move_count(index)) {
+                let next_state = self.successor(&state, &choices);
+                visit(index, &state, &choices, &next_state)?;
+                let next = self.index_of_state(&next_state);
+                if visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the chosen actions in the state of the given index
+    fn action_names(&self, index: usize, choices: &[usize]) -> Vec<String> {
+        choices
+            .iter()
+            .enumerate()
+            .map(|(player, action)| self.action_name(index, player, *action))
+            .collect()
+    }
+
+    /// Explores all reachable states and checks that no update results in a value outside the
+    /// range of a variable with the `Error` [RangePolicy](crate::lcgs::ast::RangePolicy).
+    /// Without this check, such a value is silently encoded as some other state. The first
+    /// violation found is returned.
+    pub fn check_ranges(&self) -> Result<(), RangeViolation> {
+        self.explore(|index, _, choices, next_state| {
+            for symb_id in &self.vars {
+                if let DeclKind::StateVar(var) = &self.symbols[symb_id].kind {
+                    let value = next_state.0[symb_id];
+                    if !var.ir_range.contains(&value) {
+                        return Err(RangeViolation {
+                            state: self.state_name(index),
+                            actions: self.action_names(index, choices),
+                            variable: symb_id.to_string(),
+                            value,
+                            range: var.ir_range.clone(),
+                        });
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Explores all reachable states and checks that no division or modulo by zero is
+    /// performed when evaluating the labels, the transition guards, and the updates. Such
+    /// operations evaluate to 0, unless they are found during symbol checking. The first
+    /// division by zero found is returned.
+    pub fn check_division_by_zero(&self) -> Result<(), DivisionByZero> {
+        let mut conditions = vec![];
+        for symb_id in self
+            .labels
+            .iter()
+            .chain(self.players.iter().flat_map(|player| &player.actions))
+        {
+            match &self.symbols[symb_id].kind {
+                DeclKind::Label(label) => conditions.push(&label.condition),
+                DeclKind::Transition(tran) => conditions.push(&tran.condition),
+                _ => {}
+            }
+        }
+        let mut checked = HashSet::new();
+        self.explore(|index, state, choices, _| {
+            // Labels and guards are evaluated in the state, and updates after the actions
+            // are chosen
+            if checked.insert(index) {
+                let evaluator = Evaluator::new(state);
+                if let Some(expr) = conditions
+                    .iter()
+                    .find_map(|condition| evaluator.find_division_by_zero(condition))
+                {
+                    return Err(DivisionByZero {
+                        state: self.state_name(index),
+                        actions: None,
+                        span: expr.span,
+                    });
+                }
+            }
+            let state = self.assign_actions(state, choices);
+            let evaluator = Evaluator::new(&state);
+            for symb_id in &self.vars {
+                if let DeclKind::StateVar(var) = &self.symbols[symb_id].kind {
+                    if let Some(expr) = evaluator.find_division_by_zero(&var.next_value) {
+                        return Err(DivisionByZero {
+                            state: self.state_name(index),
+                            actions: Some(self.action_names(index, choices)),
+                            span: expr.span,
+                        });
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Explores all reachable states and checks that every player has an available action in
+    /// each of them. No deadlocks are found if the [DeadlockPolicy] is `Idle`, since players
+    /// without actions then idle. The first deadlock found is returned with a shortest path
+    /// to it from an initial state.
+    pub fn check_deadlocks(&self) -> Result<(), Deadlock> {
+        let initials = self.initial_states();
+        // The predecessor of each state and the actions taken there to reach the state
+        let mut parents: HashMap<usize, (usize, Vec<usize>)> = HashMap::new();
+        for &initial in &initials {
+            self.find_deadlock(initial, &parents)?;
+        }
+        self.explore(|index, _, choices, next_state| {
+            let next = self.index_of_state(next_state);
+            if !initials.contains(&next) && !parents.contains_key(&next) {
+                parents.insert(next, (index, choices.to_vec()));
+                self.find_deadlock(next, &parents)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns a [Deadlock] if a player has no available actions in the state of the given
+    /// index. The path to the state is found by following the parents back to an initial state.
+    fn find_deadlock(
+        &self,
+        index: usize,
+        parents: &HashMap<usize, (usize, Vec<usize>)>,
+    ) -> Result<(), Deadlock> {
+        let players: Vec<String> = self
+            .move_count(index)
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == 0)
+            .map(|(player, _)| self.players[player].name.clone())
+            .collect();
+        if players.is_empty() {
+            return Ok(());
+        }
+
+        let mut path = vec![];
+        let mut current = index;
+        while let Some((parent, choices)) = parents.get(&current) {
+            path.push((
+                self.state_name(*parent),
+                self.action_names(*parent, choices),
+            ));
+            current = *parent;
+        }
+        path.reverse();
+        Err(Deadlock {
+            state: self.state_name(index),
+            players,
+            path,
+        })
+    }
+
+    /// Explores all reachable states and checks that actions taken at the same time never
+    /// update the same variable to different values. Only the first of such updates would be
+    /// performed. The states are only explored if some variable is updated by actions of
+    /// several players, which is rare. The first conflict found is returned.
+    pub fn check_update_conflicts(&self) -> Result<(), UpdateConflict> {
+        // Only variables updated by multiple players can have conflicting updates
+        let mut by_variable: HashMap<&SymbolIdentifier, Vec<&ActionUpdate>> = HashMap::new();
+        for update in &self.updates {
+            by_variable
+                .entry(&update.variable)
+                .or_default()
+                .push(update);
+        }
+        by_variable.retain(|_, updates| {
+            updates
+                .iter()
+                .any(|update| update.action.owner != updates[0].action.owner)
+        });
+        if by_variable.is_empty() {
+            return Ok(());
+        }
+
+        self.explore(|index, state, choices, _| {
+            let state = self.assign_actions(state, choices);
+            let evaluator = Evaluator::new(&state);
+            for (variable, updates) in &by_variable {
+                let performed: Vec<(String, i32)> = updates
+                    .iter()
+                    .filter(|update| state.0[&update.action] == 1)
+                    .map(|update| (update.action.to_string(), evaluator.eval(&update.value)))
+                    .collect();
+                if performed.iter().any(|(_, value)| *value != performed[0].1) {
+                    return Err(UpdateConflict {
+                        state: self.state_name(index),
+                        actions: self.action_names(index, choices),
+                        variable: variable.to_string(),
+                        updates: performed,
+                    });
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Checks that booleans and integers are used correctly in all labels, transition guards,
+    /// and update expressions. See [TypeChecker].
+    pub fn type_check(&self) -> Vec<TypeDiagnostic> {
+        let mut checker = TypeChecker::new(&self.symbols);
+        for decl in self.symbols.values() {
+            checker.check_decl(decl);
+        }
+        checker.finish()
+    }
+
+    /// Looks for unused and suspicious declarations, such as variables that are never updated
+    /// and actions that can never be taken. See [Linter]. Labels are only reported as unused
+    /// if the indexes of the labels used by the formulas being checked are given.
+    pub fn lint(&self, used_labels: Option<&HashSet<usize>>) -> Vec<Lint> {
+        Linter::new(&self.symbols, &self.used, &self.reachable_ranges()).lint(used_labels)
+    }
+
+    /// Returns a copy of the game where the state variables that cannot influence the given
+    /// labels are removed, i.e. the cone of influence of the labels. A variable influences the
+    /// labels if the labels, the guards of any transition, or the update of an influencing
+    /// variable reads it. Guards are always included, since they determine the moves of the
+    /// players. The removed variables are not part of the state encoding, so the reduced game
+    /// has fewer states, but the same labels hold in corresponding states. Labels outside the
+    /// given set keep their index, but they never hold in the reduced game.
+    pub fn cone_of_influence(&self, labels: &HashSet<Proposition>) -> IntermediateLCGS {
+        let mut relevant = HashSet::new();
+        let mut queue = vec![];
+        let mut visit = |expr: &Expr, queue: &mut Vec<SymbolIdentifier>| {
+            let mut idents = vec![];
+            collect_idents(expr, &mut idents);
+            for ident in idents {
+                if self.vars.contains(&ident) && relevant.insert(ident.clone()) {
+                    queue.push(ident);
+                }
+            }
+        };
+
+        for (i, symb_id) in self.labels.iter().enumerate() {
+            if let DeclKind::Label(label) = &self.symbols[symb_id].kind {
+                if labels.contains(&i) {
+                    visit(&label.condition, &mut queue);
+                }
+            }
+        }
+        for action in self.players.iter().flat_map(|player| &player.actions) {
+            if let DeclKind::Transition(tran) = &self.symbols[action].kind {
+                visit(&tran.condition, &mut queue);
+            }
+        }
+        while let Some(symb_id) = queue.pop() {
+            if let DeclKind::StateVar(var) = &self.symbols[&symb_id].kind {
+                visit(&var.next_value, &mut queue);
+            }
+        }
+
+        let mut reduced = self.clone();
+        reduced.vars.retain(|symb_id| relevant.contains(symb_id));
+        reduced
+            .updates
+            .retain(|update| relevant.contains(&update.variable));
+        for (i, symb_id) in self.labels.iter().enumerate() {
+            if !labels.contains(&i) {
+                if let Some(DeclKind::Label(label)) =
+                    reduced.symbols.get_mut(symb_id).map(|decl| &mut decl.kind)
+                {
+                    // The condition may read removed variables
+                    label.condition.kind = ExprKind::Number(0);
+                }
+            }
+        }
+        reduced
+    }
+
+    /// Returns a copy of the game where each state is replaced by a canonical representative
+    /// of its orbit under the symmetries of the players, i.e. permutations of players
+    /// instantiated from the same template that map the game onto itself. The symmetries must
+    /// preserve the labels and coalitions of the given formula, such that all states of an
+    /// orbit satisfy the formula equally. See [SymmetryFinder].
+    pub fn symmetry_reduction(&self, phi: &Phi) -> IntermediateLCGS {
+        let labels: Vec<SymbolIdentifier> = phi
+            .propositions()
+            .into_iter()
+            .map(|prop| self.labels[prop].clone())
+            .collect();
+        let symmetries = SymmetryFinder::new(&self.symbols, &self.vars, &self.players)
+            .find(&labels, &phi.coalitions());
+        IntermediateLCGS {
+            symmetries,
+            ..self.clone()
+        }
+    }
+
+    /// Computes bounds of the values each state variable can take in reachable states, without
+    /// exploring the states. The bounds are within the declared ranges, but may be tighter.
+    /// See [RangeAnalysis].
+    pub fn reachable_ranges(&self) -> HashMap<SymbolIdentifier, RangeInclusive<i32>> {
+        RangeAnalysis::new(&self.symbols, &self.vars).run()
+    }
+
+    /// Replaces the declared range of each state variable by the bounds computed by
+    /// [IntermediateLCGS::reachable_ranges], which reduces the number of state indexes. The
+    /// game is unchanged, since no reachable value is out of the new ranges, unless it was
+    /// out of the declared range too.
+    pub fn tighten_ranges(&mut self) {
+        for (symb_id, range) in self.reachable_ranges() {
+            if let Some(DeclKind::StateVar(var)) =
+                self.symbols.get_mut(&symb_id).map(|decl| &mut decl.kind)
+            {
+                var.ir_range = range;
+            }
+        }
+    }
+
+    /// Returns the number of symmetries used to canonicalize states, excluding the identity
+    pub fn symmetry_count(&self) -> usize {
+        self.symmetries.len()
+    }
+
+    /// Returns the canonical representative of the given state's orbit under the symmetries
+    /// of the game. States of the same orbit satisfy the formula given to
+    /// [IntermediateLCGS::symmetry_reduction] equally.
+    pub fn canonical_state(&self, state: common::State) -> common::State {
+        self.canonical_index(state)
+    }
+
+    /// Returns the index of the canonical representative of the state's orbit, which is the
+    /// smallest index of the state's images under the symmetries
+    fn canonical_index(&self, state_index: usize) -> usize {
+        if self.symmetries.is_empty() {
+            return state_index;
+        }
+        // The symmetries only map variables to variables with the same range, so the offsets
+        // of the values from the start of their ranges can be moved directly
+        let mut offsets = Vec::with_capacity(self.vars.len());
+        let mut weights = Vec::with_capacity(self.vars.len());
+        let mut carry = state_index;
+        let mut weight = 1;
+        for symb_id in &self.vars {
+            if let DeclKind::StateVar(var) = &self.symbols[symb_id].kind {
+                let size = (var.ir_range.end() - var.ir_range.start() + 1) as usize;
+                offsets.push(carry % size);
+                weights.push(weight);
+                carry /= size;
+                weight *= size;
+            }
+        }
+        self.symmetries
+            .iter()
+            .map(|perm| {
+                offsets
+                    .iter()
+                    .zip(perm)
+                    .map(|(offset, &j)| offset * weights[j])
+                    .sum()
+            })
+            .fold(state_index, usize::min)
+    }
+
+    /// Formats the expanded program in LCGS syntax, i.e. after relabeling, constant inlining,
+    /// and expansion of arrays and macros. Declarations are grouped by their owner and
+    /// references are qualified by their owner, so the result is meant for inspection and is
+    /// not a valid LCGS program in general.
+    pub fn format_expanded(&self) -> String {
+        let mut owners = vec![(Owner::Global, vec![])];
+        owners.extend(
+            self.players
+                .iter()
+                .map(|player| (player.to_owner(), player.actions.clone())),
+        );
+
+        let mut out = String::new();
+        for (owner, actions) in owners {
+            let symbols: Vec<&SymbolIdentifier> = self
+                .vars
+                .iter()
+                .chain(&self.labels)
+                .chain(&actions)
+                .filter(|symbol| symbol.owner == owner)
+                .collect();
+            if symbols.is_empty() {
+                continue;
+            }
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            match &owner {
+                Owner::Global => out.push_str("// Global\n"),
+                Owner::Player(name) => out.push_str(&format!("// Player {}\n", name)),
+            }
+            let mut prev: Option<&DeclKind> = None;
+            for symbol in symbols {
+                let decl = &self.symbols[symbol];
+                if prev.is_some_and(|prev| separate(prev, &decl.kind)) {
+                    out.push('\n');
+                }
+                if let DeclKind::StateVar(var) = &decl.kind {
+                    // The range and initial value are only evaluated, not optimized
+                    let number = |n: i32| Expr {
+                        span: Span::default(),
+                        kind: ExprKind::Number(n),
+                    };
+                    let mut var = var.clone();
+                    var.range.min = number(*var.ir_range.start());
+                    var.range.max = number(*var.ir_range.end());
+                    var.initial_value = number(var.ir_initial_value);
+                    if var.initial_max.is_some() {
+                        var.initial_max = Some(number(var.ir_initial_max));
+                    }
+                    out.push_str(&format_decl(&Decl {
+                        span: decl.span,
+                        kind: DeclKind::StateVar(var),
+                    }));
+                } else {
+                    out.push_str(&format_decl(decl));
+                }
+                out.push('\n');
+                prev = Some(&decl.kind);
+            }
+        }
+        out
+    }
+
+    /// Returns the initial values of the state variables of the LCGS game
+    pub fn initial_values(&self) -> State {
         let mut res = State(HashMap::new());
         for symb_id in &self.vars {
             let symb = self.symbols.get(symb_id).unwrap();
@@ -173,27 +734,91 @@ impl IntermediateLCGS {
         self.labels.clone()
     }
 
-    /// Returns the initial state index of the LCGS game
+    /// Returns the initial state index of the LCGS game. If some variables have a range of
+    /// initial values, this is the initial state where they all have their smallest value.
     pub fn initial_state_index(&self) -> usize {
-        self.index_of_state(&self.initial_state())
+        self.canonical_index(self.index_of_state(&self.initial_values()))
+    }
+
+    /// Returns the state where the given state variables have the given values, and all other
+    /// state variables have their initial value. Variables are named like in ATL formulas,
+    /// i.e. "`x`" for global variables and "`p1.x`" for variables of players.
+    pub fn state_from_values(&self, values: &[(String, i32)]) -> Result<State, String> {
+        let mut state = self.initial_values();
+        for (name, value) in values {
+            let symb_id = match name.rsplit_once('.') {
+                Some((owner, name)) => Owner::Player(owner.to_string()).symbol_id(name),
+                None => Owner::Global.symbol_id(name),
+            };
+            match self.symbols.get(&symb_id).map(|decl| &decl.kind) {
+                Some(DeclKind::StateVar(var)) => {
+                    if !var.ir_range.contains(value) {
+                        return Err(format!(
+                            "The value {} of '{}' is outside its range [{} .. {}].",
+                            value,
+                            name,
+                            var.ir_range.start(),
+                            var.ir_range.end()
+                        ));
+                    }
+                    state.0.insert(symb_id, *value);
+                }
+                _ => {
+                    return Err(format!(
+                        "The LCGS program does not contain any state variable named '{}'.",
+                        name
+                    ))
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    /// Makes the given state the only initial state of the game, by replacing the initial
+    /// values of the state variables by their values in the given state.
+    pub fn set_initial_state(&mut self, state: &State) {
+        for (symb_id, value) in &state.0 {
+            if let Some(DeclKind::StateVar(var)) =
+                self.symbols.get_mut(symb_id).map(|decl| &mut decl.kind)
+            {
+                var.ir_initial_value = *value;
+                var.ir_initial_max = *value;
+            }
+        }
     }
 }
 
 /// Names of declarations. First component is players and their fields. Second component
-/// is global labels. And third component is global variables.
-type DeclNames = (Vec<Player>, Vec<SymbolIdentifier>, Vec<SymbolIdentifier>);
+/// is global labels. Third component is global variables. And fourth component is the
+/// updates performed by actions.
+type DeclNames = (
+    Vec<Player>,
+    Vec<SymbolIdentifier>,
+    Vec<SymbolIdentifier>,
+    Vec<ActionUpdate>,
+);
 
 /// Registers all declarations from the root in the symbol table. Constants are optimized to
 /// numbers immediately. On success, a vector of [Player]s is returned with information
-/// about players and the names of their actions.
+/// about players and the names of their actions. Registration continues after a faulty
+/// declaration, such that all errors can be reported at once.
 fn register_decls(symbols: &mut SymbolTable, root: Root) -> Result<DeclNames, Error> {
+    let mut errors: Vec<Error> = vec![];
     let mut player_decls = vec![];
+    let mut commands = vec![];
     let mut player_names = HashSet::new();
     let mut labels = vec![];
     let mut vars = vec![];
 
     let mut next_label_index = 0;
 
+    for import in root.imports {
+        errors.push(Error::UnresolvedImport {
+            path: import.path,
+            span: import.span,
+        });
+    }
+
     // Register global declarations.
     // Constants are evaluated immediately.
     // Players are put in a separate vector and handled afterwards.
@@ -205,12 +830,21 @@ fn register_decls(symbols: &mut SymbolTable, root: Root) -> Result<DeclNames, Er
                 // refer to other constants that are above them in the program.
                 // If they don't reduce to a single number, then the SymbolChecker
                 // produces an error.
+                // A faulty constant is registered as 0 to avoid follow-up errors
                 let result = SymbolChecker::new(&symbols, Owner::Global, CheckMode::Const)
-                    .check(&cons.definition)?;
+                    .check(&cons.definition)
+                    .unwrap_or_else(|err| {
+                        errors.push(err.into());
+                        Expr {
+                            span: cons.definition.span,
+                            kind: ExprKind::Number(0),
+                        }
+                    });
                 debug_assert!(matches!(result.kind, ExprKind::Number(_)));
                 let name = cons.name.name().to_string();
                 // Construct a resolved constant decl
                 let decl = Decl {
+                    span: decl.span,
                     kind: DeclKind::Const(Box::new(ConstDecl {
                         name: Identifier::Resolved {
                             owner: Owner::Global,
@@ -219,8 +853,8 @@ fn register_decls(symbols: &mut SymbolTable, root: Root) -> Result<DeclNames, Er
                         definition: result,
                     })),
                 };
-                if symbols.insert(&Owner::Global, &name, decl).is_some() {
-                    panic!("Constant '{}' is already declared.", &name); // TODO Use custom error
+                if let Err(err) = insert_unique(symbols, &Owner::Global, &name, decl) {
+                    errors.push(err);
                 }
             }
             DeclKind::Label(label) => {
@@ -228,40 +862,76 @@ fn register_decls(symbols: &mut SymbolTable, root: Root) -> Result<DeclNames, Er
                 next_label_index += 1;
                 // Insert in symbol table and add to labels list
                 let name = decl.kind.ident().name().to_string();
-                if symbols.insert(&Owner::Global, &name, decl).is_some() {
-                    panic!("Symbol '{}' is already declared.", &name); // TODO Use custom error
+                if let Err(err) = insert_unique(symbols, &Owner::Global, &name, decl) {
+                    errors.push(err);
+                    continue;
                 }
                 labels.push(SymbolIdentifier {
                     owner: Owner::Global,
                     name,
                 });
             }
-            DeclKind::StateVar(_) => {
-                // Insert in symbol table and add to vars list
-                let name = decl.kind.ident().name().to_string();
-                if symbols.insert(&Owner::Global, &name, decl).is_some() {
-                    panic!("Symbol '{}' is already declared.", &name); // TODO Use custom error
+            DeclKind::StateVar(var) => {
+                if let Err(err) = register_enum_variants(symbols, decl.span, var) {
+                    errors.push(err);
                 }
-                vars.push(SymbolIdentifier {
-                    owner: Owner::Global,
-                    name,
+                // Insert in symbol table and add to vars list. Arrays become a variable per element
+                let expanded = expand_array(symbols, decl).unwrap_or_else(|err| {
+                    errors.push(err);
+                    vec![]
                 });
+                for decl in expanded {
+                    let name = decl.kind.ident().name().to_string();
+                    if let Err(err) = insert_unique(symbols, &Owner::Global, &name, decl) {
+                        errors.push(err);
+                        continue;
+                    }
+                    vars.push(SymbolIdentifier {
+                        owner: Owner::Global,
+                        name,
+                    });
+                }
+            }
+            DeclKind::Template(template) => {
+                // Enum variants of template variables are shared by all players using the
+                // template, so they are registered globally once
+                for template_decl in &template.decls {
+                    if let DeclKind::StateVar(var) = &template_decl.kind {
+                        if let Err(err) = register_enum_variants(symbols, template_decl.span, var) {
+                            errors.push(err);
+                        }
+                    }
+                }
+                let name = decl.kind.ident().name().to_string();
+                if let Err(err) = insert_unique(symbols, &Owner::Global, &name, decl) {
+                    errors.push(err);
+                }
             }
-            DeclKind::Template(_) => {
+            DeclKind::Define(_) => {
                 let name = decl.kind.ident().name().to_string();
-                if symbols.insert(&Owner::Global, &name, decl).is_some() {
-                    panic!("Symbol '{}' is already declared.", &name); // TODO Use custom error
+                if let Err(err) = insert_unique(symbols, &Owner::Global, &name, decl) {
+                    errors.push(err);
                 }
             }
-            DeclKind::Player(player) => {
-                // We handle player declarations later
-                if !player_names.insert(player.name.name().to_string()) {
-                    panic!("Player '{}' is already declared", &player.name.name());
-                    // TODO Use custom error
+            DeclKind::Player(_) => {
+                // We handle player declarations later. Player loops become a player per index.
+                let expanded = expand_players(symbols, decl).unwrap_or_else(|err| {
+                    errors.push(err);
+                    vec![]
+                });
+                for decl in expanded {
+                    let name = decl.kind.ident().name().to_string();
+                    if !player_names.insert(name.clone()) {
+                        errors.push(Error::DuplicateDeclaration {
+                            name,
+                            span: decl.span,
+                        });
+                        continue;
+                    }
+                    player_decls.push(decl);
                 }
-                player_decls.push(decl);
             }
-            _ => panic!("Not a global declaration. Parser must have failed."), // Not a global decl
+            _ => unreachable!("Not a global declaration. Parser must have failed."), // Not a global decl
         }
     }
 
@@ -275,12 +945,17 @@ fn register_decls(symbols: &mut SymbolTable, root: Root) -> Result<DeclNames, Er
             let mut player = Player::new(index, &player_decl.name.name());
             let relabeler = Relabeler::new(&player_decl.relabeling);
 
-            let template_decl = symbols
-                .get(&Owner::Global, &player_decl.template.name())
-                .expect("Unknown template") // TODO Use custom error
-                .declaration
-                .borrow()
-                .clone();
+            let template_name = player_decl.template.name();
+            let template_decl = match symbols.get(&Owner::Global, template_name) {
+                Some(symb) => symb.declaration.borrow().clone(),
+                None => {
+                    errors.push(Error::UnknownTemplate {
+                        name: template_name.to_string(),
+                        span: decl.span,
+                    });
+                    continue;
+                }
+            };
 
             if let DeclKind::Template(template) = template_decl.kind {
                 // Go through each declaration in the template and register a relabeled
@@ -291,152 +966,750 @@ fn register_decls(symbols: &mut SymbolTable, root: Root) -> Result<DeclNames, Er
                         DeclKind::Label(label) => {
                             label.index = next_label_index;
                             next_label_index += 1;
-                            let relabeled_decl = relabeler.relabel_decl(&decl)?;
+                            let relabeled_decl = match relabeler.relabel_decl(&decl) {
+                                Ok(relabeled_decl) => relabeled_decl,
+                                Err(err) => {
+                                    errors.push(err.into());
+                                    continue;
+                                }
+                            };
                             // Insert into symbol table and add to labels list
                             let name = relabeled_decl.kind.ident().name().to_string();
-                            if symbols
-                                .insert(&scope_owner, &name, relabeled_decl)
-                                .is_some()
+                            if let Err(err) =
+                                insert_unique(symbols, &scope_owner, &name, relabeled_decl)
                             {
-                                panic!("Label '{}.{}' is already declared.", &scope_owner, &name);
-                            };
+                                errors.push(err);
+                                continue;
+                            }
                             labels.push(SymbolIdentifier {
                                 owner: scope_owner.clone(),
                                 name,
                             });
                         }
                         DeclKind::StateVar(_) => {
-                            let relabeled_decl = relabeler.relabel_decl(&decl)?;
                             // Insert into symbol table and add to vars list
-                            let name = relabeled_decl.kind.ident().name().to_string();
-                            if symbols
-                                .insert(&scope_owner, &name, relabeled_decl)
-                                .is_some()
-                            {
-                                panic!(
-                                    "Variable '{}.{}' is already declared.",
-                                    &scope_owner, &name
-                                );
-                            };
-                            vars.push(SymbolIdentifier {
-                                owner: scope_owner.clone(),
-                                name,
-                            });
+                            let expanded = relabeler
+                                .relabel_decl(&decl)
+                                .map_err(Error::from)
+                                .and_then(|relabeled_decl| expand_array(symbols, relabeled_decl))
+                                .unwrap_or_else(|err| {
+                                    errors.push(err);
+                                    vec![]
+                                });
+                            for decl in expanded {
+                                let name = decl.kind.ident().name().to_string();
+                                if let Err(err) = insert_unique(symbols, &scope_owner, &name, decl)
+                                {
+                                    errors.push(err);
+                                    continue;
+                                }
+                                vars.push(SymbolIdentifier {
+                                    owner: scope_owner.clone(),
+                                    name,
+                                });
+                            }
                         }
                         DeclKind::Transition(_) => {
                             // Transitions are inserted in the symbol table, but their name
                             // is also stored in the player.actions so they can easily be found
-                            // later when run.
-                            let relabeled_decl = relabeler.relabel_decl(&decl)?;
+                            // later when run. Their updates are desugared once all variables
+                            // are registered.
+                            let mut relabeled_decl = match relabeler.relabel_decl(&decl) {
+                                Ok(relabeled_decl) => relabeled_decl,
+                                Err(err) => {
+                                    errors.push(err.into());
+                                    continue;
+                                }
+                            };
                             let name = relabeled_decl.kind.ident().name().to_string();
-                            if symbols
-                                .insert(&scope_owner, &name, relabeled_decl)
-                                .is_some()
+                            if let DeclKind::Transition(tran) = &mut relabeled_decl.kind {
+                                let updates = std::mem::take(&mut tran.updates);
+                                commands.push((scope_owner.symbol_id(&name), updates));
+                            }
+                            if let Err(err) =
+                                insert_unique(symbols, &scope_owner, &name, relabeled_decl)
                             {
-                                panic!("Action '{}.{}' is already declared.", &scope_owner, &name);
-                            };
+                                errors.push(err);
+                                continue;
+                            }
                             player.actions.push(scope_owner.symbol_id(&name));
                         }
-                        _ => panic!(
+                        DeclKind::Define(_) => {
+                            let relabeled_decl = match relabeler.relabel_decl(&decl) {
+                                Ok(relabeled_decl) => relabeled_decl,
+                                Err(err) => {
+                                    errors.push(err.into());
+                                    continue;
+                                }
+                            };
+                            let name = relabeled_decl.kind.ident().name().to_string();
+                            if let Err(err) =
+                                insert_unique(symbols, &scope_owner, &name, relabeled_decl)
+                            {
+                                errors.push(err);
+                            }
+                        }
+                        _ => unreachable!(
                             "Not a declaration allowed in templates. Parser must have failed."
                         ),
                     }
                 }
             } else {
-                panic!("'{}' is not a template.", template_decl.kind.ident().name());
-                // TODO Use custom error
+                errors.push(Error::NotATemplate {
+                    name: template_name.to_string(),
+                    span: decl.span,
+                });
+                continue;
             }
 
             // The player is done. We can now register the player declaration.
             players.push(player);
             let name = player_decl.name.name().to_string();
-            symbols.insert(&Owner::Global, &name, decl);
+            if let Err(err) = insert_unique(symbols, &Owner::Global, &name, decl) {
+                errors.push(err);
+            }
         } else {
-            panic!("A non-PlayerDecl got into this vector");
+            unreachable!("A non-PlayerDecl got into this vector");
         }
     }
-    Ok((players, labels, vars))
+    let updates = desugar_updates(symbols, commands).unwrap_or_else(|err| {
+        errors.push(err);
+        vec![]
+    });
+    if !errors.is_empty() {
+        return Err(Error::from_errors(errors));
+    }
+    Ok((players, labels, vars, updates))
 }
 
-/// Reduces the declarations in a [SymbolTable] to a more compact version, if possible.
-/// Validity of identifiers are also checked and resolved.
-fn check_and_optimize_decls(symbols: &SymbolTable) -> Result<(), SymbolError> {
-    for (symb_id, rc_symb) in symbols {
-        // Create resolved name
-        let SymbolIdentifier { owner, name } = symb_id;
-        let resolved_name = Identifier::Resolved {
-            owner: owner.clone(),
-            name: name.clone(),
-        };
-
-        // Optimize the declaration's expression(s)
-        let mut declaration = rc_symb.declaration.borrow_mut();
-        match declaration.kind.borrow_mut() {
-            DeclKind::Label(label) => {
-                label.name = resolved_name;
-                label.condition =
-                    SymbolChecker::new(symbols, owner.clone(), CheckMode::LabelOrTransition)
-                        .check(&label.condition)?;
-            }
-            DeclKind::StateVar(var) => {
-                var.name = resolved_name;
-                // Both initial value, min, and max are expected to be constant.
-                // Hence, we also evaluate them now so we don't have to do that each time.
-                let checker = SymbolChecker::new(symbols, owner.clone(), CheckMode::Const);
-                var.ir_initial_value = checker.check_eval(&var.initial_value)?;
-                let min = checker.check_eval(&var.range.min)?;
-                let max = checker.check_eval(&var.range.max)?;
-                var.ir_range = min..=max;
-                assert!(var.ir_range.contains(&var.ir_initial_value), "");
-                var.next_value =
-                    SymbolChecker::new(symbols, owner.clone(), CheckMode::StateVarUpdate)
-                        .check(&var.next_value)?;
-            }
-            DeclKind::Transition(tran) => {
-                tran.name = resolved_name;
-                tran.condition =
-                    SymbolChecker::new(symbols, owner.clone(), CheckMode::LabelOrTransition)
-                        .check(&tran.condition)?;
-            }
-            DeclKind::Player(player) => {
-                player.name = resolved_name;
-            }
-            DeclKind::Template(template) => {
-                template.name = resolved_name;
+/// Desugars the updates of actions into the update expressions of the updated variables.
+/// The value of an update is checked in the scope of the acting player. The update expression
+/// of a variable updated by actions becomes "`a1 ? v1 : (a2 ? v2 : ... : next)`", where `next`
+/// is the variable's own update expression, so the first update declared takes precedence,
+/// if multiple actions taken at the same time update the variable.
+fn desugar_updates(
+    symbols: &SymbolTable,
+    commands: Vec<(SymbolIdentifier, Vec<UpdateDecl>)>,
+) -> Result<Vec<ActionUpdate>, Error> {
+    let mut errors: Vec<Error> = vec![];
+    let mut updates = vec![];
+    for (action, decls) in commands {
+        let mut updated = HashSet::new();
+        for decl in decls {
+            let (variable, value) = match resolve_update(symbols, &action.owner, &decl) {
+                Ok(resolved) => resolved,
+                Err(err) => {
+                    errors.push(err.into());
+                    continue;
+                }
+            };
+            if !updated.insert(variable.clone()) {
+                errors.push(
+                    SymbolError {
+                        msg: format!(
+                            "The action '{}' updates '{}' more than once.",
+                            action, variable
+                        ),
+                        span: decl.span,
+                    }
+                    .into(),
+                );
+                continue;
             }
-            DeclKind::Const(_) => {} // Needs no further reduction
+            updates.push(ActionUpdate {
+                action: action.clone(),
+                variable,
+                value,
+            });
         }
     }
-    Ok(())
-}
-
-/// A game structure state of an LCGS. Holds a mapping of symbol names to their current value
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct State(pub HashMap<SymbolIdentifier, i32>);
 
-impl Display for State {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{{")?;
-        let len = self.0.len();
-        for (i, (symb_id, val)) in self.0.iter().enumerate() {
-            write!(f, "{}:{}", symb_id, val)?;
-            if i < len - 1 {
-                write!(f, ",")?;
-            }
+    // Wrap the update expressions starting from the last update, such that the first
+    // update is checked first
+    for update in updates.iter().rev() {
+        let symb = symbols
+            .get(&update.variable.owner, &update.variable.name)
+            .unwrap();
+        if let DeclKind::StateVar(var) = &mut symb.declaration.borrow_mut().kind {
+            let next_value = std::mem::replace(
+                &mut var.next_value,
+                Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(0),
+                },
+            );
+            var.next_value = Expr {
+                span: next_value.span,
+                kind: ExprKind::TernaryIf(
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: ExprKind::OwnedIdent(Box::new(Identifier::Resolved {
+                            owner: update.action.owner.clone(),
+                            name: update.action.name.clone(),
+                        })),
+                    }),
+                    Box::new(update.value.clone()),
+                    Box::new(next_value),
+                ),
+            };
         }
-        write!(f, "}}")
     }
+
+    if !errors.is_empty() {
+        return Err(Error::from_errors(errors));
+    }
+    Ok(updates)
 }
 
-impl GameStructure for IntermediateLCGS {
-    fn max_player(&self) -> usize {
-        self.players.len()
+/// Resolves the variable of an update performed by an action of the given player and checks
+/// the value of the update
+fn resolve_update(
+    symbols: &SymbolTable,
+    player: &Owner,
+    decl: &UpdateDecl,
+) -> Result<(SymbolIdentifier, Expr), SymbolError> {
+    let (owner, name) = match &decl.target {
+        Identifier::OptionalOwner { owner, name } => (owner, name),
+        _ => unreachable!("Update targets are not resolved before desugaring"),
+    };
+    let symb = match owner {
+        Some(player_name) => symbols.get(&Owner::Player(player_name.clone()), name),
+        None => symbols
+            .get(player, name)
+            .or_else(|| symbols.get(&Owner::Global, name)),
+    };
+    let symb = symb.ok_or_else(|| SymbolError {
+        msg: format!(
+            "Unknown variable '{}' in update.",
+            owner
+                .as_ref()
+                .map_or(name.clone(), |owner| format!("{}.{}", owner, name))
+        ),
+        span: decl.span,
+    })?;
+    if !matches!(symb.declaration.borrow().kind, DeclKind::StateVar(_)) {
+        return Err(SymbolError {
+            msg: format!(
+                "Only state variables can be updated, but '{}' is not a state variable.",
+                symb.identifier
+            ),
+            span: decl.span,
+        });
     }
+    let value = SymbolChecker::new(symbols, player.clone(), CheckMode::StateVarUpdate)
+        .check(&decl.value)?;
+    Ok((symb.identifier.clone(), value))
+}
 
-    /// Returns the set of labels/propositions available in the given state.
-    fn labels(&self, state: common::State) -> HashSet<Proposition> {
-        let state = self.state_from_index(state);
-        let mut res = HashSet::new();
+/// Inserts the declaration in the symbol table with the given owner and name. If the owner
+/// already has a declaration of that name, the existing declaration is kept and an
+/// [Error::DuplicateDeclaration] is returned.
+fn insert_unique(
+    symbols: &mut SymbolTable,
+    owner: &Owner,
+    name: &str,
+    decl: Decl,
+) -> Result<(), Error> {
+    if symbols.get(owner, name).is_some() {
+        let name = match owner {
+            Owner::Global => name.to_string(),
+            Owner::Player(_) => format!("{}.{}", owner, name),
+        };
+        return Err(Error::DuplicateDeclaration {
+            name,
+            span: decl.span,
+        });
+    }
+    symbols.insert(owner, name, decl);
+    Ok(())
+}
+
+/// Expands a player loop to a player declaration for each index named "`name[i]`". The index
+/// name is replaced by the index in the relabeling of each player. Relabel cases of the form
+/// "`p[<constant>]`" are resolved to the name of the indexed player or variable, so they can
+/// be used as owners in the template. A player declaration without a loop is returned with
+/// its relabel cases resolved in the same way.
+fn expand_players(symbols: &SymbolTable, decl: Decl) -> Result<Vec<Decl>, Error> {
+    let span = decl.span;
+    let player = match decl.kind {
+        DeclKind::Player(player) => player,
+        _ => panic!("Only player declarations can be expanded as players"),
+    };
+    let repeat = match &player.repeat {
+        Some(repeat) => repeat,
+        None => {
+            let relabeling = resolve_relabel_indexes(symbols, &player.relabeling);
+            return Ok(vec![Decl {
+                span,
+                kind: DeclKind::Player(Box::new(PlayerDecl {
+                    relabeling,
+                    ..*player
+                })),
+            }]);
+        }
+    };
+    let checker = SymbolChecker::new(symbols, Owner::Global, CheckMode::Const);
+    let min = checker.check_eval(&repeat.range.min)?;
+    let max = checker.check_eval(&repeat.range.max)?;
+
+    (min..=max)
+        .map(|i| {
+            let index_relabeling = Relabeling {
+                relabellings: vec![RelabelCase {
+                    prev: repeat.index_name.clone(),
+                    new: Expr {
+                        span: Span::default(),
+                        kind: ExprKind::Number(i),
+                    },
+                }],
+            };
+            let relabeler = Relabeler::new(&index_relabeling);
+            let relabeling = Relabeling {
+                relabellings: player
+                    .relabeling
+                    .relabellings
+                    .iter()
+                    .map(|case| {
+                        Ok(RelabelCase {
+                            prev: case.prev.clone(),
+                            new: relabeler.relabel_expr(&case.new)?,
+                        })
+                    })
+                    .collect::<Result<Vec<RelabelCase>, Error>>()?,
+            };
+            Ok(Decl {
+                span,
+                kind: DeclKind::Player(Box::new(PlayerDecl {
+                    index: 0,
+                    name: Identifier::Simple {
+                        name: format!("{}[{}]", player.name.name(), i),
+                    },
+                    template: player.template.clone(),
+                    relabeling: resolve_relabel_indexes(symbols, &relabeling),
+                    repeat: None,
+                })),
+            })
+        })
+        .collect()
+}
+
+/// Replaces relabel cases of the form "`p[<constant>]`" with the name "`p[i]`", where `i` is
+/// the value of the constant expression. Other cases are left as is.
+fn resolve_relabel_indexes(symbols: &SymbolTable, relabeling: &Relabeling) -> Relabeling {
+    let checker = SymbolChecker::new(symbols, Owner::Global, CheckMode::Const);
+    Relabeling {
+        relabellings: relabeling
+            .relabellings
+            .iter()
+            .map(|case| {
+                if let ExprKind::Index(ident, index) = &case.new.kind {
+                    if let Identifier::OptionalOwner { owner: None, name } = ident.as_ref() {
+                        if let Ok(i) = checker.check_eval(index) {
+                            return RelabelCase {
+                                prev: case.prev.clone(),
+                                new: Expr {
+                                    span: case.new.span,
+                                    kind: ExprKind::OwnedIdent(Box::new(
+                                        Identifier::OptionalOwner {
+                                            owner: None,
+                                            name: format!("{}[{}]", name, i),
+                                        },
+                                    )),
+                                },
+                            };
+                        }
+                    }
+                }
+                case.clone()
+            })
+            .collect(),
+    }
+}
+
+/// Expands an array variable declaration to a variable declaration for each element named
+/// "`name[i]`". The index name is replaced by the element's index in the update expression of
+/// the element. Any other declaration is returned as is.
+fn expand_array(symbols: &SymbolTable, decl: Decl) -> Result<Vec<Decl>, Error> {
+    let var = match &decl.kind {
+        DeclKind::StateVar(var) if var.array.is_some() => var,
+        _ => return Ok(vec![decl]),
+    };
+    let array = var.array.as_ref().unwrap();
+    let size =
+        SymbolChecker::new(symbols, Owner::Global, CheckMode::Const).check_eval(&array.size)?;
+    if size <= 0 {
+        return Err(SymbolError {
+            msg: format!(
+                "The array '{}' must have a positive size, but its size is {}.",
+                var.name.name(),
+                size
+            ),
+            span: array.size.span,
+        }
+        .into());
+    }
+
+    (0..size)
+        .map(|i| {
+            let relabeling = Relabeling {
+                relabellings: vec![RelabelCase {
+                    prev: array.index_name.clone(),
+                    new: Expr {
+                        span: Span::default(),
+                        kind: ExprKind::Number(i),
+                    },
+                }],
+            };
+            let next_value = Relabeler::new(&relabeling).relabel_expr(&var.next_value)?;
+            Ok(Decl {
+                span: decl.span,
+                kind: DeclKind::StateVar(Box::new(StateVarDecl {
+                    name: Identifier::Simple {
+                        name: format!("{}[{}]", var.name.name(), i),
+                    },
+                    next_value,
+                    array: None,
+                    ..var.as_ref().clone()
+                })),
+            })
+        })
+        .collect()
+}
+
+/// Registers the variants of an enum variable as global constants. Each variant's value is
+/// its index in the enum. Multiple enums can share a variant name, if the variant has the
+/// same value in all of them.
+fn register_enum_variants(
+    symbols: &mut SymbolTable,
+    span: Span,
+    var: &StateVarDecl,
+) -> Result<(), Error> {
+    if let VarType::Enum(variants) = &var.var_type {
+        for (value, variant) in variants.iter().enumerate() {
+            let definition = Expr {
+                span,
+                kind: ExprKind::Number(value as i32),
+            };
+            if let Some(symb) = symbols.get(&Owner::Global, variant) {
+                if let DeclKind::Const(con) = &symb.declaration.borrow().kind {
+                    if con.definition == definition {
+                        continue;
+                    }
+                }
+                return Err(SymbolError {
+                    msg: format!(
+                        "The enum variant '{}' of '{}' conflicts with another declaration of the same name.",
+                        variant,
+                        var.name.name()
+                    ),
+                    span,
+                }
+                .into());
+            }
+            let decl = Decl {
+                span,
+                kind: DeclKind::Const(Box::new(ConstDecl {
+                    name: Identifier::Resolved {
+                        owner: Owner::Global,
+                        name: variant.clone(),
+                    },
+                    definition,
+                })),
+            };
+            symbols.insert(&Owner::Global, variant, decl);
+        }
+    }
+    Ok(())
+}
+
+/// Reduces the declarations in a [SymbolTable] to a more compact version, if possible.
+/// Validity of identifiers are also checked and resolved. All declarations are checked,
+/// such that every error can be reported.
+fn check_and_optimize_decls(symbols: &SymbolTable) -> Result<(), Vec<SymbolError>> {
+    let mut errors = vec![];
+    for (symb_id, rc_symb) in symbols {
+        // Create resolved name
+        let SymbolIdentifier { owner, name } = symb_id;
+        let resolved_name = Identifier::Resolved {
+            owner: owner.clone(),
+            name: name.clone(),
+        };
+
+        // Optimize the declaration's expression(s)
+        let mut declaration = rc_symb.declaration.borrow_mut();
+        if let Err(err) = check_and_optimize_decl(symbols, owner, resolved_name, &mut declaration) {
+            errors.push(err);
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(())
+}
+
+/// Reduces a single declaration owned by the given owner. See [check_and_optimize_decls].
+fn check_and_optimize_decl(
+    symbols: &SymbolTable,
+    owner: &Owner,
+    resolved_name: Identifier,
+    decl: &mut Decl,
+) -> Result<(), SymbolError> {
+    match decl.kind.borrow_mut() {
+        DeclKind::Label(label) => {
+            label.name = resolved_name;
+            label.condition =
+                SymbolChecker::new(symbols, owner.clone(), CheckMode::LabelOrTransition)
+                    .check(&label.condition)?;
+        }
+        DeclKind::StateVar(var) => {
+            let symb_name = var.name.name().to_string();
+            var.name = resolved_name;
+            // Both initial value, min, and max are expected to be constant.
+            // Hence, we also evaluate them now so we don't have to do that each time.
+            let checker = SymbolChecker::new(symbols, owner.clone(), CheckMode::Const);
+            var.ir_initial_value = checker.check_eval(&var.initial_value)?;
+            var.ir_initial_max = match &var.initial_max {
+                Some(max) => checker.check_eval(max)?,
+                None => var.ir_initial_value,
+            };
+            let min = checker.check_eval(&var.range.min)?;
+            let max = checker.check_eval(&var.range.max)?;
+            if min > max
+                || max
+                    .checked_sub(min)
+                    .and_then(|d| d.checked_add(1))
+                    .is_none()
+            {
+                return Err(SymbolError {
+                    msg: format!(
+                        "The range of '{}' must be non-empty and fit in an integer, but it is [{} .. {}].",
+                        symb_name, min, max
+                    ),
+                    span: var.range.min.span.merge(var.range.max.span),
+                });
+            }
+            var.ir_range = min..=max;
+            let initial_span = match &var.initial_max {
+                Some(max) => var.initial_value.span.merge(max.span),
+                None => var.initial_value.span,
+            };
+            if var.ir_initial_value > var.ir_initial_max {
+                return Err(SymbolError {
+                    msg: format!(
+                        "The initial values of '{}' must be non-empty, but they are [{} .. {}].",
+                        symb_name, var.ir_initial_value, var.ir_initial_max
+                    ),
+                    span: initial_span,
+                });
+            }
+            for value in [var.ir_initial_value, var.ir_initial_max] {
+                if !var.ir_range.contains(&value) {
+                    return Err(SymbolError {
+                        msg: format!(
+                            "The initial value of '{}' is {}, which is outside its range [{} .. {}].",
+                            symb_name, value, min, max
+                        ),
+                        span: initial_span,
+                    });
+                }
+            }
+            var.next_value = SymbolChecker::new(symbols, owner.clone(), CheckMode::StateVarUpdate)
+                .check(&var.next_value)?;
+        }
+        DeclKind::Transition(tran) => {
+            tran.name = resolved_name;
+            tran.condition =
+                SymbolChecker::new(symbols, owner.clone(), CheckMode::LabelOrTransition)
+                    .check(&tran.condition)?;
+        }
+        DeclKind::Player(player) => {
+            player.name = resolved_name;
+        }
+        DeclKind::Template(template) => {
+            template.name = resolved_name;
+        }
+        DeclKind::Define(define) => {
+            // The body is checked where the macro is expanded
+            define.name = resolved_name;
+        }
+        DeclKind::Const(_) => {} // Needs no further reduction
+    }
+    Ok(())
+}
+
+/// Actions taken at the same time that update a variable to different values.
+/// See [IntermediateLCGS::check_update_conflicts].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UpdateConflict {
+    /// The name of the state in which the actions are taken
+    pub state: String,
+    /// The names of the actions taken by each player
+    pub actions: Vec<String>,
+    pub variable: String,
+    /// The actions updating the variable and the values they update it to
+    pub updates: Vec<(String, i32)>,
+}
+
+impl Display for UpdateConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let updates: Vec<String> = self
+            .updates
+            .iter()
+            .map(|(action, value)| format!("{} updates it to {}", action, value))
+            .collect();
+        write!(
+            f,
+            "Conflicting updates of '{}': {}.\nState: {}\nActions: ({})",
+            self.variable,
+            updates.join(", "),
+            self.state,
+            self.actions.join(", ")
+        )
+    }
+}
+
+/// An update of a variable to a value outside its range. See [IntermediateLCGS::check_ranges].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RangeViolation {
+    /// The name of the state in which the update happens
+    pub state: String,
+    /// The names of the actions taken by each player
+    pub actions: Vec<String>,
+    /// The name of the variable that is updated
+    pub variable: String,
+    /// The value outside of the range
+    pub value: i32,
+    pub range: RangeInclusive<i32>,
+}
+
+impl Display for RangeViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The update of '{}' results in {}, which is outside its range [{} .. {}].\nState: {}\nActions: ({})",
+            self.variable,
+            self.value,
+            self.range.start(),
+            self.range.end(),
+            self.state,
+            self.actions.join(", ")
+        )
+    }
+}
+
+/// A division or modulo by zero in a reachable state, which evaluates to 0.
+/// See [IntermediateLCGS::check_division_by_zero].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DivisionByZero {
+    /// The name of the state in which the division happens
+    pub state: String,
+    /// The names of the actions taken by each player, if the division happens in an update
+    pub actions: Option<Vec<String>>,
+    /// The location of the division in the program
+    pub span: Span,
+}
+
+impl Display for DivisionByZero {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Division by zero in a reachable state.\nState: {}",
+            self.state
+        )?;
+        if let Some(actions) = &self.actions {
+            write!(f, "\nActions: ({})", actions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A reachable state where some players have no available actions.
+/// See [IntermediateLCGS::check_deadlocks].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Deadlock {
+    /// The name of the deadlocked state
+    pub state: String,
+    /// The names of the players without available actions
+    pub players: Vec<String>,
+    /// The states on a shortest path from the initial state to the deadlocked state, and the
+    /// names of the actions taken in each of them
+    pub path: Vec<(String, Vec<String>)>,
+}
+
+impl Display for Deadlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No actions are available to {} in a reachable state.\nPath:",
+            self.players.join(", ")
+        )?;
+        for (state, actions) in &self.path {
+            write!(f, "\n  {}\n    ({})", state, actions.join(", "))?;
+        }
+        write!(f, "\n  {}", self.state)
+    }
+}
+
+/// A game structure state of an LCGS. Holds a mapping of symbol names to their current value
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct State(pub HashMap<SymbolIdentifier, i32>);
+
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        let len = self.0.len();
+        for (i, (symb_id, val)) in self.0.iter().enumerate() {
+            write!(f, "{}:{}", symb_id, val)?;
+            if i < len - 1 {
+                write!(f, ",")?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+impl GameStructure for IntermediateLCGS {
+    fn max_player(&self) -> usize {
+        self.players.len()
+    }
+
+    fn initial_state(&self) -> common::State {
+        self.initial_state_index()
+    }
+
+    /// Returns an initial state for each combination of the initial values of the state
+    /// variables. Initial states that are symmetric are only included once.
+    fn initial_states(&self) -> Vec<common::State> {
+        let mut states = vec![self.initial_values()];
+        for symb_id in &self.vars {
+            if let DeclKind::StateVar(var) = &self.symbols[symb_id].kind {
+                if var.ir_initial_max > var.ir_initial_value {
+                    states = states
+                        .into_iter()
+                        .flat_map(|state| {
+                            (var.ir_initial_value..=var.ir_initial_max).map(move |value| {
+                                let mut state = state.clone();
+                                state.0.insert(symb_id.clone(), value);
+                                state
+                            })
+                        })
+                        .collect();
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        states
+            .iter()
+            .map(|state| self.canonical_index(self.index_of_state(state)))
+            .filter(|index| seen.insert(*index))
+            .collect()
+    }
+
+    /// Returns the set of labels/propositions available in the given state.
+    fn labels(&self, state: common::State) -> HashSet<Proposition> {
+        let state = self.state_from_index(state);
+        let mut res = HashSet::new();
 
         // The labels id is their index in the self.labels vector
         for (i, symb_id) in self.labels.iter().enumerate() {
@@ -455,44 +1728,8 @@ impl GameStructure for IntermediateLCGS {
 
     /// Returns the next state given a current state and an action for each player.
     fn transitions(&self, state: common::State, choices: Vec<usize>) -> usize {
-        let mut state = self.state_from_index(state);
-        // To evaluate the next state we assign the actions to either 1 or 0 depending
-        // on whether or not the action was taken
-        for (p_index, player) in self.players.iter().enumerate() {
-            // The `choices` vector only considers available actions, so we do those first
-            let moves = self.available_actions(&state, p_index);
-            debug_assert!(
-                choices[p_index] < moves.len(),
-                format!(
-                    "Unknown action {} chosen for player {} in state {:?}",
-                    choices[p_index], p_index, state
-                )
-            );
-            for (a_index, a_symb_id) in moves.iter().enumerate() {
-                let val = if choices[p_index] == a_index { 1 } else { 0 };
-                state.0.insert(a_symb_id.clone(), val);
-            }
-
-            // Some actions might not be available. These should also be set to 0.
-            for action in &player.actions {
-                if !state.0.contains_key(action) {
-                    state.0.insert(action.clone(), 0);
-                }
-            }
-        }
-
-        // Now we can evaluate the next state based on previous state and the actions taken
-        let evaluator = Evaluator::new(&state);
-        let mut next_state = State(HashMap::new());
-        for symb_id in &self.vars {
-            let symb = self.symbols.get(symb_id).unwrap();
-            if let DeclKind::StateVar(var) = &symb.kind {
-                let val = evaluator.eval(&var.next_value);
-                next_state.0.insert(symb_id.clone(), val);
-            }
-        }
-
-        self.index_of_state(&next_state)
+        let state = self.state_from_index(state);
+        self.canonical_index(self.index_of_state(&self.successor(&state, &choices)))
     }
 
     /// Returns the number of moves available to each player in the given state.
@@ -501,12 +1738,37 @@ impl GameStructure for IntermediateLCGS {
         self.players
             .iter()
             .enumerate()
-            .map(|(i, _player)| self.available_actions(&state, i).len())
+            .map(
+                |(i, _player)| match self.available_actions(&state, i).len() {
+                    // The implicit idle action
+                    0 if self.deadlock_policy == DeadlockPolicy::Idle => 1,
+                    count => count,
+                },
+            )
             .collect()
     }
 
+    /// Returns the name of the given state, listing the values of all variables in order of
+    /// declaration. Booleans and enums are shown using their symbolic names.
     fn state_name(&self, state: common::State) -> String {
-        self.state_from_index(state).to_string()
+        let state = self.state_from_index(state);
+        let values: Vec<String> = self
+            .vars
+            .iter()
+            .map(|symb_id| {
+                let value = state.0[symb_id];
+                let value_name = match &self.symbols[symb_id].kind {
+                    DeclKind::StateVar(var) => var.var_type.value_name(value),
+                    _ => None,
+                };
+                format!(
+                    "{}:{}",
+                    symb_id,
+                    value_name.unwrap_or_else(|| value.to_string())
+                )
+            })
+            .collect();
+        format!("{{{}}}", values.join(","))
     }
 
     fn label_name(&self, proposition: Proposition) -> String {
@@ -520,14 +1782,18 @@ impl GameStructure for IntermediateLCGS {
     fn action_name(&self, state: common::State, player: common::Player, action: Action) -> String {
         let state = self.state_from_index(state);
         let actions = self.available_actions(&state, player);
+        if actions.is_empty() && self.deadlock_policy == DeadlockPolicy::Idle {
+            return format!("{}.<idle>", self.players[player].name);
+        }
         actions.get(action).unwrap().to_string()
     }
 }
 
 impl ATLExpressionParser for IntermediateLCGS {
     fn player_parser(&self) -> Parser<u8, common::Player> {
-        // In ATL, players are referred to using their name, i.e. an identifier
-        identifier().convert(move |name| {
+        // In ATL, players are referred to using their name, i.e. an identifier, possibly
+        // with an index if the player was declared in a loop
+        player_name().convert(move |name| {
             // We check if a declaration with the given name exists,
             // and that it is a player declaration
             let symbol = Owner::Global.symbol_id(&name);
@@ -550,7 +1816,7 @@ impl ATLExpressionParser for IntermediateLCGS {
         // In ATL, propositions are either "something" where "something" must be a label declared
         // in the global scope, or "someone.something" where "something" is a label owned by
         // a player of name "someone".
-        let parser = identifier() + (sym(b'.') * identifier()).opt();
+        let parser = player_name() + (sym(b'.') * identifier()).opt();
         parser.convert(move |(name_or_owner, name)| {
             // We infer whether the label should be found in local (player) or global scope.
             // full_name is used for error descriptions.
@@ -584,13 +1850,20 @@ impl ATLExpressionParser for IntermediateLCGS {
 
 #[cfg(test)]
 mod test {
-    use crate::atl::gamestructure::GameStructure;
+    use crate::atl::dependencygraph::{ATLDependencyGraph, ATLVertex};
+    use crate::atl::formula::{parse_phi, Phi};
+    use crate::atl::gamestructure::{GameStructure, MoveIterator};
+    use crate::common::VertexAssignment;
+    use crate::edg::distributed_certain_zero;
     use crate::lcgs::ast::DeclKind;
-    use crate::lcgs::ir::intermediate::{IntermediateLCGS, State};
+    use crate::lcgs::ir::error::Error;
+    use crate::lcgs::ir::intermediate::{DeadlockPolicy, IntermediateLCGS, State};
     use crate::lcgs::ir::symbol_table::Owner;
     use crate::lcgs::ir::symbol_table::SymbolIdentifier;
     use crate::lcgs::parse::parse_lcgs;
-    use std::collections::HashMap;
+    use crate::search_strategy::bfs::BreadthFirstSearchBuilder;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Arc;
 
     #[test]
     fn test_symbol_01() {
@@ -1039,6 +2312,61 @@ mod test {
         assert_eq!(1, lcgs.initial_state_index());
     }
 
+    #[test]
+    fn test_initial_states_01() {
+        // A range of initial values gives an initial state for each combination of values
+        let input = "
+        x : [0 .. 3] init 1 .. 3;
+        y : [0 .. 1] init 0 .. 1;
+        label big = x >= 2;
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let initial_states = lcgs.initial_states();
+        assert_eq!(initial_states.len(), 6);
+        assert!(initial_states.contains(&lcgs.initial_state_index()));
+        let big = initial_states
+            .iter()
+            .filter(|state| lcgs.labels(**state).contains(&0))
+            .count();
+        assert_eq!(big, 4);
+
+        // The range of initial values must be non-empty
+        let input = "x : [0 .. 3] init 2 .. 1;";
+        assert!(IntermediateLCGS::create(parse_lcgs(input).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_set_initial_state_01() {
+        // A state given by the values of some variables can replace the initial states
+        let input = "
+        player p1 = shooter;
+        player p2 = shooter;
+        template shooter
+            health : [0 .. 2] init 1 .. 2;
+            [wait] 1;
+        endtemplate
+        ";
+        let mut lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        assert_eq!(lcgs.initial_states().len(), 4);
+        let state = lcgs
+            .state_from_values(&[("p1.health".to_string(), 0)])
+            .unwrap();
+        let p1_health = Owner::Player("p1".to_string()).symbol_id("health");
+        let p2_health = Owner::Player("p2".to_string()).symbol_id("health");
+        assert_eq!(state.0[&p1_health], 0);
+        assert_eq!(state.0[&p2_health], 1);
+        lcgs.set_initial_state(&state);
+        assert_eq!(lcgs.initial_states(), vec![lcgs.index_of_state(&state)]);
+
+        // Unknown variables and values outside the range are errors
+        assert!(lcgs
+            .state_from_values(&[("p3.health".to_string(), 1)])
+            .is_err());
+        assert!(lcgs
+            .state_from_values(&[("p1.health".to_string(), 3)])
+            .is_err());
+    }
+
     /// Helper function to get the index of a player with the given name
     fn get_player_index(lcgs: &IntermediateLCGS, player_name: &str) -> usize {
         let symbol = lcgs
@@ -1068,4 +2396,850 @@ mod test {
         assert_eq!(get_player_index(&lcgs, "p2"), 1usize);
         assert_eq!(get_player_index(&lcgs, "p3"), 2usize);
     }
+
+    #[test]
+    fn test_modulo_transition_01() {
+        // A round-robin counter using modulo
+        let input = "
+        const n = 3;
+        turn : [0 .. n - 1] init 0;
+        turn' = (turn + 1) % n;
+        player p = something;
+        template something
+            [wait] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        assert_eq!(1, lcgs.transitions(0, vec![0]));
+        assert_eq!(2, lcgs.transitions(1, vec![0]));
+        assert_eq!(0, lcgs.transitions(2, vec![0]));
+    }
+
+    #[test]
+    fn test_abs_pow_clamp_const_01() {
+        // Constant expressions using abs, pow, and clamp are folded during symbol checking
+        let input = "
+        const a = abs(2 - 7);
+        const b = pow(2, a);
+        const c = clamp(b, 0, 20);
+        foo : [0 .. c] init c;
+        foo' = foo;
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let decl = lcgs.get_decl(&":global.foo".into()).unwrap();
+        if let DeclKind::StateVar(var) = &decl.kind {
+            assert_eq!(var.ir_range, 0..=20);
+            assert_eq!(var.ir_initial_value, 20);
+        } else {
+            panic!("Symbol ':global.foo' is not a state variable")
+        }
+    }
+
+    #[test]
+    fn test_division_by_zero_01() {
+        // Division and modulo by a constant zero are rejected
+        let input1 = "
+        const zero = 0;
+        const foo = 5 / zero;
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input1).unwrap()).is_err());
+        let input2 = "
+        bar : [0 .. 3] init 0;
+        bar' = (bar + 1) % 0;
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input2).unwrap()).is_err());
+        let input3 = "
+        const foo = pow(0, -1);
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input3).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_clamp_bounds_01() {
+        // The bounds of clamp must be ordered when both are constant
+        let input = "
+        bar : [0 .. 3] init 0;
+        bar' = clamp(bar + 1, 3, 0);
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_bool_var_01() {
+        // Boolean variables are 0 or 1 and any non-zero update value is true
+        let input = "
+        flag : bool init false;
+        flag' = p.set ? 5 : false;
+        label raised = flag;
+        player p = something;
+        template something
+            [set] !flag;
+            [reset] true;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        assert_eq!(0, lcgs.initial_state_index());
+        assert_eq!(1, lcgs.transitions(0, vec![0]));
+        assert_eq!(0, lcgs.transitions(1, vec![0]));
+        assert!(lcgs.labels(1).contains(&0));
+        assert_eq!("{:global.flag:true}", lcgs.state_name(1));
+    }
+
+    #[test]
+    fn test_enum_var_01() {
+        // Enum variants can be used in expressions and are printed symbolically
+        let input = "
+        player p = process;
+        template process
+            phase : {idle, trying, critical} init idle;
+            phase' = enter ? (phase == idle ? trying : critical) : (leave ? idle : phase);
+            label in_cs = phase == critical;
+            [enter] phase != critical;
+            [leave] phase == critical;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        assert!(lcgs.symbols.contains_key(&":global.critical".into()));
+        let state = lcgs.initial_state_index();
+        assert_eq!("{p.phase:idle}", lcgs.state_name(state));
+        let state = lcgs.transitions(state, vec![0]);
+        assert_eq!("{p.phase:trying}", lcgs.state_name(state));
+        let state = lcgs.transitions(state, vec![0]);
+        assert_eq!("{p.phase:critical}", lcgs.state_name(state));
+        assert!(lcgs.labels(state).contains(&0));
+        let state = lcgs.transitions(state, vec![0]);
+        assert_eq!("{p.phase:idle}", lcgs.state_name(state));
+    }
+
+    #[test]
+    fn test_enum_var_02() {
+        // Enums can share variants if they have the same value
+        let input1 = "
+        a : {off, on} init off;
+        a' = a;
+        b : {off, on, broken} init broken;
+        b' = b;
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input1).unwrap()).is_ok());
+        let input2 = "
+        a : {off, on} init off;
+        a' = a;
+        b : {on, off} init off;
+        b' = b;
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input2).unwrap()).is_err());
+        let input3 = "
+        const on = 3;
+        a : {off, on} init off;
+        a' = a;
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input3).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_define_01() {
+        // Global and template macros are expanded, and template macros are relabeled
+        let input = "
+        const max_health = 2;
+        define alive(p) = p.health > 0;
+        define hit(target, a) = target.health > 0 && a;
+        label all_alive = alive(p1) && alive(p2);
+        player p1 = cowboy [enemy=p2];
+        player p2 = cowboy [enemy=p1];
+        template cowboy
+            define lower(x) = max(x - 1, 0);
+            health : [0 .. max_health] init max_health;
+            health' = enemy.shoot ? lower(health) : health;
+            [shoot] hit(enemy, alive(enemy)) && health > 0;
+            [wait] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let state = lcgs.initial_state_index();
+        assert!(lcgs.labels(state).contains(&0));
+        assert_eq!(lcgs.move_count(state), vec![2, 2]);
+        let state = lcgs.transitions(state, vec![0, 0]);
+        let state = lcgs.transitions(state, vec![0, 0]);
+        assert!(!lcgs.labels(state).contains(&0));
+        assert_eq!(lcgs.move_count(state), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_define_02() {
+        // Wrong number of arguments and recursive macros are errors
+        let input = "
+        define f(a) = a + 1;
+        define g(a) = g(a);
+        label l1 = f(1, 2) > 0;
+        label l2 = g(1) > 0;
+        ";
+        let err = IntermediateLCGS::create(parse_lcgs(input).unwrap())
+            .err()
+            .unwrap();
+        let msg = err.message();
+        assert!(msg.contains("The macro 'f' expects 1 argument(s), but 2 were given."));
+        assert!(msg.contains("expands to a call of itself."));
+    }
+
+    #[test]
+    fn test_array_var_01() {
+        // Arrays are expanded to a variable per element, which are updated individually
+        let input = "
+        const n = 3;
+        cells : [0 .. 2][n] init 0;
+        cells[c]' = p.play == c + 1 ? 1 : cells[c];
+        pos : [0 .. n - 1] init 2;
+        pos' = pos;
+        label pos_taken = cells[pos] != 0;
+        player p = something;
+        template something
+            play : [0 .. n] init 0;
+            play' = mark ? pos + 1 : 0;
+            [mark] cells[pos] == 0;
+            [wait] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        assert!(lcgs.symbols.contains_key(&":global.cells[0]".into()));
+        assert!(lcgs.symbols.contains_key(&":global.cells[2]".into()));
+        assert!(!lcgs.symbols.contains_key(&":global.cells[3]".into()));
+        let state = lcgs.initial_state_index();
+        assert!(!lcgs.labels(state).contains(&0));
+        // Mark, and then the cell is updated in the following step
+        let state = lcgs.transitions(state, vec![0]);
+        let state = lcgs.transitions(state, vec![1]);
+        assert!(lcgs.labels(state).contains(&0));
+        assert_eq!(lcgs.move_count(state), vec![1]);
+        let values = lcgs.state_from_index(state).0;
+        assert_eq!(values[&":global.cells[0]".into()], 0);
+        assert_eq!(values[&":global.cells[2]".into()], 1);
+    }
+
+    #[test]
+    fn test_array_var_02() {
+        // Arrays in templates are owned by the player and can be indexed with an owner
+        let input = "
+        label done = p.flags[0] && p.flags[1];
+        player p = something [size=2];
+        template something
+            flags : bool[size] init false;
+            flags[i]' = set == i + 1 || flags[i];
+            set : [0 .. 2] init 1;
+            set' = set == 1 ? 2 : 0;
+            [wait] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let state = lcgs.initial_state_index();
+        let state = lcgs.transitions(state, vec![0]);
+        let state = lcgs.transitions(state, vec![0]);
+        assert!(lcgs.labels(state).contains(&0));
+        assert_eq!(
+            lcgs.state_name(state),
+            "{p.flags[0]:true,p.flags[1]:true,p.set:0}"
+        );
+    }
+
+    #[test]
+    fn test_array_var_03() {
+        // Constant indexes must be within bounds
+        let input = "
+        cells : [0 .. 2][3] init 0;
+        cells[c]' = cells[c + 1];
+        ";
+        assert!(IntermediateLCGS::create(parse_lcgs(input).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_player_loop_01() {
+        // Players declared in a loop can refer to each other using index arithmetic
+        let input = "
+        const n = 3;
+        player p[i in 0 .. n - 1] = shooter [target=p[(i + 1) % n], attacker=p[(i - 1) % n], id=i];
+        label p0_alive = p[0].health > 0;
+        template shooter
+            health : [0 .. 1] init 1;
+            health' = attacker.shoot ? 0 : health;
+            label alive = health > 0;
+            label named = id == 2;
+            [wait] 1;
+            [shoot] health > 0 && target.health > 0;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        assert_eq!(lcgs.max_player(), 3);
+        assert_eq!(get_player_index(&lcgs, "p[0]"), 0usize);
+        assert_eq!(get_player_index(&lcgs, "p[2]"), 2usize);
+        assert!(lcgs.symbols.contains_key(&"p[1].health".into()));
+
+        // p[2] shoots p[0]
+        let state = lcgs.transitions(lcgs.initial_state_index(), vec![0, 0, 1]);
+        assert_eq!(
+            lcgs.state_name(state),
+            "{p[0].health:0,p[1].health:1,p[2].health:1}"
+        );
+        assert!(!lcgs.labels(state).contains(&0));
+
+        // The index is available as a number
+        let named = get_label_index(&lcgs, "p[2].named");
+        assert!(lcgs.labels(state).contains(&named));
+    }
+
+    #[test]
+    fn test_player_loop_02() {
+        // Players declared in loops can be used in ATL formulas
+        let input = "
+        player p[i in 1 .. 2] = something;
+        template something
+            label yes = 1;
+            [wait] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let phi = parse_phi(&lcgs, "<<p[2]>> F p[1].yes").unwrap();
+        assert_eq!(
+            phi,
+            Phi::EnforceEventually {
+                players: vec![1],
+                formula: Arc::new(Phi::Proposition(0)),
+            }
+        );
+        assert!(parse_phi(&lcgs, "<<p[3]>> F p[1].yes").is_err());
+    }
+
+    #[test]
+    fn test_override_const_01() {
+        // Overridden constants are used everywhere, including other constants
+        let input = "
+        const max_health = 1;
+        const double_health = max_health * 2;
+        health : [0 .. double_health] init max_health;
+        health' = health;
+        ";
+        let mut root = parse_lcgs(input).unwrap();
+        root.override_const("max_health", 3).unwrap();
+        let lcgs = IntermediateLCGS::create(root).unwrap();
+        let decl = lcgs.get_decl(&":global.health".into()).unwrap();
+        if let DeclKind::StateVar(var) = &decl.kind {
+            assert_eq!(var.ir_range, 0..=6);
+            assert_eq!(var.ir_initial_value, 3);
+        } else {
+            panic!("Symbol ':global.health' is not a state variable")
+        }
+    }
+
+    #[test]
+    fn test_override_const_02() {
+        // Only declared constants can be overridden
+        let input = "
+        const max_health = 1;
+        health : [0 .. max_health] init max_health;
+        health' = health;
+        ";
+        let mut root = parse_lcgs(input).unwrap();
+        assert!(root.override_const("min_health", 3).is_err());
+        assert!(root.override_const("health", 3).is_err());
+    }
+
+    #[test]
+    fn test_error_span_01() {
+        // Errors point at the offending expression, also inside templates
+        let input = "player p1 = t [];\ntemplate t\n    x : [0 .. 1] init 0;\n    x' = x + bar;\nendtemplate\n";
+        let err = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!(&input[span.begin..span.end], "bar");
+    }
+
+    #[test]
+    fn test_error_span_02() {
+        // All errors are reported, ordered by their position in the program
+        let input = "
+        const a = 1 / 0;
+        label b = foo > a;
+        player p1 = t [x=2 + 3];
+        template t
+            x : [0 .. 1] init 0;
+            x' = x;
+        endtemplate
+        ";
+        let err = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap_err();
+        if let Error::Multiple(errors) = &err {
+            let spans: Vec<&str> = errors
+                .iter()
+                .map(|e| {
+                    let span = e.span().unwrap();
+                    &input[span.begin..span.end]
+                })
+                .collect();
+            assert_eq!(spans, vec!["1 / 0", "foo", "2 + 3"]);
+        } else {
+            panic!("Expected multiple errors, got: {}", err)
+        }
+    }
+
+    #[test]
+    fn test_range_policy_01() {
+        // Clamped and wrapped variables stay in range
+        let input = "
+        player p = t [];
+        template t
+            c : [0 .. 2] init 2 overflow clamp;
+            c' = c + 1;
+            w : [0 .. 2] init 2 overflow wrap;
+            w' = w + 1;
+            [wait] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let next = lcgs.transitions(lcgs.initial_state_index(), vec![0]);
+        let state = lcgs.state_from_index(next);
+        assert_eq!(state.0[&"p.c".into()], 2);
+        assert_eq!(state.0[&"p.w".into()], 0);
+        assert!(lcgs.check_ranges().is_ok());
+    }
+
+    #[test]
+    fn test_range_policy_02() {
+        // Wrapping also works below the range
+        let input = "
+        x : [-1 .. 1] init -1 overflow wrap;
+        x' = x - 4;
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let next = lcgs.transitions(lcgs.initial_state_index(), vec![]);
+        assert_eq!(lcgs.state_from_index(next).0[&":global.x".into()], 1);
+    }
+
+    #[test]
+    fn test_check_ranges_01() {
+        // The violation is found in a reachable state, not the initial one
+        let input = "
+        player p = t [];
+        template t
+            health : [0 .. 2] init 2;
+            health' = health - shoot;
+            [wait] 1;
+            [shoot] 1;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let violation = lcgs.check_ranges().unwrap_err();
+        assert_eq!(violation.variable, "p.health");
+        assert_eq!(violation.value, -1);
+        assert_eq!(violation.state, "{p.health:0}");
+        assert_eq!(violation.actions, vec!["p.shoot".to_string()]);
+    }
+
+    #[test]
+    fn test_update_commands_01() {
+        // Actions update variables, which otherwise keep their value
+        let input = "
+        player p = chooser [];
+        template chooser
+            q0 : bool init true;
+            q0' = q0;
+            choice : [0 .. 2] init 0;
+            [wait] 1;
+            [one] q0 -> choice' = 1 & q0' = false;
+            [two] q0 -> choice' = 2;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let initial = lcgs.initial_state_index();
+        let waited = lcgs.transitions(initial, vec![0]);
+        assert_eq!(waited, initial);
+        let state = lcgs.state_from_index(lcgs.transitions(initial, vec![1]));
+        assert_eq!(state.0[&"p.choice".into()], 1);
+        assert_eq!(state.0[&"p.q0".into()], 0);
+        let state = lcgs.state_from_index(lcgs.transitions(initial, vec![2]));
+        assert_eq!(state.0[&"p.choice".into()], 2);
+        assert_eq!(state.0[&"p.q0".into()], 1);
+        assert!(lcgs.check_update_conflicts().is_ok());
+    }
+
+    #[test]
+    fn test_update_commands_02() {
+        // Updates of other players' variables are checked in the scope of the acting player
+        let input = "
+        player p1 = shooter [enemy=p2];
+        player p2 = shooter [enemy=p1];
+        template shooter
+            health : [0 .. 2] init 2;
+            dmg : [1 .. 2] init 1;
+            dmg' = dmg;
+            [wait] 1;
+            [shoot] health > 0 -> enemy.health' = max(enemy.health - dmg, 0);
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let state = lcgs.transitions(lcgs.initial_state_index(), vec![1, 0]);
+        let values = lcgs.state_from_index(state).0;
+        assert_eq!(values[&"p1.health".into()], 2);
+        assert_eq!(values[&"p2.health".into()], 1);
+    }
+
+    #[test]
+    fn test_update_commands_03() {
+        // Actions taken at the same time must not update a variable to different values
+        let input = "
+        turn : [0 .. 2] init 0;
+        player p1 = claimer [id=1];
+        player p2 = claimer [id=2];
+        template claimer
+            [wait] 1;
+            [claim] turn == 0 -> turn' = id;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let conflict = lcgs.check_update_conflicts().unwrap_err();
+        assert_eq!(conflict.variable, ":global.turn");
+        assert_eq!(
+            conflict.updates,
+            vec![("p1.claim".to_string(), 1), ("p2.claim".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_update_commands_04() {
+        // An action cannot update a variable twice or update something that is not a variable
+        let input = "
+        const c = 1;
+        player p = t [];
+        template t
+            x : [0 .. 2] init 0;
+            [a] x' = 1 & x' = 2;
+            [b] 1 -> c' = 2;
+        endtemplate
+        ";
+        let err = IntermediateLCGS::create(parse_lcgs(input).unwrap())
+            .err()
+            .unwrap();
+        let msg = err.message();
+        assert!(msg.contains("The action 'p.a' updates 'p.x' more than once."));
+        assert!(msg.contains("Only state variables can be updated, but ':global.c' is not"));
+    }
+
+    /// Includes the malformed programs from the corpus in `lcgs-examples/malformed`. The first
+    /// line of each program is a comment with a part of the expected error message.
+    macro_rules! malformed {
+        ($($file:expr),* $(,)?) => {
+            vec![$(($file, include_str!(concat!("../../../../lcgs-examples/malformed/", $file)))),*]
+        };
+    }
+
+    #[test]
+    fn test_check_division_by_zero_01() {
+        // Divisions by zero are found in updates and guards, but not in branches not taken
+        let template = |update: &str, guard: &str| {
+            format!(
+                "
+        player p = t [];
+        template t
+            x : [0 .. 2] init 2;
+            x' = max(x - dec, 0);
+            y : [0 .. 6] init 1;
+            y' = {};
+            [wait] 1;
+            [dec] {};
+        endtemplate
+        ",
+                update, guard
+            )
+        };
+
+        let input = template("6 / x", "1");
+        let lcgs = IntermediateLCGS::create(parse_lcgs(&input).unwrap()).unwrap();
+        let division = lcgs.check_division_by_zero().unwrap_err();
+        assert_eq!(&input[division.span.begin..division.span.end], "6 / x");
+        assert_eq!(division.state, "{p.x:0,p.y:6}");
+        assert_eq!(division.actions, Some(vec!["p.wait".to_string()]));
+
+        let input = template("y", "6 % x == 0");
+        let lcgs = IntermediateLCGS::create(parse_lcgs(&input).unwrap()).unwrap();
+        let division = lcgs.check_division_by_zero().unwrap_err();
+        assert_eq!(&input[division.span.begin..division.span.end], "6 % x");
+        assert_eq!(division.actions, None);
+
+        let input = template("x > 0 ? 6 / x : 0", "1");
+        let lcgs = IntermediateLCGS::create(parse_lcgs(&input).unwrap()).unwrap();
+        assert!(lcgs.check_division_by_zero().is_ok());
+    }
+
+    #[test]
+    fn test_malformed_corpus() {
+        // Malformed programs must produce errors instead of panics
+        let corpus = malformed![
+            "array_index_out_of_bounds.lcgs",
+            "division_by_zero.lcgs",
+            "duplicate_action.lcgs",
+            "duplicate_const.lcgs",
+            "duplicate_label.lcgs",
+            "duplicate_player.lcgs",
+            "duplicate_player_in_loop.lcgs",
+            "duplicate_template_var.lcgs",
+            "empty_range.lcgs",
+            "enum_variant_conflict.lcgs",
+            "initial_value_out_of_range.lcgs",
+            "integer_overflow.lcgs",
+            "macro_wrong_arity.lcgs",
+            "non_constant_range.lcgs",
+            "not_a_macro.lcgs",
+            "not_a_template.lcgs",
+            "player_shadows_const.lcgs",
+            "recursive_macro.lcgs",
+            "relabel_name_to_expression.lcgs",
+            "self_referencing_label.lcgs",
+            "unknown_identifier.lcgs",
+            "unknown_identifier_in_min.lcgs",
+            "unknown_player.lcgs",
+            "unknown_template.lcgs",
+        ];
+        for (file, program) in corpus {
+            let expected = program
+                .lines()
+                .next()
+                .and_then(|line| line.strip_prefix("// expect: "))
+                .unwrap_or_else(|| panic!("{} has no expected error", file));
+            let root = parse_lcgs(program).unwrap_or_else(|_| panic!("{} failed to parse", file));
+            match IntermediateLCGS::create(root) {
+                Ok(_) => panic!("{} was accepted", file),
+                Err(err) => assert!(
+                    err.to_string().contains(expected),
+                    "{} produced the wrong error: {}",
+                    file,
+                    err
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_expanded_01() {
+        // The expanded program shows the relabeled declarations of each player
+        let input = "
+        const max_health = 2;
+        define hurt(x) = x.health < max_health;
+        rounds : [0 .. 3] init 0;
+        rounds' = rounds + 1;
+        player p1 = shooter [enemy=p2];
+        player p2 = shooter [enemy=p1];
+        template shooter
+            health : [0 .. max_health] init max_health;
+            label dying = hurt(enemy);
+            [wait] 1;
+            [shoot] health > 0 -> enemy.health' = max(enemy.health - 1, 0);
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let expected = "\
+// Global
+rounds : [0 .. 3] init 0;
+rounds' = rounds + 1;
+
+// Player p1
+health : [0 .. 2] init 2;
+health' = p2.shoot ? max(p1.health - 1, 0) : p1.health;
+
+label dying = p2.health < 2;
+
+[wait] 1;
+[shoot] p1.health > 0;
+
+// Player p2
+health : [0 .. 2] init 2;
+health' = p1.shoot ? max(p2.health - 1, 0) : p2.health;
+
+label dying = p1.health < 2;
+
+[wait] 1;
+[shoot] p2.health > 0;
+";
+        assert_eq!(lcgs.format_expanded(), expected);
+    }
+
+    #[test]
+    fn test_deadlock_01() {
+        // The counter stops once it reaches 2, since no actions are available
+        let input = "
+        player p = counter;
+        template counter
+            x : [0 .. 3] init 0;
+            x' = x + inc;
+            [inc] x < 2;
+        endtemplate
+        ";
+        let mut lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        lcgs.set_deadlock_policy(DeadlockPolicy::Allow);
+        let deadlock = lcgs.check_deadlocks().unwrap_err();
+        assert_eq!(deadlock.players, vec!["p".to_string()]);
+        assert_eq!(deadlock.state, "{p.x:2}");
+        assert_eq!(
+            deadlock.path,
+            vec![
+                ("{p.x:0}".to_string(), vec!["p.inc".to_string()]),
+                ("{p.x:1}".to_string(), vec!["p.inc".to_string()]),
+            ]
+        );
+
+        // With the idle policy, the player idles and the state loops
+        lcgs.set_deadlock_policy(DeadlockPolicy::Idle);
+        assert!(lcgs.check_deadlocks().is_ok());
+        let state = lcgs.index_of_state(&State(vec![("p.x".into(), 2)].into_iter().collect()));
+        assert_eq!(lcgs.move_count(state), vec![1]);
+        assert_eq!(lcgs.transitions(state, vec![0]), state);
+        assert_eq!(lcgs.action_name(state, 0, 0), "p.<idle>");
+    }
+
+    #[test]
+    fn test_deadlock_02() {
+        // A deadlock in the initial state has an empty path
+        let input = "
+        player p1 = thing [on=1];
+        player p2 = thing [on=0];
+        template thing
+            [act] on;
+        endtemplate
+        ";
+        let mut lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        lcgs.set_deadlock_policy(DeadlockPolicy::Error);
+        let deadlock = lcgs.check_deadlocks().unwrap_err();
+        assert_eq!(deadlock.players, vec!["p2".to_string()]);
+        assert!(deadlock.path.is_empty());
+    }
+
+    #[test]
+    fn test_cone_of_influence_01() {
+        // The clock does not influence the label 'won' nor any guard, so it is removed
+        let input = "
+        clock : [0 .. 9] init 0;
+        clock' = (clock + 1) % 10;
+        player p1 = thing [target=p2];
+        player p2 = thing [target=p1];
+        template thing
+            score : [0 .. 3] init 0;
+            score' = min(score + hit, 3);
+            health : [0 .. 3] init 3;
+            health' = max(health - target.hit, 0);
+            label won = score == 3;
+            [wait] 1;
+            [hit] health > 0;
+        endtemplate
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let phi = parse_phi(&lcgs, "<<p1>> F p1.won").unwrap();
+        let reduced = lcgs.cone_of_influence(&phi.propositions().into_iter().collect());
+        assert_eq!(
+            reduced.vars,
+            vec![
+                Owner::Player("p1".into()).symbol_id("score"),
+                Owner::Player("p1".into()).symbol_id("health"),
+                Owner::Player("p2".into()).symbol_id("health"),
+            ]
+        );
+        assert_eq!(
+            reduced.state_name(reduced.initial_state_index()),
+            "{p1.score:0,p1.health:3,p2.health:3}"
+        );
+
+        // The result of model checking is unchanged
+        let check = |lcgs: IntermediateLCGS| {
+            let graph = ATLDependencyGraph {
+                game_structure: lcgs,
+            };
+            let v0 = ATLVertex::FULL {
+                state: graph.game_structure.initial_state_index(),
+                formula: Arc::new(phi.clone()),
+            };
+            distributed_certain_zero(graph, v0, 2, BreadthFirstSearchBuilder)
+        };
+        assert_eq!(check(lcgs), check(reduced));
+    }
+
+    #[test]
+    fn test_symmetry_reduction_01() {
+        // Four identical coins. The reduced game only distinguishes states by the number of
+        // heads, and the results of model checking are unchanged.
+        let input = "
+        player p1 = coin;
+        player p2 = coin;
+        player p3 = coin;
+        player p4 = coin;
+        template coin
+            heads : [0 .. 1] init 0;
+            heads' = flip ? 1 - heads : heads;
+            [flip] 1;
+            [keep] 1;
+        endtemplate
+        label two_heads = p1.heads + p2.heads + p3.heads + p4.heads == 2;
+        label all_heads = p1.heads + p2.heads + p3.heads + p4.heads == 4;
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+
+        fn reachable(lcgs: &IntermediateLCGS) -> usize {
+            let mut visited = HashSet::new();
+            let mut queue = vec![lcgs.initial_state_index()];
+            while let Some(state) = queue.pop() {
+                if visited.insert(state) {
+                    for choices in MoveIterator::new(lcgs.move_count(state)) {
+                        queue.push(lcgs.transitions(state, choices));
+                    }
+                }
+            }
+            visited.len()
+        }
+        fn check(lcgs: IntermediateLCGS, phi: &Phi) -> VertexAssignment {
+            let graph = ATLDependencyGraph {
+                game_structure: lcgs,
+            };
+            let v0 = ATLVertex::FULL {
+                state: graph.game_structure.initial_state_index(),
+                formula: Arc::new(phi.clone()),
+            };
+            distributed_certain_zero(graph, v0, 2, BreadthFirstSearchBuilder)
+        }
+
+        for formula in &[
+            "<<>> F all_heads",
+            "<<p1, p2, p3, p4>> F all_heads",
+            "<<p1, p2>> G !all_heads",
+            "<<p1, p2, p3>> X two_heads",
+        ] {
+            let phi = parse_phi(&lcgs, formula).unwrap();
+            let reduced = lcgs.symmetry_reduction(&phi);
+            assert!(reduced.symmetry_count() > 0);
+            assert!(reachable(&reduced) < reachable(&lcgs));
+            assert_eq!(
+                check(reduced, &phi),
+                check(lcgs.clone(), &phi),
+                "{}",
+                formula
+            );
+        }
+        let phi = parse_phi(&lcgs, "<<>> F all_heads").unwrap();
+        assert_eq!(reachable(&lcgs.symmetry_reduction(&phi)), 5);
+    }
+
+    #[test]
+    fn test_symmetry_reduction_02() {
+        // Symmetric initial states are only included once
+        let input = "
+        player p1 = shooter;
+        player p2 = shooter;
+        template shooter
+            health : [0 .. 2] init 1 .. 2;
+            [wait] 1;
+        endtemplate
+        label any_dead = p1.health == 0 || p2.health == 0;
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let phi = parse_phi(&lcgs, "<<>> F any_dead").unwrap();
+        let reduced = lcgs.symmetry_reduction(&phi);
+        let initial_states = reduced.initial_states();
+        assert_eq!(lcgs.initial_states().len(), 4);
+        assert_eq!(initial_states.len(), 3);
+        for state in lcgs.initial_states() {
+            assert!(initial_states.contains(&reduced.canonical_state(state)));
+            assert_eq!(lcgs.canonical_state(state), state);
+        }
+    }
 }
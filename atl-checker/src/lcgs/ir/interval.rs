@@ -0,0 +1,584 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use crate::lcgs::ast::{
+    int_pow, BinaryOpKind, Decl, DeclKind, Expr, ExprKind, Identifier, RangePolicy, StateVarDecl,
+    UnaryOpKind, VarType,
+};
+use crate::lcgs::ir::symbol_table::SymbolIdentifier;
+
+/// The number of rounds before bounds that keep growing are widened to the declared range
+const WIDENING_DELAY: usize = 16;
+/// The maximum number of rounds used to tighten the bounds again after widening
+const NARROWING_ROUNDS: usize = 16;
+
+/// A non-empty set of integers from `min` to `max`, both inclusive. Bounds are 64 bit, such
+/// that operations on 32 bit values cannot overflow. Intervals that exceed the 32 bit range
+/// are replaced by the full 32 bit range, since the evaluation of such expressions overflows.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Interval {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Interval {
+    pub fn new(min: i64, max: i64) -> Interval {
+        debug_assert!(min <= max, "Empty interval [{} .. {}]", min, max);
+        if min < i32::MIN as i64 || max > i32::MAX as i64 {
+            Interval::top()
+        } else {
+            Interval { min, max }
+        }
+    }
+
+    pub fn point(value: i32) -> Interval {
+        Interval::new(value as i64, value as i64)
+    }
+
+    /// The interval of all 32 bit values
+    pub fn top() -> Interval {
+        Interval {
+            min: i32::MIN as i64,
+            max: i32::MAX as i64,
+        }
+    }
+
+    /// The interval of booleans, where `None` means either
+    fn bool(truth: Option<bool>) -> Interval {
+        match truth {
+            Some(truth) => Interval::point(truth as i32),
+            None => Interval::new(0, 1),
+        }
+    }
+
+    /// Returns the interval of the values of a range
+    pub fn from_range(range: &RangeInclusive<i32>) -> Interval {
+        Interval::new(*range.start() as i64, *range.end() as i64)
+    }
+
+    pub fn to_range(self) -> RangeInclusive<i32> {
+        self.min as i32..=self.max as i32
+    }
+
+    /// The smallest interval containing both intervals
+    pub fn join(self, other: Interval) -> Interval {
+        Interval::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// The common values of both intervals, if any
+    pub fn meet(self, other: Interval) -> Option<Interval> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        if min <= max {
+            Some(Interval::new(min, max))
+        } else {
+            None
+        }
+    }
+
+    /// Whether values in the interval are true, i.e. non-zero. `None` means that some are.
+    fn truth(self) -> Option<bool> {
+        if self.min == 0 && self.max == 0 {
+            Some(false)
+        } else if self.min > 0 || self.max < 0 {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// The interval of applying a function to all combinations of the bounds of both
+    /// intervals. Only valid for functions that are monotone in each argument.
+    fn corners(self, other: Interval, f: impl Fn(i64, i64) -> i64) -> Interval {
+        let values = [
+            f(self.min, other.min),
+            f(self.min, other.max),
+            f(self.max, other.min),
+            f(self.max, other.max),
+        ];
+        Interval::new(*values.iter().min().unwrap(), *values.iter().max().unwrap())
+    }
+}
+
+/// The bounds of the values of the state variables. Actions are not included.
+type Env = HashMap<SymbolIdentifier, Interval>;
+
+/// A [RangeAnalysis] computes sound bounds of the values each state variable can take in
+/// reachable states by abstract interpretation of the update expressions over intervals.
+/// Starting from the initial values, the bounds of each variable are extended by the bounds
+/// of its update expression until nothing changes. Conditions of `?:` expressions that compare
+/// a variable are used to restrict its bounds in each branch, such that bounded counters like
+/// "`x' = x < 10 ? x + 1 : x`" are found to stay in `[0 .. 10]`.
+pub struct RangeAnalysis<'a> {
+    symbols: &'a HashMap<SymbolIdentifier, Decl>,
+    vars: &'a [SymbolIdentifier],
+}
+
+impl<'a> RangeAnalysis<'a> {
+    pub fn new(
+        symbols: &'a HashMap<SymbolIdentifier, Decl>,
+        vars: &'a [SymbolIdentifier],
+    ) -> RangeAnalysis<'a> {
+        RangeAnalysis { symbols, vars }
+    }
+
+    /// Returns the bounds of each state variable. They are always within the declared range.
+    pub fn run(&self) -> HashMap<SymbolIdentifier, RangeInclusive<i32>> {
+        let initial: Env = self
+            .vars
+            .iter()
+            .map(|symb_id| {
+                let var = self.var(symb_id);
+                let initial = Interval::new(var.ir_initial_value as i64, var.ir_initial_max as i64);
+                (symb_id.clone(), initial)
+            })
+            .collect();
+
+        // Extend the bounds until they include the updates. Bounds that are still growing
+        // after a while are widened to the declared range to speed up termination.
+        let mut env = initial.clone();
+        for round in 0.. {
+            let mut next = env.clone();
+            for symb_id in self.vars {
+                let old = env[symb_id];
+                let mut new = match self.update(symb_id, &env) {
+                    Some(update) => old.join(update),
+                    None => old,
+                };
+                if round >= WIDENING_DELAY && new != old {
+                    let declared = Interval::from_range(&self.var(symb_id).ir_range);
+                    if new.min < old.min {
+                        new.min = declared.min;
+                    }
+                    if new.max > old.max {
+                        new.max = declared.max;
+                    }
+                }
+                next.insert(symb_id.clone(), new);
+            }
+            if next == env {
+                break;
+            }
+            env = next;
+        }
+
+        // The bounds now include the initial values and updates. Recomputing them from the
+        // initial values and updates can only tighten bounds that were widened.
+        for _ in 0..NARROWING_ROUNDS {
+            let next: Env = self
+                .vars
+                .iter()
+                .map(|symb_id| {
+                    let bounds = match self.update(symb_id, &env) {
+                        Some(update) => initial[symb_id].join(update),
+                        None => initial[symb_id],
+                    };
+                    (symb_id.clone(), bounds)
+                })
+                .collect();
+            if next == env {
+                break;
+            }
+            env = next;
+        }
+
+        env.into_iter()
+            .map(|(symb_id, bounds)| (symb_id, bounds.to_range()))
+            .collect()
+    }
+
+    fn var(&self, symb_id: &SymbolIdentifier) -> &StateVarDecl {
+        match &self.symbols[symb_id].kind {
+            DeclKind::StateVar(var) => var,
+            _ => panic!("State variable was not a state variable."),
+        }
+    }
+
+    /// Returns the bounds of the next value of the variable after its range policy is applied,
+    /// or `None` if every update is out of range, which is an error
+    fn update(&self, symb_id: &SymbolIdentifier, env: &Env) -> Option<Interval> {
+        let var = self.var(symb_id);
+        let mut value = self.eval(&var.next_value, env);
+        if var.var_type == VarType::Bool {
+            value = Interval::bool(value.truth());
+        }
+        let declared = Interval::from_range(&var.ir_range);
+        match var.range_policy {
+            RangePolicy::Error => value.meet(declared),
+            RangePolicy::Clamp => Some(Interval::new(
+                value.min.max(declared.min).min(declared.max),
+                value.max.max(declared.min).min(declared.max),
+            )),
+            RangePolicy::Wrap => value
+                .meet(declared)
+                .filter(|&bounds| bounds == value)
+                .or(Some(declared)),
+        }
+    }
+
+    /// Returns the bounds of the values of the expression, given the bounds of the variables
+    pub fn eval(&self, expr: &Expr, env: &Env) -> Interval {
+        match &expr.kind {
+            ExprKind::Number(n) => Interval::point(*n),
+            ExprKind::OwnedIdent(ident) => match ident.as_ref() {
+                Identifier::Resolved { owner, name } => {
+                    let symb_id = owner.symbol_id(name);
+                    match env.get(&symb_id) {
+                        Some(bounds) => *bounds,
+                        // Actions are 1 if chosen and 0 otherwise
+                        None => Interval::new(0, 1),
+                    }
+                }
+                _ => panic!("Unresolved identifier. Something went wrong in symbol checking."),
+            },
+            ExprKind::Index(..) | ExprKind::Call(..) => Interval::top(),
+            ExprKind::UnaryOp(UnaryOpKind::Not, e) => {
+                Interval::bool(self.eval(e, env).truth().map(|truth| !truth))
+            }
+            ExprKind::UnaryOp(UnaryOpKind::Negation, e) => {
+                let bounds = self.eval(e, env);
+                Interval::new(-bounds.max, -bounds.min)
+            }
+            ExprKind::BinaryOp(op, e1, e2) => {
+                self.eval_binop(op, self.eval(e1, env), self.eval(e2, env))
+            }
+            ExprKind::TernaryIf(cond, e1, e2) => {
+                let then = self.refine(cond, true, env).map(|env| self.eval(e1, &env));
+                let otherwise = self.refine(cond, false, env).map(|env| self.eval(e2, &env));
+                match (then, otherwise) {
+                    (Some(then), Some(otherwise)) => then.join(otherwise),
+                    (Some(bounds), None) | (None, Some(bounds)) => bounds,
+                    (None, None) => self.eval(e1, env).join(self.eval(e2, env)),
+                }
+            }
+            ExprKind::Min(es) => es
+                .iter()
+                .map(|e| self.eval(e, env))
+                .reduce(|a, b| Interval::new(a.min.min(b.min), a.max.min(b.max)))
+                .unwrap(),
+            ExprKind::Max(es) => es
+                .iter()
+                .map(|e| self.eval(e, env))
+                .reduce(|a, b| Interval::new(a.min.max(b.min), a.max.max(b.max)))
+                .unwrap(),
+            ExprKind::Abs(e) => {
+                let bounds = self.eval(e, env);
+                if bounds.min >= 0 {
+                    bounds
+                } else if bounds.max <= 0 {
+                    Interval::new(-bounds.max, -bounds.min)
+                } else {
+                    Interval::new(0, bounds.max.max(-bounds.min))
+                }
+            }
+            ExprKind::Pow(base, exp) => match (self.eval(base, env), self.eval(exp, env)) {
+                (base, exp) if base.min == base.max && exp.min == exp.max => {
+                    Interval::point(int_pow(base.min as i32, exp.min as i32))
+                }
+                _ => Interval::top(),
+            },
+            ExprKind::Clamp(e, lo, hi) => {
+                let (e, lo, hi) = (self.eval(e, env), self.eval(lo, env), self.eval(hi, env));
+                Interval::new(e.min.max(lo.min).min(hi.min), e.max.max(lo.max).min(hi.max))
+            }
+        }
+    }
+
+    fn eval_binop(&self, op: &BinaryOpKind, a: Interval, b: Interval) -> Interval {
+        match op {
+            BinaryOpKind::Addition => Interval::new(a.min + b.min, a.max + b.max),
+            BinaryOpKind::Subtraction => Interval::new(a.min - b.max, a.max - b.min),
+            BinaryOpKind::Multiplication => a.corners(b, |x, y| x * y),
+            BinaryOpKind::Division => {
+                // Division by zero is zero. Otherwise, truncated division is monotone in each
+                // argument when the sign of the divisor is fixed.
+                let mut result = b.meet(Interval::point(0)).map(|_| Interval::point(0));
+                for divisor in [
+                    b.meet(Interval::new(1, i32::MAX as i64)),
+                    b.meet(Interval::new(i32::MIN as i64, -1)),
+                ]
+                .iter()
+                .flatten()
+                {
+                    let quotient = a.corners(*divisor, |x, y| x / y);
+                    result = Some(result.map_or(quotient, |r| r.join(quotient)));
+                }
+                result.unwrap()
+            }
+            BinaryOpKind::Modulo => {
+                // The result of euclidean modulo is in [0 .. |divisor| - 1], and it is the
+                // dividend itself if that is already in that range for every divisor
+                let largest = b.min.abs().max(b.max.abs());
+                let smallest = if b.min <= 0 && b.max >= 0 {
+                    0
+                } else {
+                    b.min.abs().min(b.max.abs())
+                };
+                if a.min >= 0 && a.max < smallest {
+                    a
+                } else if b.min == b.max
+                    && b.min != 0
+                    && a.max - a.min < largest
+                    && a.min.rem_euclid(largest) <= a.max.rem_euclid(largest)
+                {
+                    Interval::new(a.min.rem_euclid(largest), a.max.rem_euclid(largest))
+                } else {
+                    Interval::new(0, (largest - 1).max(0))
+                }
+            }
+            BinaryOpKind::Equality => Interval::bool(if a.min == a.max && a == b {
+                Some(true)
+            } else if a.meet(b).is_none() {
+                Some(false)
+            } else {
+                None
+            }),
+            BinaryOpKind::Inequality => {
+                let equal = self.eval_binop(&BinaryOpKind::Equality, a, b);
+                Interval::bool(equal.truth().map(|truth| !truth))
+            }
+            BinaryOpKind::LessThan => Interval::bool(if a.max < b.min {
+                Some(true)
+            } else if a.min >= b.max {
+                Some(false)
+            } else {
+                None
+            }),
+            BinaryOpKind::GreaterThan => self.eval_binop(&BinaryOpKind::LessThan, b, a),
+            BinaryOpKind::LessOrEqual => {
+                let greater = self.eval_binop(&BinaryOpKind::LessThan, b, a);
+                Interval::bool(greater.truth().map(|truth| !truth))
+            }
+            BinaryOpKind::GreaterOrEqual => self.eval_binop(&BinaryOpKind::LessOrEqual, b, a),
+            BinaryOpKind::And => Interval::bool(match (a.truth(), b.truth()) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            }),
+            BinaryOpKind::Or => Interval::bool(match (a.truth(), b.truth()) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            }),
+            BinaryOpKind::Xor => Interval::bool(match (a.truth(), b.truth()) {
+                (Some(t1), Some(t2)) => Some(t1 != t2),
+                _ => None,
+            }),
+            BinaryOpKind::Implication => Interval::bool(match (a.truth(), b.truth()) {
+                (Some(false), _) | (_, Some(true)) => Some(true),
+                (Some(true), Some(false)) => Some(false),
+                _ => None,
+            }),
+        }
+    }
+
+    /// Returns the bounds of the variables restricted to the states where the condition has
+    /// the given truth value, or `None` if there are no such states. Only comparisons of
+    /// variables and conjunctions of such are used to restrict the bounds.
+    fn refine(&self, cond: &Expr, truth: bool, env: &Env) -> Option<Env> {
+        if self.eval(cond, env).truth() == Some(!truth) {
+            return None;
+        }
+        match (&cond.kind, truth) {
+            (ExprKind::UnaryOp(UnaryOpKind::Not, e), _) => self.refine(e, !truth, env),
+            (ExprKind::BinaryOp(BinaryOpKind::And, e1, e2), true)
+            | (ExprKind::BinaryOp(BinaryOpKind::Or, e1, e2), false) => {
+                let env = self.refine(e1, truth, env)?;
+                self.refine(e2, truth, &env)
+            }
+            (ExprKind::BinaryOp(op, e1, e2), _) => {
+                let op = if truth { Some(op.clone()) } else { negate(op) };
+                match (op, &e1.kind, &e2.kind) {
+                    (Some(op), ExprKind::OwnedIdent(ident), _) => {
+                        self.restrict(env, ident, &op, self.eval(e2, env))
+                    }
+                    (Some(op), _, ExprKind::OwnedIdent(ident)) => match flip(&op) {
+                        Some(op) => self.restrict(env, ident, &op, self.eval(e1, env)),
+                        None => Some(env.clone()),
+                    },
+                    _ => Some(env.clone()),
+                }
+            }
+            _ => Some(env.clone()),
+        }
+    }
+
+    /// Restricts the bounds of the variable to the values `x` where "`x op other`" can hold
+    fn restrict(
+        &self,
+        env: &Env,
+        ident: &Identifier,
+        op: &BinaryOpKind,
+        other: Interval,
+    ) -> Option<Env> {
+        let symb_id = match ident {
+            Identifier::Resolved { owner, name } => owner.symbol_id(name),
+            _ => return Some(env.clone()),
+        };
+        let bounds = match env.get(&symb_id) {
+            Some(bounds) => *bounds,
+            None => return Some(env.clone()),
+        };
+        let restricted = match op {
+            BinaryOpKind::LessThan if other.max > bounds.min => {
+                Interval::new(bounds.min, bounds.max.min(other.max - 1))
+            }
+            BinaryOpKind::LessOrEqual if other.max >= bounds.min => {
+                Interval::new(bounds.min, bounds.max.min(other.max))
+            }
+            BinaryOpKind::GreaterThan if other.min < bounds.max => {
+                Interval::new(bounds.min.max(other.min + 1), bounds.max)
+            }
+            BinaryOpKind::GreaterOrEqual if other.min <= bounds.max => {
+                Interval::new(bounds.min.max(other.min), bounds.max)
+            }
+            BinaryOpKind::Equality => bounds.meet(other)?,
+            BinaryOpKind::Inequality if other.min == other.max => {
+                if bounds.min == bounds.max && bounds.min == other.min {
+                    return None;
+                } else if bounds.min == other.min {
+                    Interval::new(bounds.min + 1, bounds.max)
+                } else if bounds.max == other.min {
+                    Interval::new(bounds.min, bounds.max - 1)
+                } else {
+                    bounds
+                }
+            }
+            BinaryOpKind::LessThan
+            | BinaryOpKind::LessOrEqual
+            | BinaryOpKind::GreaterThan
+            | BinaryOpKind::GreaterOrEqual => return None,
+            _ => bounds,
+        };
+        let mut env = env.clone();
+        env.insert(symb_id, restricted);
+        Some(env)
+    }
+}
+
+/// Returns the comparison that holds exactly when the given comparison does not
+fn negate(op: &BinaryOpKind) -> Option<BinaryOpKind> {
+    match op {
+        BinaryOpKind::Equality => Some(BinaryOpKind::Inequality),
+        BinaryOpKind::Inequality => Some(BinaryOpKind::Equality),
+        BinaryOpKind::LessThan => Some(BinaryOpKind::GreaterOrEqual),
+        BinaryOpKind::LessOrEqual => Some(BinaryOpKind::GreaterThan),
+        BinaryOpKind::GreaterThan => Some(BinaryOpKind::LessOrEqual),
+        BinaryOpKind::GreaterOrEqual => Some(BinaryOpKind::LessThan),
+        _ => None,
+    }
+}
+
+/// Returns the comparison that holds when the operands of the given comparison are swapped
+fn flip(op: &BinaryOpKind) -> Option<BinaryOpKind> {
+    match op {
+        BinaryOpKind::Equality | BinaryOpKind::Inequality => Some(op.clone()),
+        BinaryOpKind::LessThan => Some(BinaryOpKind::GreaterThan),
+        BinaryOpKind::LessOrEqual => Some(BinaryOpKind::GreaterOrEqual),
+        BinaryOpKind::GreaterThan => Some(BinaryOpKind::LessThan),
+        BinaryOpKind::GreaterOrEqual => Some(BinaryOpKind::LessOrEqual),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ops::RangeInclusive;
+
+    use crate::atl::gamestructure::GameStructure;
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::ir::symbol_table::Owner;
+    use crate::lcgs::parse::parse_lcgs;
+
+    fn ranges(input: &str) -> HashMap<String, RangeInclusive<i32>> {
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        lcgs.reachable_ranges()
+            .into_iter()
+            .map(|(symb_id, range)| match symb_id.owner {
+                Owner::Global => (symb_id.name, range),
+                Owner::Player(_) => (symb_id.to_string(), range),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_range_analysis_01() {
+        // Bounded counters, wrapping counters, and clamped differences
+        let input = "
+        guarded : [0 .. 100] init 0;
+        guarded' = guarded < 10 ? guarded + 1 : guarded;
+        minimum : [0 .. 100] init 3;
+        minimum' = min(minimum + 1, 7);
+        modulo : [0 .. 100] init 0;
+        modulo' = (modulo + 1) % 5;
+        wrapping : [0 .. 100] init 0 overflow wrap;
+        wrapping' = wrapping + 1;
+        clamped : [-50 .. 50] init 0 overflow clamp;
+        clamped' = clamped - p.act;
+        constant : [0 .. 9] init 4;
+        player p = thing;
+        template thing
+            [act] 1;
+        endtemplate
+        ";
+        let ranges = ranges(input);
+        assert_eq!(ranges["guarded"], 0..=10);
+        assert_eq!(ranges["minimum"], 3..=7);
+        assert_eq!(ranges["modulo"], 0..=4);
+        assert_eq!(ranges["wrapping"], 0..=100);
+        assert_eq!(ranges["clamped"], -50..=0);
+        assert_eq!(ranges["constant"], 4..=4);
+    }
+
+    #[test]
+    fn test_range_analysis_02() {
+        // Variables of players depending on each other, booleans, and negated conditions
+        let input = "
+        player p1 = shooter [target=p2];
+        player p2 = shooter [target=p1];
+        template shooter
+            health : [0 .. 100] init 3;
+            health' = max(health - target.shoot, 0);
+            hit : bool init false;
+            hit' = target.shoot;
+            level : [-10 .. 10] init 0;
+            level' = !(level >= 2) ? level + 1 : level - 1;
+            [wait] 1;
+            [shoot] health > 0;
+        endtemplate
+        ";
+        let ranges = ranges(input);
+        assert_eq!(ranges["p1.health"], 0..=3);
+        assert_eq!(ranges["p2.health"], 0..=3);
+        assert_eq!(ranges["p1.hit"], 0..=1);
+        assert_eq!(ranges["p1.level"], 0..=2);
+    }
+
+    #[test]
+    fn test_tighten_ranges_01() {
+        // Tightening the ranges shrinks the state indexes, but not the game
+        let input = "
+        x : [0 .. 1000] init 0;
+        x' = min(x + p.inc, 3);
+        player p = thing;
+        template thing
+            [inc] 1;
+            [wait] 1;
+        endtemplate
+        label done = x == 3;
+        ";
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let mut tightened = lcgs.clone();
+        tightened.tighten_ranges();
+        let (mut state, mut tight_state) =
+            (lcgs.initial_state_index(), tightened.initial_state_index());
+        for _ in 0..4 {
+            assert_eq!(lcgs.state_name(state), tightened.state_name(tight_state));
+            assert_eq!(lcgs.labels(state), tightened.labels(tight_state));
+            assert!(tight_state < 4);
+            state = lcgs.transitions(state, vec![0]);
+            tight_state = tightened.transitions(tight_state, vec![0]);
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use crate::lcgs::ast::{Decl, DeclKind, Expr, ExprKind, Identifier, Relabeling, Span, VarType};
+use crate::lcgs::ir::relabeling::Relabeler;
+use crate::lcgs::ir::symbol_table::{Owner, SymbolIdentifier};
+
+/// A declaration that is likely a mistake, although the program is valid
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Lint {
+    pub msg: String,
+    pub span: Span,
+}
+
+/// A [Linter] looks for unused and suspicious declarations in a symbol checked program.
+/// Declarations of templates are instantiated once per player, so a lint about such a
+/// declaration is only reported if it applies to every player.
+pub struct Linter<'a> {
+    symbols: &'a HashMap<SymbolIdentifier, Decl>,
+    /// Symbols referenced by expressions, including constants before they were inlined
+    used: &'a HashSet<SymbolIdentifier>,
+    /// Bounds of the values of the state variables in reachable states
+    ranges: &'a HashMap<SymbolIdentifier, RangeInclusive<i32>>,
+    /// Lints of each instance of a declaration, keyed by the span of the declaration
+    instances: HashMap<Span, Vec<Option<String>>>,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(
+        symbols: &'a HashMap<SymbolIdentifier, Decl>,
+        used: &'a HashSet<SymbolIdentifier>,
+        ranges: &'a HashMap<SymbolIdentifier, RangeInclusive<i32>>,
+    ) -> Linter<'a> {
+        Linter {
+            symbols,
+            used,
+            ranges,
+            instances: HashMap::new(),
+            lints: vec![],
+        }
+    }
+
+    /// Runs all lints. Labels are only reported as unused if the indexes of the labels
+    /// used by the formulas being checked are given, since labels are only used by formulas.
+    pub fn lint(mut self, used_labels: Option<&HashSet<usize>>) -> Vec<Lint> {
+        let read = self.read_vars();
+        let symbols = self.symbols;
+        for (symb_id, decl) in symbols {
+            let lint = match &decl.kind {
+                DeclKind::Const(_) if !self.used.contains(symb_id) => {
+                    Some(format!("The constant '{}' is never used.", symb_id.name))
+                }
+                DeclKind::Label(label) => match label.condition.kind {
+                    ExprKind::Number(0) => {
+                        Some(format!("The label '{}' is always false.", symb_id.name))
+                    }
+                    ExprKind::Number(_) => {
+                        Some(format!("The label '{}' is always true.", symb_id.name))
+                    }
+                    // Formulas refer to the labels of specific players, so each instance
+                    // is reported
+                    _ if used_labels.is_some_and(|used| !used.contains(&label.index)) => {
+                        let name = match &symb_id.owner {
+                            Owner::Global => symb_id.name.clone(),
+                            Owner::Player(_) => symb_id.to_string(),
+                        };
+                        self.lints.push(Lint {
+                            msg: format!("The label '{}' is not used by any formula.", name),
+                            span: decl.span,
+                        });
+                        continue;
+                    }
+                    _ => None,
+                },
+                DeclKind::StateVar(var) => {
+                    let unchanged = ExprKind::OwnedIdent(Box::new(Identifier::Resolved {
+                        owner: symb_id.owner.clone(),
+                        name: symb_id.name.clone(),
+                    }));
+                    if var.next_value.kind == unchanged {
+                        Some(format!(
+                            "The variable '{}' is never updated. Consider using a constant.",
+                            base_name(&symb_id.name)
+                        ))
+                    } else if !read.contains(symb_id) {
+                        Some(format!(
+                            "The variable '{}' is never read.",
+                            base_name(&symb_id.name)
+                        ))
+                    } else if var.var_type == VarType::Int
+                        && self
+                            .ranges
+                            .get(symb_id)
+                            .is_some_and(|range| *range != var.ir_range)
+                    {
+                        let range = &self.ranges[symb_id];
+                        Some(format!(
+                            "The variable '{}' only takes values in [{} .. {}], but its range is [{} .. {}].",
+                            base_name(&symb_id.name),
+                            range.start(),
+                            range.end(),
+                            var.ir_range.start(),
+                            var.ir_range.end()
+                        ))
+                    } else {
+                        None
+                    }
+                }
+                DeclKind::Transition(tran) if tran.condition.kind == ExprKind::Number(0) => {
+                    Some(format!(
+                        "The action '{}' can never be taken, since its condition is always false.",
+                        symb_id.name
+                    ))
+                }
+                DeclKind::Player(player) => {
+                    self.lint_relabeling(player.template.name(), &player.relabeling);
+                    continue;
+                }
+                _ => continue,
+            };
+            // Only declarations owned by players can have several instances
+            if symb_id.owner == Owner::Global && !matches!(decl.kind, DeclKind::StateVar(_)) {
+                if let Some(msg) = lint {
+                    self.lints.push(Lint {
+                        msg,
+                        span: decl.span,
+                    });
+                }
+            } else {
+                self.instances.entry(decl.span).or_default().push(lint);
+            }
+        }
+        self.finish()
+    }
+
+    /// Returns the variables that are read by another declaration than their own update
+    fn read_vars(&self) -> HashSet<SymbolIdentifier> {
+        let mut read = HashSet::new();
+        for (symb_id, decl) in self.symbols {
+            let mut idents = vec![];
+            match &decl.kind {
+                DeclKind::Label(label) => collect_idents(&label.condition, &mut idents),
+                DeclKind::Transition(tran) => collect_idents(&tran.condition, &mut idents),
+                DeclKind::StateVar(var) => collect_idents(&var.next_value, &mut idents),
+                _ => {}
+            }
+            read.extend(
+                idents
+                    .into_iter()
+                    .filter(|ident| ident != symb_id)
+                    .filter(|ident| {
+                        matches!(
+                            self.symbols.get(ident).map(|decl| &decl.kind),
+                            Some(DeclKind::StateVar(_))
+                        )
+                    }),
+            );
+        }
+        read
+    }
+
+    /// Reports relabel cases that do not change any declaration of the template
+    fn lint_relabeling(&mut self, template_name: &str, relabeling: &Relabeling) {
+        let template_id = Owner::Global.symbol_id(template_name);
+        let template = match self.symbols.get(&template_id).map(|decl| &decl.kind) {
+            Some(DeclKind::Template(template)) => template,
+            _ => return,
+        };
+        for case in &relabeling.relabellings {
+            let single = Relabeling {
+                relabellings: vec![case.clone()],
+            };
+            let relabeler = Relabeler::new(&single);
+            // A relabeling that fails changed something, since it was applied
+            let unused = template.decls.iter().all(|decl| {
+                relabeler
+                    .relabel_decl(decl)
+                    .is_ok_and(|relabeled| relabeled == *decl)
+            });
+            if unused {
+                self.lints.push(Lint {
+                    msg: format!(
+                        "The template '{}' does not use '{}', so relabeling it has no effect.",
+                        template_name, case.prev
+                    ),
+                    span: case.new.span,
+                });
+            }
+        }
+    }
+
+    /// Returns the collected lints ordered by their position in the program. Lints of
+    /// declarations with several instances are only included if all instances have the lint.
+    fn finish(mut self) -> Vec<Lint> {
+        for (span, lints) in self.instances.drain() {
+            if let Some(Some(first)) = lints.first() {
+                if lints.iter().all(|lint| lint.as_ref() == Some(first)) {
+                    self.lints.push(Lint {
+                        msg: first.clone(),
+                        span,
+                    });
+                }
+            }
+        }
+        self.lints
+            .sort_by(|a, b| (a.span.begin, &a.msg).cmp(&(b.span.begin, &b.msg)));
+        self.lints.dedup();
+        self.lints
+    }
+}
+
+/// Returns the name of a variable without the index of an array element, such that all
+/// elements of an array are reported as one
+fn base_name(name: &str) -> &str {
+    name.split('[').next().unwrap()
+}
+
+/// Collects the resolved identifiers of the given expression
+pub(crate) fn collect_idents(expr: &Expr, idents: &mut Vec<SymbolIdentifier>) {
+    match &expr.kind {
+        ExprKind::Number(_) => {}
+        ExprKind::OwnedIdent(ident) => {
+            if let Identifier::Resolved { owner, name } = ident.as_ref() {
+                idents.push(owner.symbol_id(name));
+            }
+        }
+        ExprKind::Index(_, index) => collect_idents(index, idents),
+        ExprKind::UnaryOp(_, e) | ExprKind::Abs(e) => collect_idents(e, idents),
+        ExprKind::BinaryOp(_, e1, e2) | ExprKind::Pow(e1, e2) => {
+            collect_idents(e1, idents);
+            collect_idents(e2, idents);
+        }
+        ExprKind::TernaryIf(e1, e2, e3) | ExprKind::Clamp(e1, e2, e3) => {
+            collect_idents(e1, idents);
+            collect_idents(e2, idents);
+            collect_idents(e3, idents);
+        }
+        ExprKind::Min(es) | ExprKind::Max(es) | ExprKind::Call(_, es) => {
+            for e in es {
+                collect_idents(e, idents);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::parse::parse_lcgs;
+
+    fn lint(input: &str, used_labels: Option<&HashSet<usize>>) -> Vec<String> {
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        lcgs.lint(used_labels)
+            .into_iter()
+            .map(|lint| lint.msg)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_01() {
+        // Unused constants, unread variables, and variables that are never updated
+        let input = "
+        const n = 3;
+        const unused = 2;
+        counter : [0 .. n] init 0;
+        counter' = min(counter + 1, n);
+        fixed : [0 .. 2] init 1;
+        unread : [0 .. 2] init 0;
+        unread' = (unread + 1) % 3;
+        player p = thing;
+        template thing
+            label done = counter == n && fixed == 1;
+            [wait] 1;
+        endtemplate
+        ";
+        assert_eq!(
+            lint(input, None),
+            vec![
+                "The constant 'unused' is never used.",
+                "The variable 'fixed' is never updated. Consider using a constant.",
+                "The variable 'unread' is never read.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_02() {
+        // Constant guards and labels, and labels not used by formulas
+        let input = "
+        const enabled = 0;
+        player p = thing;
+        template thing
+            x : [0 .. 1] init 0;
+            x' = act;
+            label always = enabled < 1;
+            label one = x == 1;
+            label zero = x == 0;
+            [wait] 1;
+            [act] enabled == 1;
+        endtemplate
+        ";
+        assert_eq!(
+            lint(input, None),
+            vec![
+                "The label 'always' is always true.",
+                "The action 'act' can never be taken, since its condition is always false.",
+            ]
+        );
+        let used_labels = [1].iter().cloned().collect();
+        assert_eq!(
+            lint(input, Some(&used_labels)),
+            vec![
+                "The label 'always' is always true.",
+                "The label 'p.zero' is not used by any formula.",
+                "The action 'act' can never be taken, since its condition is always false.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_03() {
+        // Template declarations are only reported if the lint applies to all players, and
+        // relabelings of names the template does not use are reported
+        let input = "
+        player p1 = shooter [target=p2, dmg=1];
+        player p2 = shooter [target=p1, dmg=1, speed=2];
+        template shooter
+            health : [0 .. 3] init 3;
+            health' = max(health - target.shoot, 0);
+            ammo : [0 .. 3] init 3;
+            ammo' = ammo - shoot;
+            [wait] 1;
+            [shoot] ammo > 0;
+        endtemplate
+        ";
+        assert_eq!(
+            lint(input, None),
+            vec![
+                "The template 'shooter' does not use 'dmg', so relabeling it has no effect.",
+                "The template 'shooter' does not use 'dmg', so relabeling it has no effect.",
+                "The template 'shooter' does not use 'speed', so relabeling it has no effect.",
+                "The variable 'health' is never read.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_04() {
+        // Integer variables with ranges wider than the reachable values
+        let input = "
+        counter : [0 .. 100] init 0;
+        counter' = counter < 10 ? counter + 1 : 0;
+        player p = thing;
+        template thing
+            flag : bool init false;
+            flag' = flag || counter == 10;
+            label done = flag;
+            [wait] 1;
+        endtemplate
+        ";
+        assert_eq!(
+            lint(input, None),
+            vec!["The variable 'counter' only takes values in [0 .. 10], but its range is [0 .. 100]."]
+        );
+    }
+}
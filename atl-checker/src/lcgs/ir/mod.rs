@@ -1,6 +1,10 @@
-mod error;
+pub mod error;
 mod eval;
 pub mod intermediate;
+pub mod interval;
+pub mod lint;
 mod relabeling;
 mod symbol_checker;
 pub mod symbol_table;
+pub mod symmetry;
+pub mod type_checker;
@@ -1,12 +1,14 @@
 use crate::lcgs::ast::{
-    BinaryOpKind, Decl, DeclKind, Expr, ExprKind, Identifier, LabelDecl, Relabeling, StateVarDecl,
-    TransitionDecl, TypeRange, UnaryOpKind,
+    ArrayDecl, BinaryOpKind, Decl, DeclKind, DefineDecl, Expr, ExprKind, Identifier, LabelDecl,
+    Relabeling, Span, StateVarDecl, TransitionDecl, TypeRange, UnaryOpKind, UpdateDecl,
 };
 use std::ops::Deref;
 
 #[derive(Debug)]
 pub struct RelabelError {
     pub msg: String,
+    /// The span of the relabeling case or expression that caused the error
+    pub span: Span,
 }
 
 /// A [Relabeler] applies relabeling of declarations and their parts. It performs the
@@ -35,9 +37,10 @@ impl<'a> Relabeler<'a> {
 
     pub fn relabel_decl(&self, decl: &Decl) -> Result<Decl, RelabelError> {
         match &decl.kind {
-            DeclKind::Label(label) => self.relabel_label(label),
-            DeclKind::StateVar(var) => self.relabel_var(var),
-            DeclKind::Transition(tran) => self.relabel_transition(tran),
+            DeclKind::Label(label) => self.relabel_label(decl.span, label),
+            DeclKind::StateVar(var) => self.relabel_var(decl.span, var),
+            DeclKind::Transition(tran) => self.relabel_transition(decl.span, tran),
+            DeclKind::Define(define) => self.relabel_define(decl.span, define),
             // The following declarations only appear in global scope and thus wont be renamed
             DeclKind::Const(_) => panic!("Cannot relabel a ConstDecl"),
             DeclKind::Player(_) => panic!("Cannot relabel a PlayerDecl"),
@@ -45,8 +48,9 @@ impl<'a> Relabeler<'a> {
         }
     }
 
-    fn relabel_label(&self, label: &LabelDecl) -> Result<Decl, RelabelError> {
+    fn relabel_label(&self, span: Span, label: &LabelDecl) -> Result<Decl, RelabelError> {
         Ok(Decl {
+            span,
             kind: DeclKind::Label(Box::new(LabelDecl {
                 index: label.index,
                 name: self.relabel_simple_ident(&label.name)?,
@@ -55,31 +59,108 @@ impl<'a> Relabeler<'a> {
         })
     }
 
-    fn relabel_var(&self, var: &StateVarDecl) -> Result<Decl, RelabelError> {
+    fn relabel_var(&self, span: Span, var: &StateVarDecl) -> Result<Decl, RelabelError> {
+        let (array, next_value) = if let Some(array) = &var.array {
+            // The index name of an array shadows any relabeling of that name
+            let shadowed = Relabeling {
+                relabellings: self
+                    .relabeling
+                    .relabellings
+                    .iter()
+                    .filter(|case| case.prev != array.index_name)
+                    .cloned()
+                    .collect(),
+            };
+            let array = ArrayDecl {
+                size: self.relabel_expr(&array.size)?,
+                index_name: array.index_name.clone(),
+            };
+            let next_value = Relabeler::new(&shadowed).relabel_expr(&var.next_value)?;
+            (Some(array), next_value)
+        } else {
+            (None, self.relabel_expr(&var.next_value)?)
+        };
         Ok(Decl {
+            span,
             kind: DeclKind::StateVar(Box::new(StateVarDecl {
                 name: self.relabel_simple_ident(&var.name)?,
+                var_type: var.var_type.clone(),
                 range: TypeRange {
                     min: self.relabel_expr(&var.range.min)?,
                     max: self.relabel_expr(&var.range.max)?,
                 },
                 ir_range: 0..=0,
                 initial_value: self.relabel_expr(&var.initial_value)?,
+                initial_max: match &var.initial_max {
+                    Some(max) => Some(self.relabel_expr(max)?),
+                    None => None,
+                },
                 ir_initial_value: 0,
-                next_value: self.relabel_expr(&var.next_value)?,
+                ir_initial_max: 0,
+                range_policy: var.range_policy,
+                next_value,
+                array,
             })),
         })
     }
 
-    fn relabel_transition(&self, tran: &TransitionDecl) -> Result<Decl, RelabelError> {
+    fn relabel_transition(&self, span: Span, tran: &TransitionDecl) -> Result<Decl, RelabelError> {
         Ok(Decl {
+            span,
             kind: DeclKind::Transition(Box::new(TransitionDecl {
                 name: self.relabel_simple_ident(&tran.name)?,
                 condition: self.relabel_expr(&tran.condition)?,
+                updates: tran
+                    .updates
+                    .iter()
+                    .map(|update| self.relabel_update(update))
+                    .collect::<Result<Vec<UpdateDecl>, RelabelError>>()?,
             })),
         })
     }
 
+    fn relabel_define(&self, span: Span, define: &DefineDecl) -> Result<Decl, RelabelError> {
+        // The parameters shadow any relabeling of their names
+        let shadowed = Relabeling {
+            relabellings: self
+                .relabeling
+                .relabellings
+                .iter()
+                .filter(|case| !define.params.contains(&case.prev))
+                .cloned()
+                .collect(),
+        };
+        Ok(Decl {
+            span,
+            kind: DeclKind::Define(Box::new(DefineDecl {
+                name: self.relabel_simple_ident(&define.name)?,
+                params: define.params.clone(),
+                body: Relabeler::new(&shadowed).relabel_expr(&define.body)?,
+            })),
+        })
+    }
+
+    /// Relabels an update of an action. Like array identifiers, the parts of the updated
+    /// variable's identifier can only be relabeled to other names.
+    fn relabel_update(&self, update: &UpdateDecl) -> Result<UpdateDecl, RelabelError> {
+        if let Identifier::OptionalOwner { owner, name } = &update.target {
+            let new_owner = match owner {
+                Some(owner) => Some(self.relabel_ident_part(owner)?),
+                None => None,
+            };
+            Ok(UpdateDecl {
+                target: Identifier::OptionalOwner {
+                    owner: new_owner,
+                    name: self.relabel_ident_part(name)?,
+                },
+                value: self.relabel_expr(&update.value)?,
+                span: update.span,
+            })
+        } else {
+            unreachable!()
+        }
+    }
+
     /// Relabel a simple identifier (declaration name). The returned identifier is guaranteed
     /// to be a [Identifier::Simple] too.
     fn relabel_simple_ident(&self, ident: &Identifier) -> Result<Identifier, RelabelError> {
@@ -101,7 +182,8 @@ impl<'a> Relabeler<'a> {
                     {
                         if owner.is_some() {
                             return Err(RelabelError {
-                                msg: format!("You cannot relabel '{}' to an expression since it is the name of a declaration.", name)
+                                msg: format!("You cannot relabel '{}' to an expression since it is the name of a declaration.", name),
+                                span: relabel.new.span,
                             });
                         }
                         return Ok(Identifier::Simple {
@@ -113,7 +195,8 @@ impl<'a> Relabeler<'a> {
                     // The new name is not an identifier, so it is some other expression, which
                     // is not valid
                     return Err(RelabelError {
-                        msg: format!("You cannot relabel '{}' to an expression since it is the name of a declaration.", name)
+                        msg: format!("You cannot relabel '{}' to an expression since it is the name of a declaration.", name),
+                        span: relabel.new.span,
                     });
                 }
             }
@@ -126,27 +209,49 @@ impl<'a> Relabeler<'a> {
     pub fn relabel_expr(&self, expr: &Expr) -> Result<Expr, RelabelError> {
         match &expr.kind {
             ExprKind::Number(_) => Ok(expr.clone()),
-            ExprKind::OwnedIdent(ident) => self.relabel_owned_ident(ident),
-            ExprKind::UnaryOp(op, expr) => self.relabel_unop(op, expr),
-            ExprKind::BinaryOp(op, lhs, rhs) => self.relabel_binop(op, lhs, rhs),
+            ExprKind::OwnedIdent(ident) => self.relabel_owned_ident(expr.span, ident),
+            ExprKind::Index(ident, index) => self.relabel_index(expr.span, ident, index),
+            ExprKind::Call(ident, args) => self.relabel_call(expr.span, ident, args),
+            ExprKind::UnaryOp(op, inner) => self.relabel_unop(expr.span, op, inner),
+            ExprKind::BinaryOp(op, lhs, rhs) => self.relabel_binop(expr.span, op, lhs, rhs),
             ExprKind::TernaryIf(cond, true_expr, false_expr) => {
-                self.relabel_if(cond, true_expr, false_expr)
+                self.relabel_if(expr.span, cond, true_expr, false_expr)
             }
-            ExprKind::Min(exprs) => self.relabel_min(exprs),
-            ExprKind::Max(exprs) => self.relabel_max(exprs),
+            ExprKind::Min(exprs) => self.relabel_min(expr.span, exprs),
+            ExprKind::Max(exprs) => self.relabel_max(expr.span, exprs),
+            ExprKind::Abs(inner) => Ok(Expr {
+                span: expr.span,
+                kind: ExprKind::Abs(Box::new(self.relabel_expr(inner)?)),
+            }),
+            ExprKind::Pow(base, exp) => Ok(Expr {
+                span: expr.span,
+                kind: ExprKind::Pow(
+                    Box::new(self.relabel_expr(base)?),
+                    Box::new(self.relabel_expr(exp)?),
+                ),
+            }),
+            ExprKind::Clamp(inner, lo, hi) => Ok(Expr {
+                span: expr.span,
+                kind: ExprKind::Clamp(
+                    Box::new(self.relabel_expr(inner)?),
+                    Box::new(self.relabel_expr(lo)?),
+                    Box::new(self.relabel_expr(hi)?),
+                ),
+            }),
         }
     }
 
     /// Relabels an owner identifier (those found in expressions). The owner is not guaranteed
     /// to be there. If it is there, we allow rename of the owner and the name separately.
     /// If there is no explicit owner, then relabeling to an expression is also okay.
-    fn relabel_owned_ident(&self, ident: &Identifier) -> Result<Expr, RelabelError> {
+    fn relabel_owned_ident(&self, span: Span, ident: &Identifier) -> Result<Expr, RelabelError> {
         if let Identifier::OptionalOwner { owner, name } = ident {
             // If we have an owner, we relabel owner and name separately
             if let Some(owner) = owner {
                 let new_owner = self.relabel_ident_part(owner)?;
                 let new_name = self.relabel_ident_part(name)?;
                 Ok(Expr {
+                    span,
                     kind: ExprKind::OwnedIdent(Box::new(Identifier::OptionalOwner {
                         owner: Some(new_owner),
                         name: new_name,
@@ -163,6 +268,7 @@ impl<'a> Relabeler<'a> {
 
                 // No relabeling performed
                 Ok(Expr {
+                    span,
                     kind: ExprKind::OwnedIdent(Box::new(ident.clone())),
                 })
             }
@@ -171,6 +277,64 @@ impl<'a> Relabeler<'a> {
         }
     }
 
+    /// Relabels an indexed array identifier. Since the identifier must remain the name of an
+    /// array, its parts can only be relabeled to other names.
+    fn relabel_index(
+        &self,
+        span: Span,
+        ident: &Identifier,
+        index: &Expr,
+    ) -> Result<Expr, RelabelError> {
+        if let Identifier::OptionalOwner { owner, name } = ident {
+            let new_owner = match owner {
+                Some(owner) => Some(self.relabel_ident_part(owner)?),
+                None => None,
+            };
+            Ok(Expr {
+                span,
+                kind: ExprKind::Index(
+                    Box::new(Identifier::OptionalOwner {
+                        owner: new_owner,
+                        name: self.relabel_ident_part(name)?,
+                    }),
+                    Box::new(self.relabel_expr(index)?),
+                ),
+            })
+        } else {
+            unreachable!()
+        }
+    }
+
+    /// Relabels a macro call. Like array identifiers, the parts of the macro's identifier can
+    /// only be relabeled to other names.
+    fn relabel_call(
+        &self,
+        span: Span,
+        ident: &Identifier,
+        args: &[Expr],
+    ) -> Result<Expr, RelabelError> {
+        if let Identifier::OptionalOwner { owner, name } = ident {
+            let new_owner = match owner {
+                Some(owner) => Some(self.relabel_ident_part(owner)?),
+                None => None,
+            };
+            Ok(Expr {
+                span,
+                kind: ExprKind::Call(
+                    Box::new(Identifier::OptionalOwner {
+                        owner: new_owner,
+                        name: self.relabel_ident_part(name)?,
+                    }),
+                    args.iter()
+                        .map(|arg| self.relabel_expr(arg))
+                        .collect::<Result<Vec<Expr>, RelabelError>>()?,
+                ),
+            })
+        } else {
+            unreachable!()
+        }
+    }
+
     /// Relabels a part of an [Identifier::OptionallyOwned]. That is, either "p1" or "foo" in
     /// "p1.foo". In this case, it is not allowed to relabel to an expression, only another name,
     /// so the resulting identifier still makes sense.
@@ -188,7 +352,8 @@ impl<'a> Relabeler<'a> {
                     {
                         if owner.is_some() {
                             return Err(RelabelError {
-                                msg: format!("You cannot relabel '{}' to an expression since it is a part of an owned identifier.", name)
+                                msg: format!("You cannot relabel '{}' to an expression since it is a part of an owned identifier.", name),
+                                span: relabel_case.new.span,
                             });
                         }
                         return Ok(new_name.clone());
@@ -198,7 +363,8 @@ impl<'a> Relabeler<'a> {
                 // The new name is not an identifier, so it is some other expression which
                 // is not allowed
                 return Err(RelabelError {
-                    msg: format!("You cannot relabel '{}' to an expression since it is a part of an owned identifier.", name)
+                    msg: format!("You cannot relabel '{}' to an expression since it is a part of an owned identifier.", name),
+                    span: relabel_case.new.span,
                 });
             }
         }
@@ -207,19 +373,27 @@ impl<'a> Relabeler<'a> {
         Ok(name.to_string())
     }
 
-    fn relabel_unop(&self, op: &UnaryOpKind, expr: &Expr) -> Result<Expr, RelabelError> {
+    fn relabel_unop(
+        &self,
+        span: Span,
+        op: &UnaryOpKind,
+        expr: &Expr,
+    ) -> Result<Expr, RelabelError> {
         Ok(Expr {
+            span,
             kind: ExprKind::UnaryOp(op.clone(), Box::new(self.relabel_expr(expr)?)),
         })
     }
 
     fn relabel_binop(
         &self,
+        span: Span,
         op: &BinaryOpKind,
         lhs: &Expr,
         rhs: &Expr,
     ) -> Result<Expr, RelabelError> {
         Ok(Expr {
+            span,
             kind: ExprKind::BinaryOp(
                 op.clone(),
                 Box::new(self.relabel_expr(&lhs)?),
@@ -230,11 +404,13 @@ impl<'a> Relabeler<'a> {
 
     fn relabel_if(
         &self,
+        span: Span,
         cond: &Expr,
         true_expr: &Expr,
         false_expr: &Expr,
     ) -> Result<Expr, RelabelError> {
         Ok(Expr {
+            span,
             kind: ExprKind::TernaryIf(
                 Box::new(self.relabel_expr(&cond)?),
                 Box::new(self.relabel_expr(&true_expr)?),
@@ -243,8 +419,9 @@ impl<'a> Relabeler<'a> {
         })
     }
 
-    fn relabel_min(&self, exprs: &[Expr]) -> Result<Expr, RelabelError> {
+    fn relabel_min(&self, span: Span, exprs: &[Expr]) -> Result<Expr, RelabelError> {
         Ok(Expr {
+            span,
             kind: ExprKind::Min(
                 exprs
                     .iter()
@@ -254,8 +431,9 @@ impl<'a> Relabeler<'a> {
         })
     }
 
-    fn relabel_max(&self, exprs: &[Expr]) -> Result<Expr, RelabelError> {
+    fn relabel_max(&self, span: Span, exprs: &[Expr]) -> Result<Expr, RelabelError> {
         Ok(Expr {
+            span,
             kind: ExprKind::Max(
                 exprs
                     .iter()
@@ -268,7 +446,9 @@ impl<'a> Relabeler<'a> {
 
 #[cfg(test)]
 mod test {
-    use crate::lcgs::ast::{BinaryOpKind, Decl, DeclKind, Expr, ExprKind, Identifier, LabelDecl};
+    use crate::lcgs::ast::{
+        BinaryOpKind, Decl, DeclKind, Expr, ExprKind, Identifier, LabelDecl, Span,
+    };
     use crate::lcgs::ir::relabeling::Relabeler;
     use crate::lcgs::parse;
 
@@ -284,12 +464,15 @@ mod test {
         assert_eq!(
             new_expr,
             Expr {
+                span: Span::default(),
                 kind: ExprKind::BinaryOp(
                     BinaryOpKind::Addition,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: ExprKind::Number(5),
                     }),
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: ExprKind::OwnedIdent(Box::new(Identifier::OptionalOwner {
                             owner: None,
                             name: "res".to_string()
@@ -312,12 +495,15 @@ mod test {
         assert_eq!(
             new_expr,
             Expr {
+                span: Span::default(),
                 kind: ExprKind::BinaryOp(
                     BinaryOpKind::Addition,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: ExprKind::Number(5),
                     }),
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: ExprKind::Number(4),
                     }),
                 )
@@ -337,15 +523,18 @@ mod test {
         assert_eq!(
             new_expr,
             Expr {
+                span: Span::default(),
                 kind: ExprKind::BinaryOp(
                     BinaryOpKind::Addition,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: ExprKind::OwnedIdent(Box::new(Identifier::OptionalOwner {
                             owner: Some("foo".to_string()),
                             name: "yum".to_string()
                         }))
                     }),
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: ExprKind::OwnedIdent(Box::new(Identifier::OptionalOwner {
                             owner: Some("yum".to_string()),
                             name: "baz".to_string()
@@ -364,13 +553,15 @@ mod test {
         let decl = "label foo = bar.baz";
         let decl = parse::label_decl().parse(decl.as_bytes()).unwrap();
         let relabeler = Relabeler::new(&relabeling);
-        let new_decl = relabeler.relabel_label(&decl).unwrap();
+        let new_decl = relabeler.relabel_label(Span::default(), &decl).unwrap();
         assert_eq!(
             new_decl,
             Decl {
+                span: Span::default(),
                 kind: DeclKind::Label(Box::new(LabelDecl {
                     index: 0usize,
                     condition: Expr {
+                        span: Span::default(),
                         kind: ExprKind::OwnedIdent(Box::new(Identifier::OptionalOwner {
                             owner: Some("bar".to_string()),
                             name: "baz".to_string()
@@ -1,9 +1,30 @@
-use crate::lcgs::ast::{BinaryOpKind, DeclKind, Expr, ExprKind, Identifier, UnaryOpKind};
+use std::cell::RefCell;
+
+use crate::lcgs::ast::{
+    int_clamp, int_pow, BinaryOpKind, DeclKind, Expr, ExprKind, Identifier, RelabelCase,
+    Relabeling, Span, UnaryOpKind,
+};
+use crate::lcgs::ir::relabeling::Relabeler;
 use crate::lcgs::ir::symbol_table::{Owner, SymbolIdentifier, SymbolTable};
 
 #[derive(Debug)]
 pub struct SymbolError {
     pub msg: String,
+    /// The span of the offending expression. Errors are created with an empty span, which
+    /// is replaced by the span of the innermost expression being checked, when the error
+    /// propagates through [SymbolChecker::check].
+    pub span: Span,
+}
+
+/// Formats an unresolved identifier as written by the user
+fn id_to_string(id: &Identifier) -> String {
+    match id {
+        Identifier::OptionalOwner {
+            owner: Some(owner),
+            name,
+        } => format!("{}.{}", owner, name),
+        _ => id.name().to_string(),
+    }
 }
 
 /// [CheckMode]s control which declaration identifiers are allow to refer to in the [SymbolChecker].
@@ -42,6 +63,8 @@ pub struct SymbolChecker<'a> {
     symbols: &'a SymbolTable,
     scope_owner: Owner,
     mode: CheckMode,
+    /// The macros currently being expanded, used to detect recursive macros
+    expanding: RefCell<Vec<SymbolIdentifier>>,
 }
 
 impl<'a> SymbolChecker<'a> {
@@ -50,6 +73,7 @@ impl<'a> SymbolChecker<'a> {
             symbols,
             scope_owner,
             mode,
+            expanding: RefCell::new(vec![]),
         }
     }
 
@@ -60,20 +84,45 @@ impl<'a> SymbolChecker<'a> {
         if let ExprKind::Number(n) = checked.kind {
             Ok(n)
         } else {
-            panic!("Constant expression was not reduced to a single number.")
+            Err(SymbolError {
+                msg: "Expected constant expression, which could be reduced to a single number."
+                    .to_string(),
+                span: expr.span,
+            })
         }
     }
 
-    /// Checks the given expressions
+    /// Checks the given expressions. The resulting expression has the same span as the
+    /// given expression.
     pub fn check(&self, expr: &Expr) -> Result<Expr, SymbolError> {
+        match self.check_kind(expr) {
+            Ok(mut res) => {
+                res.span = expr.span;
+                Ok(res)
+            }
+            Err(mut err) => {
+                if err.span == Span::default() {
+                    err.span = expr.span;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn check_kind(&self, expr: &Expr) -> Result<Expr, SymbolError> {
         match &expr.kind {
             ExprKind::Number(_) => Ok(expr.clone()),
             ExprKind::OwnedIdent(id) => self.check_ident(id),
+            ExprKind::Index(id, index) => self.check_index(id, index),
+            ExprKind::Call(id, args) => self.check_call(id, args),
             ExprKind::UnaryOp(op, expr) => self.check_unop(op, expr),
             ExprKind::BinaryOp(op, e1, e2) => self.check_binop(op, e1, e2),
             ExprKind::TernaryIf(c, e1, e2) => self.check_if(c, e1, e2),
             ExprKind::Min(ls) => self.check_min(ls),
             ExprKind::Max(ls) => self.check_max(ls),
+            ExprKind::Abs(e) => self.check_abs(e),
+            ExprKind::Pow(base, exp) => self.check_pow(base, exp),
+            ExprKind::Clamp(e, lo, hi) => self.check_clamp(e, lo, hi),
         }
     }
 
@@ -98,6 +147,7 @@ impl<'a> SymbolChecker<'a> {
                                 "Expected constant expression. Found '{}.{}', which is not constant, since it is owned by a player.",
                                 player_name, name,
                             ),
+                            span: Span::default(),
                         });
                     } else {
                         self.symbols.get(&Owner::Global, &name).ok_or(SymbolError {
@@ -105,6 +155,7 @@ impl<'a> SymbolChecker<'a> {
                                 "Expected constant expression. Found unknown constant '{}'.",
                                 name
                             ),
+                            span: Span::default(),
                         })?
                     }
                 } else if let Some(player_name) = owner {
@@ -114,6 +165,7 @@ impl<'a> SymbolChecker<'a> {
                         .get(&Owner::Global, player_name)
                         .ok_or(SymbolError {
                             msg: format!("Unknown player '{}'.", player_name),
+                            span: Span::default(),
                         })?;
 
                     // The player exists, so now we fetch the symbol
@@ -121,7 +173,8 @@ impl<'a> SymbolChecker<'a> {
                     self.symbols
                             .get(&owner, &name)
                             .ok_or(SymbolError {
-                                msg: format!("Unknown identifier '{}.{}'. The player does not own a declaration of that name.", owner, name)
+                                msg: format!("Unknown identifier '{}.{}'. The player does not own a declaration of that name.", owner, name),
+                                span: Span::default(),
                             })?
                 } else {
                     // Player is omitted. Assume it is scope owner. If not, then try global.
@@ -133,13 +186,21 @@ impl<'a> SymbolChecker<'a> {
                                 "Unknown identifier '{}', neither declared in player's scope or globally",
                                 name
                             ),
+                            span: Span::default(),
                         })?
                 }
             }
-            // Already resolved once ... which should never happen.
-            Identifier::Resolved { .. } => panic!("Identifier was already resolved once."),
+            // The values of updates performed by actions are checked in the scope of the
+            // acting player before they become part of the updated variable's expression
+            Identifier::Resolved { .. } => {
+                return Ok(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::OwnedIdent(Box::new(id.clone())),
+                })
+            }
         };
 
+        self.symbols.mark_used(&symb.identifier);
         if let Ok(declaration) = &symb.declaration.try_borrow() {
             // Check if symbol is allowed to be referenced in this mode
             if !self.mode.allows(&declaration.kind) {
@@ -153,6 +214,7 @@ impl<'a> SymbolChecker<'a> {
                         "The declaration '{}' cannot be referenced in {}.",
                         symb.identifier, context
                     ),
+                    span: Span::default(),
                 });
             }
 
@@ -164,6 +226,7 @@ impl<'a> SymbolChecker<'a> {
             // Identifier is okay. Return a resolved identifier where owner is specified.
             let SymbolIdentifier { owner, name } = &symb.identifier;
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::OwnedIdent(Box::new(Identifier::Resolved {
                     owner: owner.clone(),
                     name: name.clone(),
@@ -177,6 +240,7 @@ impl<'a> SymbolChecker<'a> {
             // in CheckMode::StateVarUpdate. In such case we can return immediately.
             if self.mode == CheckMode::StateVarUpdate {
                 Ok(Expr {
+                    span: Span::default(),
                     kind: ExprKind::OwnedIdent(Box::new(Identifier::Resolved {
                         owner: symb.identifier.owner.clone(),
                         name: symb.identifier.name.clone(),
@@ -185,20 +249,179 @@ impl<'a> SymbolChecker<'a> {
             } else {
                 Err(SymbolError {
                     msg: format!("The definition of '{}' refers to itself.", symb.identifier),
+                    span: Span::default(),
                 })
             }
         }
     }
 
+    /// Checks the given array index. Arrays are expanded to a variable per element, so an index
+    /// that can be evaluated now is resolved to the element directly. Otherwise the index is
+    /// replaced by a chain of ternary ifs selecting the element. Such an index evaluates to 0
+    /// if it is out of bounds.
+    fn check_index(&self, id: &Identifier, index: &Expr) -> Result<Expr, SymbolError> {
+        let (owner, name) = match id {
+            Identifier::OptionalOwner { owner, name } => (owner, name),
+            _ => panic!("Array identifiers should not be resolved before symbol checking."),
+        };
+        let element = |i: usize| Identifier::OptionalOwner {
+            owner: owner.clone(),
+            name: format!("{}[{}]", name, i),
+        };
+
+        // The size of the array is the number of element declarations. The elements
+        // are searched for in the same scopes as other identifiers.
+        let scopes = match owner {
+            Some(player_name) => vec![Owner::Player(player_name.clone())],
+            None => vec![self.scope_owner.clone(), Owner::Global],
+        };
+        let size = scopes
+            .iter()
+            .map(|scope| {
+                (0..)
+                    .take_while(|i| self.symbols.get(scope, element(*i).name()).is_some())
+                    .count()
+            })
+            .find(|size| *size > 0)
+            .ok_or_else(|| SymbolError {
+                msg: format!("Unknown array '{}'.", id_to_string(id)),
+                span: Span::default(),
+            })?;
+
+        let index = self.check(index)?;
+        if let ExprKind::Number(i) = index.kind {
+            if i < 0 || i as usize >= size {
+                return Err(SymbolError {
+                    msg: format!(
+                        "Index {} is out of bounds for '{}' of size {}.",
+                        i,
+                        id_to_string(id),
+                        size
+                    ),
+                    span: Span::default(),
+                });
+            }
+            return self.check_ident(&element(i as usize));
+        }
+
+        // Build the chain from the last element and outwards
+        let mut res = Expr {
+            span: Span::default(),
+            kind: ExprKind::Number(0),
+        };
+        for i in (0..size).rev() {
+            let cond = Expr {
+                span: Span::default(),
+                kind: ExprKind::BinaryOp(
+                    BinaryOpKind::Equality,
+                    Box::new(index.clone()),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: ExprKind::Number(i as i32),
+                    }),
+                ),
+            };
+            res = Expr {
+                span: Span::default(),
+                kind: ExprKind::TernaryIf(
+                    Box::new(cond),
+                    Box::new(self.check_ident(&element(i))?),
+                    Box::new(res),
+                ),
+            };
+        }
+        Ok(res)
+    }
+
+    /// Checks the given macro call by expanding it. The parameters in the body of the macro are
+    /// replaced by the arguments and the result is checked in the scope of the call.
+    fn check_call(&self, id: &Identifier, args: &[Expr]) -> Result<Expr, SymbolError> {
+        let (owner, name) = match id {
+            Identifier::OptionalOwner { owner, name } => (owner, name),
+            _ => panic!("Macro identifiers should not be resolved before symbol checking."),
+        };
+        let symb = match owner {
+            Some(player_name) => self.symbols.get(&Owner::Player(player_name.clone()), name),
+            None => self
+                .symbols
+                .get(&self.scope_owner, name)
+                .or_else(|| self.symbols.get(&Owner::Global, name)),
+        }
+        .ok_or_else(|| SymbolError {
+            msg: format!("Unknown macro '{}'.", id_to_string(id)),
+            span: Span::default(),
+        })?;
+
+        let declaration = symb.declaration.borrow();
+        let define = match &declaration.kind {
+            DeclKind::Define(define) => define,
+            _ => {
+                return Err(SymbolError {
+                    msg: format!("'{}' is not a macro.", id_to_string(id)),
+                    span: Span::default(),
+                })
+            }
+        };
+        if define.params.len() != args.len() {
+            return Err(SymbolError {
+                msg: format!(
+                    "The macro '{}' expects {} argument(s), but {} were given.",
+                    id_to_string(id),
+                    define.params.len(),
+                    args.len()
+                ),
+                span: Span::default(),
+            });
+        }
+        if self.expanding.borrow().contains(&symb.identifier) {
+            return Err(SymbolError {
+                msg: format!(
+                    "The macro '{}' expands to a call of itself.",
+                    symb.identifier
+                ),
+                span: Span::default(),
+            });
+        }
+
+        let relabeling = Relabeling {
+            relabellings: define
+                .params
+                .iter()
+                .zip(args)
+                .map(|(param, arg)| RelabelCase {
+                    prev: param.clone(),
+                    new: arg.clone(),
+                })
+                .collect(),
+        };
+        let expanded = Relabeler::new(&relabeling)
+            .relabel_expr(&define.body)
+            .map_err(|err| SymbolError {
+                msg: err.msg,
+                span: err.span,
+            })?;
+
+        self.expanding.borrow_mut().push(symb.identifier.clone());
+        let res = self.check(&expanded);
+        self.expanding.borrow_mut().pop();
+        res
+    }
+
     /// Optimizes the given unary operator and checks the operand
     fn check_unop(&self, op: &UnaryOpKind, expr: &Expr) -> Result<Expr, SymbolError> {
         let res = self.check(expr)?;
         if let ExprKind::Number(n) = &res.kind {
+            let value = op.checked_apply(*n).ok_or_else(|| SymbolError {
+                msg: format!("Integer overflow. The negation of {} is too large.", n),
+                span: Span::default(),
+            })?;
             return Ok(Expr {
-                kind: ExprKind::Number(op.as_fn()(*n)),
+                span: Span::default(),
+                kind: ExprKind::Number(value),
             });
         }
         Ok(Expr {
+            span: Span::default(),
             kind: ExprKind::UnaryOp(op.clone(), Box::new(res)),
         })
     }
@@ -209,14 +432,29 @@ impl<'a> SymbolChecker<'a> {
         // TODO Some operators allow optimizations even when only one operand is a number
         let res1 = self.check(e1)?;
         let res2 = self.check(e2)?;
+        if op.divides() && res2.kind == ExprKind::Number(0) {
+            return Err(SymbolError {
+                msg: format!("Division by zero. The right hand side of '{}' is 0.", op),
+                span: Span::default(),
+            });
+        }
         if let ExprKind::Number(n1) = &res1.kind {
             if let ExprKind::Number(n2) = &res2.kind {
+                let value = op.checked_apply(*n1, *n2).ok_or_else(|| SymbolError {
+                    msg: format!(
+                        "Integer overflow. The value of '{} {} {}' is too large.",
+                        n1, op, n2
+                    ),
+                    span: Span::default(),
+                })?;
                 return Ok(Expr {
-                    kind: ExprKind::Number(op.as_fn()(*n1, *n2)),
+                    span: Span::default(),
+                    kind: ExprKind::Number(value),
                 });
             }
         }
         Ok(Expr {
+            span: Span::default(),
             kind: ExprKind::BinaryOp(op.clone(), Box::new(res1), Box::new(res2)),
         })
     }
@@ -232,6 +470,7 @@ impl<'a> SymbolChecker<'a> {
             };
         }
         Ok(Expr {
+            span: Span::default(),
             kind: ExprKind::TernaryIf(
                 Box::new(cond_res),
                 Box::new(self.check(e1)?),
@@ -239,10 +478,82 @@ impl<'a> SymbolChecker<'a> {
             ),
         })
     }
+
+    /// Optimizes the given abs expression and checks the operand
+    fn check_abs(&self, expr: &Expr) -> Result<Expr, SymbolError> {
+        let res = self.check(expr)?;
+        if let ExprKind::Number(n) = &res.kind {
+            return Ok(Expr {
+                span: Span::default(),
+                kind: ExprKind::Number(n.saturating_abs()),
+            });
+        }
+        Ok(Expr {
+            span: Span::default(),
+            kind: ExprKind::Abs(Box::new(res)),
+        })
+    }
+
+    /// Optimizes the given pow expression and checks the operands
+    fn check_pow(&self, base: &Expr, exp: &Expr) -> Result<Expr, SymbolError> {
+        let base_res = self.check(base)?;
+        let exp_res = self.check(exp)?;
+        if let ExprKind::Number(b) = &base_res.kind {
+            if let ExprKind::Number(e) = &exp_res.kind {
+                if *b == 0 && *e < 0 {
+                    return Err(SymbolError {
+                        msg: "Division by zero. Cannot raise 0 to a negative power.".to_string(),
+                        span: Span::default(),
+                    });
+                }
+                return Ok(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(int_pow(*b, *e)),
+                });
+            }
+        }
+        Ok(Expr {
+            span: Span::default(),
+            kind: ExprKind::Pow(Box::new(base_res), Box::new(exp_res)),
+        })
+    }
+
+    /// Optimizes the given clamp expression and checks the operands. The bounds are
+    /// required to be ordered if both are known.
+    fn check_clamp(&self, expr: &Expr, lo: &Expr, hi: &Expr) -> Result<Expr, SymbolError> {
+        let res = self.check(expr)?;
+        let lo_res = self.check(lo)?;
+        let hi_res = self.check(hi)?;
+        if let (ExprKind::Number(l), ExprKind::Number(h)) = (&lo_res.kind, &hi_res.kind) {
+            if l > h {
+                return Err(SymbolError {
+                    msg: format!(
+                        "The lower bound of clamp ({}) is greater than the upper bound ({}).",
+                        l, h
+                    ),
+                    span: Span::default(),
+                });
+            }
+            if let ExprKind::Number(n) = &res.kind {
+                return Ok(Expr {
+                    span: Span::default(),
+                    kind: ExprKind::Number(int_clamp(*n, *l, *h)),
+                });
+            }
+        }
+        Ok(Expr {
+            span: Span::default(),
+            kind: ExprKind::Clamp(Box::new(res), Box::new(lo_res), Box::new(hi_res)),
+        })
+    }
+
     /// First combines all numbers, as we already know the min of that
     /// Then returns a new checked Vec of Expr to find Min of.
     fn check_min(&self, ls: &[Expr]) -> Result<Expr, SymbolError> {
-        let checked_list: Vec<Expr> = ls.iter().map(|p| self.check(p).unwrap()).collect();
+        let checked_list = ls
+            .iter()
+            .map(|p| self.check(p))
+            .collect::<Result<Vec<Expr>, SymbolError>>()?;
         let number: Option<i32> = checked_list
             .iter()
             .filter_map(|p| match p.kind {
@@ -257,18 +568,22 @@ impl<'a> SymbolChecker<'a> {
                 .collect();
             if res.is_empty() {
                 Ok(Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(x),
                 })
             } else {
                 res.push(Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(x),
                 });
                 Ok(Expr {
+                    span: Span::default(),
                     kind: ExprKind::Min(res),
                 })
             }
         } else {
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Min(checked_list),
             })
         }
@@ -276,7 +591,10 @@ impl<'a> SymbolChecker<'a> {
     /// First combines all numbers, as we already know the max of that
     /// Then returns a new checked Vec of Expr to find Max of.
     fn check_max(&self, ls: &[Expr]) -> Result<Expr, SymbolError> {
-        let checked_list: Vec<Expr> = ls.iter().map(|p| self.check(p).unwrap()).collect();
+        let checked_list = ls
+            .iter()
+            .map(|p| self.check(p))
+            .collect::<Result<Vec<Expr>, SymbolError>>()?;
         let number: Option<i32> = checked_list
             .iter()
             .filter_map(|p| match p.kind {
@@ -291,18 +609,22 @@ impl<'a> SymbolChecker<'a> {
                 .collect();
             if res.is_empty() {
                 Ok(Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(x),
                 })
             } else {
                 res.push(Expr {
+                    span: Span::default(),
                     kind: ExprKind::Number(x),
                 });
                 Ok(Expr {
+                    span: Span::default(),
                     kind: ExprKind::Max(res),
                 })
             }
         } else {
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Max(checked_list),
             })
         }
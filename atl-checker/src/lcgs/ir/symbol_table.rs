@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 
 use crate::lcgs::ast::Decl;
@@ -49,15 +49,29 @@ pub struct Symbol {
 #[derive(Clone, Debug)]
 pub struct SymbolTable {
     symbols: HashMap<SymbolIdentifier, Symbol>,
+    /// Symbols that have been referenced by an expression. Constants are inlined, so this is
+    /// the only record of whether they are used.
+    used: RefCell<HashSet<SymbolIdentifier>>,
 }
 
 impl SymbolTable {
     pub fn new() -> SymbolTable {
         SymbolTable {
             symbols: HashMap::new(),
+            used: RefCell::new(HashSet::new()),
         }
     }
 
+    /// Records that the given symbol is referenced by an expression
+    pub fn mark_used(&self, symb_id: &SymbolIdentifier) {
+        self.used.borrow_mut().insert(symb_id.clone());
+    }
+
+    /// Returns the symbols that have been referenced by an expression so far
+    pub fn used(&self) -> HashSet<SymbolIdentifier> {
+        self.used.borrow().clone()
+    }
+
     pub fn len(&self) -> usize {
         self.symbols.len()
     }
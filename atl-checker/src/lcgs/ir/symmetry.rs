@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::atl::common;
+use crate::lcgs::ast::{BinaryOpKind, Decl, DeclKind, Expr, ExprKind, Identifier};
+use crate::lcgs::format::format_expr;
+use crate::lcgs::ir::intermediate::Player;
+use crate::lcgs::ir::symbol_table::{Owner, SymbolIdentifier};
+
+/// The maximum number of permutations generated from the detected symmetries. If the group
+/// is larger, only some of its permutations are used. States are then not always mapped to
+/// the same representative of their orbit, so the reduction is smaller, but still sound.
+const MAX_SYMMETRIES: usize = 5040;
+
+/// A [SymmetryFinder] detects permutations of players that map the game onto itself. Such a
+/// permutation maps the variables and actions of each player to the variables and actions of
+/// another player instantiated from the same template, and a state to a state that satisfies
+/// the same formulas, as long as the permutation preserves the labels and coalitions of the
+/// formulas. Expressions are compared syntactically modulo commutativity, so not all
+/// symmetries are found.
+pub struct SymmetryFinder<'a> {
+    symbols: &'a HashMap<SymbolIdentifier, Decl>,
+    vars: &'a [SymbolIdentifier],
+    players: &'a [Player],
+}
+
+impl<'a> SymmetryFinder<'a> {
+    pub fn new(
+        symbols: &'a HashMap<SymbolIdentifier, Decl>,
+        vars: &'a [SymbolIdentifier],
+        players: &'a [Player],
+    ) -> SymmetryFinder<'a> {
+        SymmetryFinder {
+            symbols,
+            vars,
+            players,
+        }
+    }
+
+    /// Returns the symmetries that preserve the given labels and coalitions as permutations of
+    /// the state variables, excluding the identity. Each permutation maps the position of a
+    /// variable in `vars` to the position of its image.
+    pub fn find(
+        &self,
+        labels: &[SymbolIdentifier],
+        coalitions: &[Vec<common::Player>],
+    ) -> Vec<Vec<usize>> {
+        let generators = self
+            .candidates()
+            .into_iter()
+            .filter_map(|perm| self.var_permutation(&perm, labels, coalitions))
+            .collect();
+        generate_group(generators)
+    }
+
+    /// Returns the candidate permutations of players. These are the transpositions of two
+    /// players instantiated from the same template, and the rotation of all players
+    /// instantiated from the same template in order of declaration, which is the symmetry
+    /// of players arranged in a ring.
+    fn candidates(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(&str, Vec<usize>)> = vec![];
+        for player in self.players {
+            let decl = &self.symbols[&Owner::Global.symbol_id(&player.get_name())];
+            if let DeclKind::Player(player_decl) = &decl.kind {
+                let template = player_decl.template.name();
+                match groups.iter_mut().find(|(name, _)| *name == template) {
+                    Some((_, group)) => group.push(player.index()),
+                    None => groups.push((template, vec![player.index()])),
+                }
+            }
+        }
+
+        let identity: Vec<usize> = (0..self.players.len()).collect();
+        let mut candidates = vec![];
+        for (_, group) in groups {
+            for (i, &p) in group.iter().enumerate() {
+                for &q in &group[i + 1..] {
+                    let mut perm = identity.clone();
+                    perm.swap(p, q);
+                    candidates.push(perm);
+                }
+            }
+            if group.len() > 2 {
+                let mut perm = identity.clone();
+                for (i, &p) in group.iter().enumerate() {
+                    perm[p] = group[(i + 1) % group.len()];
+                }
+                candidates.push(perm);
+            }
+        }
+        candidates
+    }
+
+    /// Returns the permutation of state variables induced by the given permutation of players,
+    /// if the permutation of players maps the game onto itself and preserves the given labels
+    /// and coalitions.
+    fn var_permutation(
+        &self,
+        perm: &[usize],
+        labels: &[SymbolIdentifier],
+        coalitions: &[Vec<common::Player>],
+    ) -> Option<Vec<usize>> {
+        let preserves_coalitions = coalitions.iter().all(|coalition| {
+            let image: HashSet<usize> = coalition.iter().map(|&p| perm[p]).collect();
+            image == coalition.iter().cloned().collect()
+        });
+        if !preserves_coalitions {
+            return None;
+        }
+
+        let owners: HashMap<Owner, Owner> = self
+            .players
+            .iter()
+            .map(|player| {
+                let image = &self.players[perm[player.index()]];
+                (player.to_owner(), image.to_owner())
+            })
+            .collect();
+        let image = |symb_id: &SymbolIdentifier| match owners.get(&symb_id.owner) {
+            Some(owner) => owner.symbol_id(&symb_id.name),
+            None => symb_id.clone(),
+        };
+        let maps_to = |expr: &Expr, target: &Expr| {
+            normalize(&rename_owners(expr, &owners)) == normalize(target)
+        };
+
+        // The labels of the formulas must hold in the same states as before
+        for label_id in labels {
+            match &self.symbols[label_id].kind {
+                DeclKind::Label(label)
+                    if image(label_id) == *label_id
+                        && maps_to(&label.condition, &label.condition) => {}
+                _ => return None,
+            }
+        }
+
+        let positions: HashMap<&SymbolIdentifier, usize> = self
+            .vars
+            .iter()
+            .enumerate()
+            .map(|(i, symb_id)| (symb_id, i))
+            .collect();
+        let mut var_perm = Vec::with_capacity(self.vars.len());
+        for symb_id in self.vars {
+            let image_id = image(symb_id);
+            let &j = positions.get(&image_id)?;
+            match (&self.symbols[symb_id].kind, &self.symbols[&image_id].kind) {
+                (DeclKind::StateVar(var), DeclKind::StateVar(image_var))
+                    if var.ir_range == image_var.ir_range
+                        && var.var_type == image_var.var_type
+                        && var.range_policy == image_var.range_policy
+                        && maps_to(&var.next_value, &image_var.next_value) =>
+                {
+                    var_perm.push(j)
+                }
+                _ => return None,
+            }
+        }
+
+        // Actions must correspond one-to-one, since moves are numbered in order of declaration
+        for player in self.players {
+            let image_player = &self.players[perm[player.index()]];
+            if player.actions().len() != image_player.actions().len() {
+                return None;
+            }
+            for (action, image_action) in player.actions().iter().zip(image_player.actions()) {
+                match (&self.symbols[action].kind, &self.symbols[image_action].kind) {
+                    (DeclKind::Transition(tran), DeclKind::Transition(image_tran))
+                        if image(action) == *image_action
+                            && maps_to(&tran.condition, &image_tran.condition) => {}
+                    _ => return None,
+                }
+            }
+        }
+
+        Some(var_perm)
+    }
+}
+
+/// Returns all compositions of the given permutations, excluding the identity, up to
+/// [MAX_SYMMETRIES] permutations
+fn generate_group(generators: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    let identity: Vec<usize> = match generators.first() {
+        Some(generator) => (0..generator.len()).collect(),
+        None => return vec![],
+    };
+    let mut group = HashSet::new();
+    let mut queue = vec![identity.clone()];
+    group.insert(identity.clone());
+    while let Some(perm) = queue.pop() {
+        for generator in &generators {
+            if group.len() >= MAX_SYMMETRIES {
+                break;
+            }
+            let composed: Vec<usize> = perm.iter().map(|&i| generator[i]).collect();
+            if group.insert(composed.clone()) {
+                queue.push(composed);
+            }
+        }
+    }
+    group.remove(&identity);
+    group.into_iter().collect()
+}
+
+/// Returns a copy of the expression where the owners of identifiers are replaced
+fn rename_owners(expr: &Expr, owners: &HashMap<Owner, Owner>) -> Expr {
+    let rename = |e: &Expr| Box::new(rename_owners(e, owners));
+    let kind = match &expr.kind {
+        ExprKind::OwnedIdent(ident) => match ident.as_ref() {
+            Identifier::Resolved { owner, name } if owners.contains_key(owner) => {
+                ExprKind::OwnedIdent(Box::new(Identifier::Resolved {
+                    owner: owners[owner].clone(),
+                    name: name.clone(),
+                }))
+            }
+            _ => expr.kind.clone(),
+        },
+        ExprKind::Number(_) | ExprKind::Index(..) | ExprKind::Call(..) => expr.kind.clone(),
+        ExprKind::UnaryOp(op, e) => ExprKind::UnaryOp(op.clone(), rename(e)),
+        ExprKind::Abs(e) => ExprKind::Abs(rename(e)),
+        ExprKind::BinaryOp(op, e1, e2) => ExprKind::BinaryOp(op.clone(), rename(e1), rename(e2)),
+        ExprKind::Pow(e1, e2) => ExprKind::Pow(rename(e1), rename(e2)),
+        ExprKind::TernaryIf(e1, e2, e3) => ExprKind::TernaryIf(rename(e1), rename(e2), rename(e3)),
+        ExprKind::Clamp(e1, e2, e3) => ExprKind::Clamp(rename(e1), rename(e2), rename(e3)),
+        ExprKind::Min(es) => ExprKind::Min(es.iter().map(|e| rename_owners(e, owners)).collect()),
+        ExprKind::Max(es) => ExprKind::Max(es.iter().map(|e| rename_owners(e, owners)).collect()),
+    };
+    Expr {
+        span: expr.span,
+        kind,
+    }
+}
+
+/// Returns an equivalent expression where the operands of commutative operators are sorted,
+/// such that e.g. "`p1.alive || p2.alive`" and "`p2.alive || p1.alive`" are equal
+fn normalize(expr: &Expr) -> Expr {
+    let kind = match &expr.kind {
+        ExprKind::BinaryOp(op, ..) if is_associative(op) => {
+            let mut operands = vec![];
+            flatten(op, expr, &mut operands);
+            let mut operands = sorted(operands.into_iter().map(normalize).collect());
+            let first = operands.remove(0);
+            return operands.into_iter().fold(first, |acc, operand| Expr {
+                span: expr.span,
+                kind: ExprKind::BinaryOp(op.clone(), Box::new(acc), Box::new(operand)),
+            });
+        }
+        ExprKind::BinaryOp(op @ (BinaryOpKind::Equality | BinaryOpKind::Inequality), e1, e2) => {
+            let mut operands = sorted(vec![normalize(e1), normalize(e2)]).into_iter();
+            ExprKind::BinaryOp(
+                op.clone(),
+                Box::new(operands.next().unwrap()),
+                Box::new(operands.next().unwrap()),
+            )
+        }
+        ExprKind::BinaryOp(op, e1, e2) => {
+            ExprKind::BinaryOp(op.clone(), Box::new(normalize(e1)), Box::new(normalize(e2)))
+        }
+        ExprKind::Number(_)
+        | ExprKind::OwnedIdent(_)
+        | ExprKind::Index(..)
+        | ExprKind::Call(..) => expr.kind.clone(),
+        ExprKind::UnaryOp(op, e) => ExprKind::UnaryOp(op.clone(), Box::new(normalize(e))),
+        ExprKind::Abs(e) => ExprKind::Abs(Box::new(normalize(e))),
+        ExprKind::Pow(e1, e2) => ExprKind::Pow(Box::new(normalize(e1)), Box::new(normalize(e2))),
+        ExprKind::TernaryIf(e1, e2, e3) => ExprKind::TernaryIf(
+            Box::new(normalize(e1)),
+            Box::new(normalize(e2)),
+            Box::new(normalize(e3)),
+        ),
+        ExprKind::Clamp(e1, e2, e3) => ExprKind::Clamp(
+            Box::new(normalize(e1)),
+            Box::new(normalize(e2)),
+            Box::new(normalize(e3)),
+        ),
+        ExprKind::Min(es) => ExprKind::Min(sorted(es.iter().map(normalize).collect())),
+        ExprKind::Max(es) => ExprKind::Max(sorted(es.iter().map(normalize).collect())),
+    };
+    Expr {
+        span: expr.span,
+        kind,
+    }
+}
+
+/// Whether the operator is both associative and commutative, such that a chain of it can be
+/// reordered freely
+fn is_associative(op: &BinaryOpKind) -> bool {
+    matches!(
+        op,
+        BinaryOpKind::Addition
+            | BinaryOpKind::Multiplication
+            | BinaryOpKind::And
+            | BinaryOpKind::Or
+            | BinaryOpKind::Xor
+    )
+}
+
+/// Collects the operands of a chain of the given operator
+fn flatten<'e>(op: &BinaryOpKind, expr: &'e Expr, operands: &mut Vec<&'e Expr>) {
+    match &expr.kind {
+        ExprKind::BinaryOp(other, e1, e2) if other == op => {
+            flatten(op, e1, operands);
+            flatten(op, e2, operands);
+        }
+        _ => operands.push(expr),
+    }
+}
+
+/// Sorts expressions by their textual representation
+fn sorted(mut exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs.sort_by_cached_key(format_expr);
+    exprs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::atl::formula::parse_phi;
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::parse::parse_lcgs;
+
+    fn symmetries(input: &str, formula: &str) -> usize {
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        let phi = parse_phi(&lcgs, formula).unwrap();
+        lcgs.symmetry_reduction(&phi).symmetry_count()
+    }
+
+    #[test]
+    fn test_symmetry_01() {
+        // Three shooters in a ring. The rotations are symmetries, but the transpositions are
+        // not, since each player shoots left and right. The global label is invariant up to
+        // commutativity of '&&'.
+        let input = "
+        label all_dead = p1.health == 0 && p2.health == 0 && p3.health == 0;
+        player p1 = shooter [left=p2, right=p3];
+        player p2 = shooter [left=p3, right=p1];
+        player p3 = shooter [left=p1, right=p2];
+        template shooter
+            label alive = health > 0;
+            health : [0 .. 1] init 1;
+            health' = max(health - (left.shoot_right + right.shoot_left), 0);
+            [wait] 1;
+            [shoot_left] health > 0 && left.health > 0;
+            [shoot_right] health > 0 && right.health > 0;
+        endtemplate
+        ";
+        assert_eq!(symmetries(input, "<<p1, p2, p3>> F all_dead"), 2);
+        // Coalitions and labels of players must be preserved
+        assert_eq!(symmetries(input, "<<p1>> F all_dead"), 0);
+        assert_eq!(symmetries(input, "<<>> F p1.alive"), 0);
+    }
+
+    #[test]
+    fn test_symmetry_02() {
+        // Identical players that do not interact. All permutations are symmetries, except
+        // those that move the player of the coalition.
+        let input = "
+        player p1 = coin;
+        player p2 = coin;
+        player p3 = coin;
+        player p4 = coin;
+        template coin
+            heads : [0 .. 1] init 0;
+            heads' = flip ? 1 - heads : heads;
+            [flip] 1;
+            [keep] 1;
+        endtemplate
+        label all_heads = p1.heads + p2.heads + p3.heads + p4.heads == 4;
+        ";
+        assert_eq!(symmetries(input, "<<>> F all_heads"), 23);
+        assert_eq!(symmetries(input, "<<p1>> F all_heads"), 5);
+        // A player with a different template is never permuted
+        let input = input.replace("player p4 = coin;", "player p4 = other;")
+            + "
+        template other
+            heads : [0 .. 1] init 0;
+            heads' = heads;
+            [keep] 1;
+        endtemplate";
+        assert_eq!(symmetries(&input, "<<>> F all_heads"), 5);
+    }
+}
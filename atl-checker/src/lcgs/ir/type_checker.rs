@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use crate::lcgs::ast::{
+    BinaryOpKind, Decl, DeclKind, Expr, ExprKind, Identifier, Span, UnaryOpKind, VarType,
+};
+use crate::lcgs::ir::symbol_table::SymbolIdentifier;
+
+/// The types of LCGS expressions. All values are integers at runtime, so the types only
+/// exist to catch mistakes such as "`health + alive`".
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Type {
+    Int,
+    Bool,
+    /// The literals 0 and 1 (including `false` and `true`) can be used as both types
+    Bit,
+}
+
+impl Type {
+    fn name(&self) -> &'static str {
+        match self {
+            Type::Int => "an integer",
+            Type::Bool => "a boolean",
+            Type::Bit => "a bit",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Severity {
+    /// Operands of mismatched types, e.g. "`alive == health`". Such expressions are almost
+    /// always mistakes.
+    Error,
+    /// Implicit conversions between integers and booleans, which older models rely on,
+    /// e.g. "`health - target.shoot`" or "`p0.deadlock || p1.deadlock`" over `[0 .. 1]` variables.
+    Warning,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TypeDiagnostic {
+    pub severity: Severity,
+    pub msg: String,
+    pub span: Span,
+}
+
+/// A [TypeChecker] infers the types of symbol checked expressions and collects
+/// [TypeDiagnostic]s where types do not match. Expressions are assumed to be symbol checked,
+/// i.e. identifiers are resolved and constants are inlined.
+pub struct TypeChecker<'a> {
+    symbols: &'a HashMap<SymbolIdentifier, Decl>,
+    diagnostics: Vec<TypeDiagnostic>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(symbols: &'a HashMap<SymbolIdentifier, Decl>) -> TypeChecker<'a> {
+        TypeChecker {
+            symbols,
+            diagnostics: vec![],
+        }
+    }
+
+    /// Checks the expressions of the given declaration
+    pub fn check_decl(&mut self, decl: &Decl) {
+        match &decl.kind {
+            DeclKind::Label(label) => self.expect(&label.condition, Type::Bool),
+            DeclKind::Transition(tran) => self.expect(&tran.condition, Type::Bool),
+            DeclKind::StateVar(var) => {
+                let expected = match var.var_type {
+                    VarType::Bool => Type::Bool,
+                    _ => Type::Int,
+                };
+                self.expect(&var.next_value, expected)
+            }
+            // Macros are checked where they are expanded
+            DeclKind::Const(_)
+            | DeclKind::Player(_)
+            | DeclKind::Template(_)
+            | DeclKind::Define(_) => {}
+        }
+    }
+
+    /// Returns the collected diagnostics ordered by their position in the program. Diagnostics
+    /// are only reported once, even if the expression is part of a template used by many players.
+    pub fn finish(mut self) -> Vec<TypeDiagnostic> {
+        self.diagnostics.sort_by_key(|diag| diag.span.begin);
+        self.diagnostics.dedup();
+        self.diagnostics
+    }
+
+    /// Checks that the given expression has the expected type
+    fn expect(&mut self, expr: &Expr, expected: Type) {
+        let found = self.infer(expr);
+        match (expected, found) {
+            (Type::Bool, Type::Int) => self.diagnostics.push(TypeDiagnostic {
+                severity: Severity::Warning,
+                msg: "Expected a boolean expression, but found an integer expression. It is implicitly true when non-zero.".to_string(),
+                span: expr.span,
+            }),
+            (Type::Int, Type::Bool) => self.diagnostics.push(TypeDiagnostic {
+                severity: Severity::Warning,
+                msg: "Expected an integer expression, but found a boolean expression. It is implicitly converted to 0 or 1.".to_string(),
+                span: expr.span,
+            }),
+            _ => {}
+        }
+    }
+
+    /// Infers the type of the given expression, while checking the types of its operands
+    pub fn infer(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Number(0) | ExprKind::Number(1) => Type::Bit,
+            ExprKind::Number(_) => Type::Int,
+            ExprKind::OwnedIdent(id) => self.infer_ident(id),
+            ExprKind::Index(..) => {
+                panic!("Unresolved array index. Something went wrong in symbol checking.")
+            }
+            ExprKind::Call(..) => {
+                panic!("Unexpanded macro call. Something went wrong in symbol checking.")
+            }
+            ExprKind::UnaryOp(UnaryOpKind::Not, e) => {
+                self.expect(e, Type::Bool);
+                Type::Bool
+            }
+            ExprKind::UnaryOp(UnaryOpKind::Negation, e) => {
+                self.expect(e, Type::Int);
+                Type::Int
+            }
+            ExprKind::BinaryOp(op, e1, e2) => self.infer_binop(expr, op, e1, e2),
+            ExprKind::TernaryIf(c, e1, e2) => {
+                self.expect(c, Type::Bool);
+                self.infer_same(expr, e1, e2)
+            }
+            ExprKind::Min(exprs) | ExprKind::Max(exprs) => {
+                exprs.iter().for_each(|e| self.expect(e, Type::Int));
+                Type::Int
+            }
+            ExprKind::Abs(e) => {
+                self.expect(e, Type::Int);
+                Type::Int
+            }
+            ExprKind::Pow(base, exp) => {
+                self.expect(base, Type::Int);
+                self.expect(exp, Type::Int);
+                Type::Int
+            }
+            ExprKind::Clamp(e, lo, hi) => {
+                self.expect(e, Type::Int);
+                self.expect(lo, Type::Int);
+                self.expect(hi, Type::Int);
+                Type::Int
+            }
+        }
+    }
+
+    fn infer_ident(&self, id: &Identifier) -> Type {
+        let symb_id = match id {
+            Identifier::Resolved { owner, name } => SymbolIdentifier {
+                owner: owner.clone(),
+                name: name.clone(),
+            },
+            _ => panic!("Unresolved identifier. Something went wrong in symbol checking."),
+        };
+        match self.symbols.get(&symb_id).map(|decl| &decl.kind) {
+            Some(DeclKind::StateVar(var)) if var.var_type == VarType::Bool => Type::Bool,
+            Some(DeclKind::Label(_)) | Some(DeclKind::Transition(_)) => Type::Bool,
+            _ => Type::Int,
+        }
+    }
+
+    fn infer_binop(&mut self, expr: &Expr, op: &BinaryOpKind, e1: &Expr, e2: &Expr) -> Type {
+        match op {
+            BinaryOpKind::Addition
+            | BinaryOpKind::Multiplication
+            | BinaryOpKind::Subtraction
+            | BinaryOpKind::Division
+            | BinaryOpKind::Modulo => {
+                self.expect(e1, Type::Int);
+                self.expect(e2, Type::Int);
+                Type::Int
+            }
+            BinaryOpKind::GreaterThan
+            | BinaryOpKind::LessThan
+            | BinaryOpKind::GreaterOrEqual
+            | BinaryOpKind::LessOrEqual => {
+                self.expect(e1, Type::Int);
+                self.expect(e2, Type::Int);
+                Type::Bool
+            }
+            BinaryOpKind::Equality | BinaryOpKind::Inequality => {
+                self.infer_same(expr, e1, e2);
+                Type::Bool
+            }
+            BinaryOpKind::And
+            | BinaryOpKind::Or
+            | BinaryOpKind::Xor
+            | BinaryOpKind::Implication => {
+                self.expect(e1, Type::Bool);
+                self.expect(e2, Type::Bool);
+                Type::Bool
+            }
+        }
+    }
+
+    /// Infers the types of two expressions that must have the same type, e.g. the operands
+    /// of an equality or the branches of a ternary if. Returns the common type.
+    fn infer_same(&mut self, expr: &Expr, e1: &Expr, e2: &Expr) -> Type {
+        match (self.infer(e1), self.infer(e2)) {
+            (Type::Bit, t) | (t, Type::Bit) => t,
+            (t1, t2) if t1 == t2 => t1,
+            (t1, t2) => {
+                self.diagnostics.push(TypeDiagnostic {
+                    severity: Severity::Error,
+                    msg: format!(
+                        "Mismatched types. The left side is {} expression, but the right side is {} expression.",
+                        t1.name(),
+                        t2.name()
+                    ),
+                    span: expr.span,
+                });
+                Type::Int
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::ir::type_checker::Severity;
+    use crate::lcgs::parse::parse_lcgs;
+
+    fn severities(input: &str) -> Vec<Severity> {
+        let lcgs = IntermediateLCGS::create(parse_lcgs(input).unwrap()).unwrap();
+        lcgs.type_check()
+            .into_iter()
+            .map(|diag| diag.severity)
+            .collect()
+    }
+
+    #[test]
+    fn test_type_check_01() {
+        // A well-typed program
+        let input = "
+        player p1 = thing;
+        template thing
+            x : [0 .. 3] init 0;
+            x' = min(x + 1, 3);
+            flag : bool init false;
+            flag' = x == 3 || flag;
+            [up] !flag && x < 3;
+            [wait] 1;
+        endtemplate
+        label done = p1.flag -> p1.x == 3;
+        ";
+        assert_eq!(severities(input), vec![]);
+    }
+
+    #[test]
+    fn test_type_check_02() {
+        // Integer used as guard is only a warning
+        let input = "
+        player p1 = thing;
+        template thing
+            x : [0 .. 3] init 0;
+            x' = x;
+            [up] x;
+        endtemplate
+        ";
+        assert_eq!(severities(input), vec![Severity::Warning]);
+    }
+
+    #[test]
+    fn test_type_check_03() {
+        // Boolean in arithmetic is only a warning
+        let input = "
+        player p1 = thing;
+        template thing
+            x : [0 .. 3] init 0;
+            x' = x + up;
+            [up] 1;
+        endtemplate
+        ";
+        assert_eq!(severities(input), vec![Severity::Warning]);
+    }
+
+    #[test]
+    fn test_type_check_04() {
+        // Equality between a boolean and an integer, and an integer in an implication
+        let input = "
+        x : [0 .. 3] init 0;
+        x' = x;
+        b : bool init true;
+        b' = b;
+        label l1 = b == x;
+        label l2 = x -> b;
+        ";
+        assert_eq!(severities(input), vec![Severity::Error, Severity::Warning]);
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::lcgs::ast::{Decl, ImportDecl, Root};
+use crate::lcgs::diagnostics::SourceMap;
+use crate::lcgs::parse::parse_lcgs_at;
+
+/// Loads the LCGS program of the given file and resolves its imports. Imported paths are
+/// relative to the importing file, and the given function is used to read them. A file that
+/// is imported several times is only included the first time, while import cycles are errors.
+/// All declarations are returned in a single [Root] along with the [SourceMap] of the files,
+/// which must be used when rendering later errors. Errors are returned as rendered diagnostics.
+pub fn load_lcgs<F>(path: &Path, content: &str, read: F) -> Result<(Root, SourceMap), String>
+where
+    F: FnMut(&Path) -> io::Result<String>,
+{
+    let mut loader = Loader {
+        read,
+        sources: SourceMap::default(),
+        loaded: HashSet::new(),
+        stack: vec![],
+    };
+    let decls = loader.load_file(normalize(path), content)?;
+    let root = Root {
+        decls,
+        imports: vec![],
+    };
+    Ok((root, loader.sources))
+}
+
+struct Loader<F> {
+    read: F,
+    sources: SourceMap,
+    /// Files that have been loaded so far
+    loaded: HashSet<PathBuf>,
+    /// Files currently being loaded. The last file is the one currently being parsed.
+    stack: Vec<PathBuf>,
+}
+
+impl<F: FnMut(&Path) -> io::Result<String>> Loader<F> {
+    /// Parses the given file and returns its declarations with the declarations of its imports
+    /// included at the position of the imports
+    fn load_file(&mut self, path: PathBuf, content: &str) -> Result<Vec<Decl>, String> {
+        let begin = self.sources.add_file(&path.display().to_string(), content);
+        let root = parse_lcgs_at(self.sources.text(), begin)
+            .map_err(|err| self.sources.render_parse_error(&err))?;
+
+        self.loaded.insert(path.clone());
+        self.stack.push(path);
+
+        let mut decls = vec![];
+        let mut imports = root.imports.into_iter().peekable();
+        for (i, decl) in root.decls.into_iter().enumerate() {
+            while let Some(import) = imports.next_if(|import| import.position == i) {
+                decls.extend(self.load_import(import)?);
+            }
+            decls.push(decl);
+        }
+        for import in imports {
+            decls.extend(self.load_import(import)?);
+        }
+
+        self.stack.pop();
+        Ok(decls)
+    }
+
+    /// Loads the imported file, unless it has already been loaded
+    fn load_import(&mut self, import: ImportDecl) -> Result<Vec<Decl>, String> {
+        let importer = self.stack.last().unwrap();
+        let path = normalize(
+            &importer
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(&import.path),
+        );
+
+        if let Some(i) = self.stack.iter().position(|p| *p == path) {
+            let cycle = self.stack[i..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            return Err(self
+                .sources
+                .render(import.span, &format!("Import cycle: {}", cycle)));
+        }
+        if self.loaded.contains(&path) {
+            return Ok(vec![]);
+        }
+
+        let content = (self.read)(&path).map_err(|err| {
+            self.sources.render(
+                import.span,
+                &format!("Cannot import '{}': {}", path.display(), err),
+            )
+        })?;
+        self.load_file(path, &content)
+    }
+}
+
+/// Removes `.` and resolves `..` in the path where possible without accessing the file system,
+/// such that the same file imported from different directories has the same path
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+    use std::path::Path;
+
+    use crate::lcgs::ast::DeclKind;
+    use crate::lcgs::ir::intermediate::IntermediateLCGS;
+    use crate::lcgs::loader::{load_lcgs, normalize};
+
+    /// Loads "main.lcgs" from the given in-memory files
+    fn load(files: &[(&str, &str)]) -> Result<Vec<String>, String> {
+        let files: HashMap<&str, &str> = files.iter().cloned().collect();
+        let (root, _) = load_lcgs(Path::new("main.lcgs"), files["main.lcgs"], |path| {
+            files
+                .get(path.to_str().unwrap())
+                .map(|content| content.to_string())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        })?;
+        Ok(root
+            .decls
+            .iter()
+            .map(|decl| decl.kind.ident().name().to_string())
+            .collect())
+    }
+
+    #[test]
+    fn test_import_01() {
+        // Imported declarations are included at the position of the import
+        let files = [
+            (
+                "main.lcgs",
+                "const a = 1;\nimport \"lib/common.lcgs\";\nconst c = b;",
+            ),
+            ("lib/common.lcgs", "const b = a + 1;"),
+        ];
+        assert_eq!(load(&files), Ok(vec!["a".into(), "b".into(), "c".into()]));
+    }
+
+    #[test]
+    fn test_import_02() {
+        // Paths are relative to the importing file and files are only included once
+        let files = [
+            (
+                "main.lcgs",
+                "import \"lib/a.lcgs\";\nimport \"lib/b.lcgs\";\nconst c = 1;",
+            ),
+            ("lib/a.lcgs", "import \"common.lcgs\";\nconst a = 1;"),
+            (
+                "lib/b.lcgs",
+                "import \"../lib/./common.lcgs\";\nconst b = 1;",
+            ),
+            ("lib/common.lcgs", "const common = 1;"),
+        ];
+        assert_eq!(
+            load(&files),
+            Ok(vec!["common".into(), "a".into(), "b".into(), "c".into()])
+        );
+    }
+
+    #[test]
+    fn test_import_03() {
+        // Import cycle
+        let files = [
+            ("main.lcgs", "import \"a.lcgs\";"),
+            ("a.lcgs", "import \"main.lcgs\";"),
+        ];
+        let err = load(&files).unwrap_err();
+        assert!(err.contains("Import cycle: main.lcgs -> a.lcgs -> main.lcgs"));
+        assert!(err.contains("--> a.lcgs:1:1"));
+    }
+
+    #[test]
+    fn test_import_04() {
+        // Errors name the file that contains the error
+        let files = [
+            ("main.lcgs", "import \"a.lcgs\";\nimport \"b.lcgs\";"),
+            ("a.lcgs", "const a = 1;"),
+            ("b.lcgs", "const b = 1;\nconst c = ;"),
+        ];
+        let err = load(&files).unwrap_err();
+        assert!(err.contains("--> b.lcgs:2:"));
+
+        let files = [("main.lcgs", "\n\nimport \"missing.lcgs\";")];
+        let err = load(&files).unwrap_err();
+        assert!(err.contains("Cannot import 'missing.lcgs'"));
+        assert!(err.contains("--> main.lcgs:3:1"));
+    }
+
+    #[test]
+    fn test_import_05() {
+        // Errors in the intermediate representation are located in the imported file
+        let main = "import \"common.lcgs\";\nplayer p1 = thing;";
+        let common = "template thing\n    [a] foo;\nendtemplate";
+        let (root, sources) =
+            load_lcgs(Path::new("main.lcgs"), main, |_| Ok(common.to_string())).unwrap();
+        assert!(matches!(root.decls[0].kind, DeclKind::Template(_)));
+        let err = IntermediateLCGS::create(root).err().unwrap();
+        assert!(err.render(&sources).contains("--> common.lcgs:2:9"));
+    }
+
+    #[test]
+    fn test_normalize_01() {
+        assert_eq!(normalize(Path::new("./a/../b/./c")), Path::new("b/c"));
+        assert_eq!(normalize(Path::new("../a/b/../c")), Path::new("../a/c"));
+        assert_eq!(normalize(Path::new("/a/../../b")), Path::new("/b"));
+    }
+}
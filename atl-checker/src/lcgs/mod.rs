@@ -1,4 +1,7 @@
 pub mod ast;
+pub mod diagnostics;
+pub mod format;
 pub mod ir;
+pub mod loader;
 pub mod parse;
 mod precedence;
@@ -9,8 +9,10 @@ use std::vec::Drain;
 
 use pom::parser::*;
 
-use crate::lcgs::ast::DeclKind::{Const, Label, StateVar, Template, Transition};
-use crate::lcgs::ast::ExprKind::{BinaryOp, Max, Min, Number, OwnedIdent, TernaryIf, UnaryOp};
+use crate::lcgs::ast::DeclKind::{Const, Define, Label, StateVar, Template, Transition};
+use crate::lcgs::ast::ExprKind::{
+    Abs, BinaryOp, Call, Clamp, Index, Max, Min, Number, OwnedIdent, Pow, TernaryIf, UnaryOp,
+};
 use crate::lcgs::ast::UnaryOpKind::{Negation, Not};
 use crate::lcgs::ast::*;
 use crate::lcgs::precedence::Associativity::RightToLeft;
@@ -33,18 +35,15 @@ lazy_static! {
         set.insert("false");
         set.insert("min");
         set.insert("max");
+        set.insert("abs");
+        set.insert("pow");
+        set.insert("clamp");
+        set.insert("import");
+        set.insert("define");
         set
     };
 }
 
-/// A `Span` describes the position of a slice of text in the original program.
-/// Usually used to describe what text an AST node was created from.
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct Span {
-    begin: usize,
-    end: usize,
-}
-
 trait WithSpan<'a, I, O: 'a> {
     fn with_span(self) -> Parser<'a, I, (Span, O)>;
 }
@@ -108,10 +107,25 @@ fn number<'a>() -> Parser<'a, u8, Expr> {
         .convert(i32::from_str);
     parsed
         .with_span()
-        .map(|(_span, v)| Expr { kind: Number(v) })
+        .map(|(span, v)| Expr {
+            span,
+            kind: Number(v),
+        })
         .name("number")
 }
 
+/// Parser that parses the boolean literals "`true`" and "`false`" as 1 and 0 respectively
+fn boolean<'a>() -> Parser<'a, u8, Expr> {
+    let literal = seq(b"true").map(|_| 1) | seq(b"false").map(|_| 0);
+    (literal - !(alpha() | digit() | sym(b'_')))
+        .with_span()
+        .map(|(span, v)| Expr {
+            span,
+            kind: Number(v),
+        })
+        .name("boolean")
+}
+
 /// Parser that parses a symbol name. It must start with an alpha character, but subsequent
 /// characters can be digits or "_" too. Parser fails if it is a keyword.
 fn name<'a>() -> Parser<'a, u8, String> {
@@ -138,10 +152,24 @@ fn identifier<'a>() -> Parser<'a, u8, Identifier> {
         .name("identifier")
 }
 
+/// Parser that parses a player name, which may have a constant index if the player was
+/// declared using a loop, e.g. "`p1`" or "`p[2]`"
+pub(crate) fn player_name<'a>() -> Parser<'a, u8, String> {
+    let index = sym(b'[') * ws() * ((non_0_digit() - digit().repeat(0..)) | sym(b'0')).collect()
+        - ws()
+        - sym(b']');
+    (name() + index.opt())
+        .map(|(name, index)| match index {
+            Some(index) => format!("{}[{}]", name, String::from_utf8_lossy(index)),
+            None => name,
+        })
+        .name("player name")
+}
+
 /// Parser that parses a name with an optional owner and returns an `OwnedIdentifier`.
-/// I.e. "health" or "p1.health"
+/// I.e. "health", "p1.health", or "p[1].health"
 fn owned_identifier<'a>() -> Parser<'a, u8, Identifier> {
-    let identifier = (name() - sym(b'.')).opt() + name();
+    let identifier = (player_name() - sym(b'.')).opt() + name();
     identifier
         .with_span()
         .map(|(_span, (owner, name))| Identifier::OptionalOwner { owner, name })
@@ -156,6 +184,7 @@ fn binop<'a>() -> Parser<'a, u8, BinaryOpKind> {
         | seq(b"-")
         | seq(b"*")
         | seq(b"/")
+        | seq(b"%")
         | seq(b"==")
         | seq(b"!=")
         | seq(b">=")
@@ -195,6 +224,7 @@ fn solve_binary_precedence(
             }
             // Combine lhs and rhs using the given binary operator
             lhs = Expr {
+                span: lhs.span.merge(rhs.span),
                 kind: BinaryOp(op, Box::new(lhs), Box::new(rhs)),
             }
         } else {
@@ -213,6 +243,7 @@ fn ternary_expr<'a>() -> Parser<'a, u8, Expr> {
     (binary_expr() - ws() - sym(b'?') - ws() + binary_expr() - ws() - sym(b':') - ws()
         + binary_expr())
     .map(|((cond, then), els)| Expr {
+        span: cond.span.merge(els.span),
         kind: TernaryIf(Box::new(cond), Box::new(then), Box::new(els)),
     })
     .name("ternary expression")
@@ -220,7 +251,6 @@ fn ternary_expr<'a>() -> Parser<'a, u8, Expr> {
 
 /// Parser that parses an expression consisting of binary operators and primary expressions
 fn binary_expr<'a>() -> Parser<'a, u8, Expr> {
-    // TODO Spans and combining them
     let binexpr = primary_expr() + (ws() * binop() - ws() + primary_expr()).repeat(0..);
     binexpr
         .map(|(e, mut es)| solve_binary_precedence(e, 0, &mut es.drain(..).peekable()))
@@ -230,26 +260,68 @@ fn binary_expr<'a>() -> Parser<'a, u8, Expr> {
 /// Parser that parses an expression with a unary operator
 /// or a primary expression, i.e. number, identifier, or a parenthesised expression
 fn primary_expr<'a>() -> Parser<'a, u8, Expr> {
-    let neg = (sym(b'-') * call(primary_expr)).map(|e| Expr {
-        kind: UnaryOp(Negation, Box::new(e)),
-    });
-    let not = (sym(b'!') * call(primary_expr)).map(|e| Expr {
-        kind: UnaryOp(Not, Box::new(e)),
-    });
+    let neg = (sym(b'-') * call(primary_expr))
+        .with_span()
+        .map(|(span, e)| Expr {
+            span,
+            kind: UnaryOp(Negation, Box::new(e)),
+        });
+    let not = (sym(b'!') * call(primary_expr))
+        .with_span()
+        .map(|(span, e)| Expr {
+            span,
+            kind: UnaryOp(Not, Box::new(e)),
+        });
     let num = number();
-    let ident = owned_identifier().map(|i| Expr {
-        kind: OwnedIdent(Box::new(i)),
-    });
-    let par = sym(b'(') * ws() * call(expr) - ws() - sym(b')');
-
-    neg | not | num | min_expr() | max_expr() | ident | par
+    let index = sym(b'[') * ws() * call(expr) - ws() - sym(b']');
+    let ident =
+        (owned_identifier() + index.opt())
+            .with_span()
+            .map(|(span, (i, index))| match index {
+                Some(index) => Expr {
+                    span,
+                    kind: Index(Box::new(i), Box::new(index)),
+                },
+                None => Expr {
+                    span,
+                    kind: OwnedIdent(Box::new(i)),
+                },
+            });
+    let args = sym(b'(') * ws() * list(call(expr), ws() * sym(b',') - ws()) - ws() - sym(b')');
+    let macro_call = (owned_identifier() - ws() + args)
+        .with_span()
+        .map(|(span, (i, args))| Expr {
+            span,
+            kind: Call(Box::new(i), args),
+        })
+        .name("macro call");
+    // The span of a parenthesised expression includes the parentheses
+    let par = (sym(b'(') * ws() * call(expr) - ws() - sym(b')'))
+        .with_span()
+        .map(|(span, e)| Expr { span, ..e });
+
+    neg | not
+        | num
+        | boolean()
+        | min_expr()
+        | max_expr()
+        | abs_expr()
+        | pow_expr()
+        | clamp_expr()
+        | macro_call
+        | ident
+        | par
 }
 
 /// Parser for min expressions, e.g. "`min(0, 1, 2)`"
 fn min_expr<'a>() -> Parser<'a, u8, Expr> {
     let inner = list(call(expr), ws() * sym(b',') - ws());
     let full = seq(b"min") * ws() * sym(b'(') * ws() * inner - ws() - sym(b')');
-    full.map(|min| Expr { kind: Min(min) })
+    full.with_span()
+        .map(|(span, min)| Expr {
+            span,
+            kind: Min(min),
+        })
         .name("min expression")
 }
 
@@ -257,10 +329,50 @@ fn min_expr<'a>() -> Parser<'a, u8, Expr> {
 fn max_expr<'a>() -> Parser<'a, u8, Expr> {
     let inner = list(call(expr), ws() - sym(b',') - ws());
     let full = seq(b"max") * ws() * sym(b'(') * ws() * inner - ws() - sym(b')');
-    full.map(|max| Expr { kind: Max(max) })
+    full.with_span()
+        .map(|(span, max)| Expr {
+            span,
+            kind: Max(max),
+        })
         .name("max expression")
 }
 
+/// Parser for absolute value expressions, e.g. "`abs(x - 2)`"
+fn abs_expr<'a>() -> Parser<'a, u8, Expr> {
+    let full = seq(b"abs") * ws() * sym(b'(') * ws() * call(expr) - ws() - sym(b')');
+    full.with_span()
+        .map(|(span, e)| Expr {
+            span,
+            kind: Abs(Box::new(e)),
+        })
+        .name("abs expression")
+}
+
+/// Parser for integer power expressions, e.g. "`pow(2, x)`"
+fn pow_expr<'a>() -> Parser<'a, u8, Expr> {
+    let inner = call(expr) - ws() - sym(b',') - ws() + call(expr);
+    let full = seq(b"pow") * ws() * sym(b'(') * ws() * inner - ws() - sym(b')');
+    full.with_span()
+        .map(|(span, (base, exp))| Expr {
+            span,
+            kind: Pow(Box::new(base), Box::new(exp)),
+        })
+        .name("pow expression")
+}
+
+/// Parser for clamp expressions, e.g. "`clamp(x, 0, 5)`"
+fn clamp_expr<'a>() -> Parser<'a, u8, Expr> {
+    let inner =
+        call(expr) - ws() - sym(b',') - ws() + call(expr) - ws() - sym(b',') - ws() + call(expr);
+    let full = seq(b"clamp") * ws() * sym(b'(') * ws() * inner - ws() - sym(b')');
+    full.with_span()
+        .map(|(span, ((e, lo), hi))| Expr {
+            span,
+            kind: Clamp(Box::new(e), Box::new(lo), Box::new(hi)),
+        })
+        .name("clamp expression")
+}
+
 /// Parser that parses a type range, e.g. "`[0 .. max_health]`"
 fn type_range<'a>() -> Parser<'a, u8, TypeRange> {
     let inner = expr() - ws() - seq(b"..") - ws() + expr();
@@ -270,26 +382,110 @@ fn type_range<'a>() -> Parser<'a, u8, TypeRange> {
         .name("type range")
 }
 
+/// Parser that parses the type of a state variable, i.e. a type range, "`bool`", or an enum
+/// such as "`{idle, trying, critical}`". Booleans and enums are given their implied range.
+fn var_type<'a>() -> Parser<'a, u8, (VarType, TypeRange)> {
+    let range = type_range().map(|range| (VarType::Int, range));
+    // The implied ranges of booleans and enums get the span of the type
+    let boolean = (seq(b"bool") - !(alpha() | digit() | sym(b'_')))
+        .with_span()
+        .map(|(span, _)| {
+            let range = TypeRange {
+                min: Expr {
+                    span,
+                    kind: Number(0),
+                },
+                max: Expr {
+                    span,
+                    kind: Number(1),
+                },
+            };
+            (VarType::Bool, range)
+        });
+    let variants = list(name(), ws() * sym(b',') - ws());
+    let enumeration = (sym(b'{') * ws() * variants - ws() - sym(b'}'))
+        .with_span()
+        .convert(|(span, variants)| {
+            if variants.is_empty() {
+                return Err("An enum must have at least one variant.".to_string());
+            }
+            for (i, variant) in variants.iter().enumerate() {
+                if variants[..i].contains(variant) {
+                    return Err(format!("The enum variant '{}' is declared twice.", variant));
+                }
+            }
+            let range = TypeRange {
+                min: Expr {
+                    span,
+                    kind: Number(0),
+                },
+                max: Expr {
+                    span,
+                    kind: Number(variants.len() as i32 - 1),
+                },
+            };
+            Ok((VarType::Enum(variants), range))
+        });
+    (range | boolean | enumeration).name("variable type")
+}
+
 /// Parser that parses a variable, e.g.
-/// "`health : [0 .. max_health] init max_health`"
+/// "`health : [0 .. max_health] init max_health`" or an array variable, e.g.
+/// "`board : [0 .. 2][9] init 0; board[c]' = board[c]`". The initial value can be followed by
+/// the variable's [RangePolicy], e.g. "`health : [0 .. 3] init 3 overflow clamp`". The initial
+/// value can also be a range, e.g. "`health : [0 .. 3] init 1 .. 3`", in which case the game has
+/// an initial state for each combination of initial values. The update
+/// can be omitted, if the variable is only updated by actions, e.g. "`choice : [0 .. 2] init 0`",
+/// in which case the variable keeps its value unless it is updated by an action.
 fn var_decl<'a>() -> Parser<'a, u8, StateVarDecl> {
-    let base = identifier() - ws() - sym(b':') - ws() + type_range();
-    let init = seq(b"init") * ws() * expr();
-    let update = identifier() - sym(b'\'') - ws() - sym(b'=') - ws() + expr();
-    let whole = base - ws() + init - ws() - sym(b';') - ws() + update;
-    whole.convert(|(((name, range), initv), (prime, nextv))| {
-        if name == prime {
-            Ok(StateVarDecl {
-                name,
-                range,
-                ir_range: 0..=0,
-                initial_value: initv,
-                ir_initial_value: 0,
-                next_value: nextv,
-            })
-        } else {
-            Err("The names of the state variable and the following update declaration does not match.")
+    let size = sym(b'[') * ws() * call(expr) - ws() - sym(b']');
+    let base = identifier() - ws() - sym(b':') - ws() + var_type() - ws() + size.opt();
+    let init_max = ws() * seq(b"..") * ws() * call(expr);
+    let init = seq(b"init") * ws() * expr() + init_max.opt();
+    let policy = seq(b"overflow")
+        * ws()
+        * (seq(b"error").map(|_| RangePolicy::Error)
+            | seq(b"clamp").map(|_| RangePolicy::Clamp)
+            | seq(b"wrap").map(|_| RangePolicy::Wrap));
+    let index_name = sym(b'[') * ws() * name() - ws() - sym(b']');
+    let update = identifier() + index_name.opt() - sym(b'\'') - ws() - sym(b'=') - ws() + expr();
+    let whole = base - ws() + init - ws() + policy.opt() + (ws() * sym(b';') * ws() * update).opt();
+    whole.convert(|(((((name, (var_type, range)), size), (initv, init_max)), policy), update)| {
+        let ((prime, index_name), nextv) = match update {
+            Some(update) => update,
+            None => {
+                let unchanged = Expr {
+                    span: Span::default(),
+                    kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                        owner: None,
+                        name: name.name().to_string(),
+                    })),
+                };
+                ((name.clone(), None), unchanged)
+            }
+        };
+        if name != prime {
+            return Err("The names of the state variable and the following update declaration does not match.");
         }
+        let array = match (size, index_name) {
+            (Some(size), Some(index_name)) => Some(ArrayDecl { size, index_name }),
+            (None, None) => None,
+            (Some(_), None) => return Err("The update declaration of an array variable must name the index of the element, e.g. \"board[i]' = ...\"."),
+            (None, Some(_)) => return Err("The update declaration names an index, but the variable is not an array."),
+        };
+        Ok(StateVarDecl {
+            name,
+            var_type,
+            range,
+            ir_range: 0..=0,
+            initial_value: initv,
+            initial_max: init_max,
+            ir_initial_value: 0,
+            ir_initial_max: 0,
+            range_policy: policy.unwrap_or_default(),
+            next_value: nextv,
+            array,
+        })
     })
         .name("variable declaration")
 }
@@ -330,19 +526,27 @@ pub(crate) fn relabeling<'a>() -> Parser<'a, u8, Relabeling> {
 }
 
 /// Parser that parses a player declaration, e.g.
-/// "`player p1 = shooter [target1=p2, target2=p3]`"
+/// "`player p1 = shooter [target1=p2, target2=p3]`" or multiple players at once, e.g.
+/// "`player p[i in 0 .. 2] = shooter [target1=p[(i + 1) % 3], target2=p[(i + 2) % 3]]`"
 fn player_decl<'a>() -> Parser<'a, u8, PlayerDecl> {
-    let rhs = seq(b"player") * ws() * identifier();
+    let range = expr() - ws() - seq(b"..") - ws() + expr();
+    let repeat = sym(b'[') * ws() * name() - ws() - seq(b"in") - ws() + range - ws() - sym(b']');
+    let repeat = repeat.map(|(index_name, (min, max))| PlayerLoop {
+        index_name,
+        range: TypeRange { min, max },
+    });
+    let rhs = seq(b"player") * ws() * identifier() - ws() + repeat.opt();
     let lhs = identifier() - ws() + relabeling().opt();
     let whole = rhs - ws() - sym(b'=') - ws() + lhs;
     whole
-        .map(|(name, (template, relabel))| PlayerDecl {
+        .map(|((name, repeat), (template, relabel))| PlayerDecl {
             index: 0usize,
             name,
             template,
             relabeling: relabel.unwrap_or_else(|| Relabeling {
                 relabellings: vec![],
             }),
+            repeat,
         })
         .name("player declaration")
 }
@@ -351,23 +555,97 @@ fn player_decl<'a>() -> Parser<'a, u8, PlayerDecl> {
 /// "`[shoot_right] health > 0 & target1.health > 0`"
 fn transition_decl<'a>() -> Parser<'a, u8, TransitionDecl> {
     let name = sym(b'[') * ws() * identifier() - ws() - sym(b']');
-    let whole = name - ws() + expr();
+    // The guard and the target of the first update is parsed as an implication, e.g.
+    // "`q0 -> choice`" in "`q0 -> choice' = 1`", since "`->`" is also an operator
+    let first_update = sym(b'\'') * ws() * sym(b'=') * ws() * call(expr);
+    let update = (owned_identifier() - sym(b'\'') - ws() - sym(b'=') - ws() + call(expr))
+        .with_span()
+        .map(|(span, (target, value))| UpdateDecl {
+            target,
+            value,
+            span,
+        });
+    let updates = first_update.with_span() + (ws() * sym(b'&') * ws() * update).repeat(0..);
+    let whole = name - ws() + expr() + updates.opt();
     whole
-        .map(|(name, cond)| TransitionDecl {
-            name,
-            condition: cond,
+        .convert(|((name, cond), updates)| -> Result<TransitionDecl, &str> {
+            let ((first_span, first_value), mut updates) = match updates {
+                Some(updates) => updates,
+                None => {
+                    return Ok(TransitionDecl {
+                        name,
+                        condition: cond,
+                        updates: vec![],
+                    })
+                }
+            };
+            let (condition, target) = split_update_target(cond).ok_or(
+                "Expected a guard followed by '->' and an update, e.g. \"[one] q0 -> choice' = 1\".",
+            )?;
+            updates.insert(
+                0,
+                UpdateDecl {
+                    span: target.span.merge(first_span),
+                    target: match target.kind {
+                        OwnedIdent(target) => *target,
+                        _ => unreachable!(),
+                    },
+                    value: first_value,
+                },
+            );
+            Ok(TransitionDecl {
+                name,
+                condition,
+                updates,
+            })
         })
         .name("transition declaration")
 }
 
+/// Splits the target of the first update from the guard of a transition, e.g. "`q0 -> choice`"
+/// becomes "`q0`" and "`choice`". Without a guard, e.g. "`choice`", the guard is true.
+fn split_update_target(expr: Expr) -> Option<(Expr, Expr)> {
+    match expr.kind {
+        OwnedIdent(_) => Some((
+            Expr {
+                span: Span::default(),
+                kind: Number(1),
+            },
+            expr,
+        )),
+        BinaryOp(BinaryOpKind::Implication, guard, target)
+            if matches!(target.kind, OwnedIdent(_)) =>
+        {
+            Some((*guard, *target))
+        }
+        _ => None,
+    }
+}
+
+/// Parser that parses a macro declaration, e.g. "`define alive(p) = p.health > 0`"
+fn define_decl<'a>() -> Parser<'a, u8, DefineDecl> {
+    let params = sym(b'(') * ws() * list(name(), ws() * sym(b',') - ws()) - ws() - sym(b')');
+    let whole =
+        seq(b"define") * ws() * identifier() - ws() + params - ws() - sym(b'=') - ws() + expr();
+    whole
+        .map(|((name, params), body)| DefineDecl { name, params, body })
+        .name("macro declaration")
+}
+
 /// Parser that parses template declarations
 fn template_decl<'a>() -> Parser<'a, u8, TemplateDecl> {
-    let simple_decl = label_decl().map(|ld| Decl {
+    let simple_decl = label_decl().with_span().map(|(span, ld)| Decl {
+        span,
         kind: Label(Box::new(ld)),
-    }) | var_decl().map(|vd| Decl {
+    }) | var_decl().with_span().map(|(span, vd)| Decl {
+        span,
         kind: StateVar(Box::new(vd)),
-    }) | transition_decl().map(|td| Decl {
+    }) | transition_decl().with_span().map(|(span, td)| Decl {
+        span,
         kind: Transition(Box::new(td)),
+    }) | define_decl().with_span().map(|(span, dd)| Decl {
+        span,
+        kind: Define(Box::new(dd)),
     });
     let inner_decls = (simple_decl.with_semi() - ws()).repeat(0..);
     let temp =
@@ -376,39 +654,87 @@ fn template_decl<'a>() -> Parser<'a, u8, TemplateDecl> {
         .name("template declaration")
 }
 
+/// Items that can occur at root level
+enum RootItem {
+    Decl(Decl),
+    Import(Span, String),
+}
+
+/// Parser that parses an import of another LCGS file, e.g. "`import "common.lcgs"`"
+fn import_decl<'a>() -> Parser<'a, u8, String> {
+    let path = sym(b'"') * none_of(b"\"\r\n").repeat(1..).convert(String::from_utf8) - sym(b'"');
+    (seq(b"import") * ws() * path).name("import")
+}
+
 /// Parser that parses root level, i.e. all the global declarations
 fn root<'a>() -> Parser<'a, u8, Root> {
-    let simple_decl = label_decl().map(|ld| Decl {
+    let simple_decl = label_decl().with_span().map(|(span, ld)| Decl {
+        span,
         kind: Label(Box::new(ld)),
-    }) | var_decl().map(|vd| Decl {
+    }) | var_decl().with_span().map(|(span, vd)| Decl {
+        span,
         kind: StateVar(Box::new(vd)),
-    }) | player_decl().map(|pd| Decl {
+    }) | player_decl().with_span().map(|(span, pd)| Decl {
+        span,
         kind: DeclKind::Player(Box::new(pd)),
-    }) | const_decl().map(|cd| Decl {
+    }) | const_decl().with_span().map(|(span, cd)| Decl {
+        span,
         kind: Const(Box::new(cd)),
+    }) | define_decl().with_span().map(|(span, dd)| Decl {
+        span,
+        kind: Define(Box::new(dd)),
     });
     let any_decl = simple_decl.with_semi()
-        | template_decl().map(|td| Decl {
+        | template_decl().with_span().map(|(span, td)| Decl {
+            span,
             kind: Template(Box::new(td)),
         });
-    let root = ws() * (any_decl - ws()).repeat(0..) - ws() - end();
-    root.map(|decls| Root { decls })
+    let item = import_decl()
+        .with_span()
+        .with_semi()
+        .map(|(span, path)| RootItem::Import(span, path))
+        | any_decl.map(RootItem::Decl);
+    let root = ws() * (item - ws()).repeat(0..) - ws() - end();
+    root.map(|items| {
+        let mut decls = vec![];
+        let mut imports = vec![];
+        for item in items {
+            match item {
+                RootItem::Decl(decl) => decls.push(decl),
+                RootItem::Import(span, path) => imports.push(ImportDecl {
+                    path,
+                    position: decls.len(),
+                    span,
+                }),
+            }
+        }
+        Root { decls, imports }
+    })
 }
 
 /// Parse a LCGS program
 pub fn parse_lcgs(input: &str) -> pom::Result<Root> {
-    root().parse(input.as_bytes()).map_err(|err| {
-        if let pom::Error::Mismatch { position, .. } = err {
-            let line_nr = 1 + input.chars().take(position).filter(|c| *c == '\n').count();
-            Error::Custom {
-                message: format!("Bad declaration on line {}", line_nr),
-                position,
-                inner: Some(Box::new(err)),
+    parse_lcgs_at(input, 0)
+}
+
+/// Parse a LCGS program that starts at the given byte position of the input and continues to
+/// the end of the input. Spans and error positions are relative to the beginning of the input.
+pub(crate) fn parse_lcgs_at(input: &str, start: usize) -> pom::Result<Root> {
+    root()
+        .parse_at(input.as_bytes(), start)
+        .map(|(root, _)| root)
+        .map_err(|err| {
+            if let pom::Error::Mismatch { position, .. } = err {
+                let line_nr = 1 + input[start..position].matches('\n').count();
+                Error::Custom {
+                    message: format!("Bad declaration on line {}", line_nr),
+                    position,
+                    inner: Some(Box::new(err)),
+                }
+            } else {
+                err
             }
-        } else {
-            err
-        }
-    })
+        })
 }
 
 #[cfg(test)]
@@ -423,15 +749,18 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(23),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Min(rs)
             })
         )
@@ -443,18 +772,22 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(23),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(5),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Min(rs)
             })
         )
@@ -466,22 +799,32 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
-                    Box::new(Expr { kind: Number(23) }),
-                    Box::new(Expr { kind: Number(5) }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(23),
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(5),
+                    }),
                 ),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(5),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Min(rs)
             })
         )
@@ -493,15 +836,18 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(5),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Min(rs)
             })
         )
@@ -513,15 +859,18 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(23),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Max(rs)
             })
         )
@@ -533,18 +882,22 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(23),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(5),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Max(rs)
             })
         )
@@ -556,22 +909,32 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
-                    Box::new(Expr { kind: Number(23) }),
-                    Box::new(Expr { kind: Number(5) }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(23),
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(5),
+                    }),
                 ),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(5),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Max(rs)
             })
         )
@@ -583,15 +946,18 @@ mod tests {
         let parser = expr();
         let rs = vec![
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(1),
             },
             Expr {
+                span: Span::default(),
                 kind: ExprKind::Number(5),
             },
         ];
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: ExprKind::Max(rs)
             })
         )
@@ -641,10 +1007,20 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: TernaryIf(
-                    Box::new(Expr { kind: Number(1) }),
-                    Box::new(Expr { kind: Number(2) }),
-                    Box::new(Expr { kind: Number(3) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(1)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(2)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(3)
+                    })
                 )
             })
         );
@@ -666,18 +1042,36 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: TernaryIf(
                     Box::new(Expr {
-                        kind: UnaryOp(Not, Box::new(Expr { kind: Number(1) }))
+                        span: Span::default(),
+                        kind: UnaryOp(
+                            Not,
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            })
+                        )
                     }),
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Addition,
-                            Box::new(Expr { kind: Number(2) }),
-                            Box::new(Expr { kind: Number(3) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(3)
+                            })
                         )
                     }),
-                    Box::new(Expr { kind: Number(4) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(4)
+                    })
                 )
             })
         );
@@ -691,10 +1085,17 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
-                    Box::new(Expr { kind: Number(1) }),
-                    Box::new(Expr { kind: Number(2) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(1)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(2)
+                    })
                 )
             })
         );
@@ -708,16 +1109,27 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Addition,
-                            Box::new(Expr { kind: Number(1) }),
-                            Box::new(Expr { kind: Number(2) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
                         )
                     }),
-                    Box::new(Expr { kind: Number(3) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(3)
+                    })
                 )
             })
         );
@@ -731,16 +1143,27 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Subtraction,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Subtraction,
-                            Box::new(Expr { kind: Number(1) }),
-                            Box::new(Expr { kind: Number(2) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
                         )
                     }),
-                    Box::new(Expr { kind: Number(3) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(3)
+                    })
                 )
             })
         );
@@ -754,10 +1177,17 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Multiplication,
-                    Box::new(Expr { kind: Number(1) }),
-                    Box::new(Expr { kind: Number(2) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(1)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(2)
+                    })
                 )
             })
         );
@@ -771,16 +1201,27 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Multiplication,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Multiplication,
-                            Box::new(Expr { kind: Number(1) }),
-                            Box::new(Expr { kind: Number(2) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
                         )
                     }),
-                    Box::new(Expr { kind: Number(3) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(3)
+                    })
                 )
             })
         );
@@ -794,21 +1235,177 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Division,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Division,
-                            Box::new(Expr { kind: Number(1) }),
-                            Box::new(Expr { kind: Number(2) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
                         )
                     }),
-                    Box::new(Expr { kind: Number(3) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(3)
+                    })
+                )
+            })
+        );
+    }
+
+    #[test]
+    fn test_mod_01() {
+        // Modulo has the same precedence as multiplication
+        let input = br"1 + 7 % 3 * 2";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: BinaryOp(
+                    Addition,
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(1)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: BinaryOp(
+                            Multiplication,
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: BinaryOp(
+                                    Modulo,
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(7)
+                                    }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(3)
+                                    })
+                                )
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
+                        )
+                    })
+                )
+            })
+        );
+    }
+
+    #[test]
+    fn test_abs_01() {
+        let input = br"abs(-2) + 1";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: BinaryOp(
+                    Addition,
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Abs(Box::new(Expr {
+                            span: Span::default(),
+                            kind: UnaryOp(
+                                Negation,
+                                Box::new(Expr {
+                                    span: Span::default(),
+                                    kind: Number(2)
+                                })
+                            )
+                        }))
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(1)
+                    })
+                )
+            })
+        );
+    }
+
+    #[test]
+    fn test_pow_01() {
+        let input = br"pow(2, 3 + 1)";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: Pow(
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(2)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: BinaryOp(
+                            Addition,
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(3)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            })
+                        )
+                    })
+                )
+            })
+        );
+    }
+
+    #[test]
+    fn test_clamp_01() {
+        let input = br"clamp(x, 0, 5)";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: Clamp(
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                            owner: None,
+                            name: "x".to_string()
+                        }))
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(0)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(5)
+                    })
                 )
             })
         );
     }
 
+    #[test]
+    fn test_clamp_02() {
+        // clamp requires exactly three arguments
+        let input = br"clamp(x, 5)";
+        let parser = expr() - end();
+        assert!(parser.parse(input).is_err());
+    }
+
     #[test]
     fn test_neg_01() {
         // Simple negation
@@ -817,7 +1414,14 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
-                kind: UnaryOp(Negation, Box::new(Expr { kind: Number(2) })),
+                span: Span::default(),
+                kind: UnaryOp(
+                    Negation,
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(2)
+                    })
+                ),
             })
         );
     }
@@ -830,11 +1434,22 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Subtraction,
-                    Box::new(Expr { kind: Number(1) },),
                     Box::new(Expr {
-                        kind: UnaryOp(Negation, Box::new(Expr { kind: Number(2) })),
+                        span: Span::default(),
+                        kind: Number(1)
+                    },),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: UnaryOp(
+                            Negation,
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
+                        ),
                     })
                 )
             })
@@ -849,16 +1464,32 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: UnaryOp(
                     Not,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Equality,
                             Box::new(Expr {
-                                kind: UnaryOp(Negation, Box::new(Expr { kind: Number(1) }))
+                                span: Span::default(),
+                                kind: UnaryOp(
+                                    Negation,
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(1)
+                                    })
+                                )
                             }),
                             Box::new(Expr {
-                                kind: UnaryOp(Negation, Box::new(Expr { kind: Number(2) }))
+                                span: Span::default(),
+                                kind: UnaryOp(
+                                    Negation,
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(2)
+                                    })
+                                )
                             }),
                         )
                     }),
@@ -875,16 +1506,27 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Multiplication,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Addition,
-                            Box::new(Expr { kind: Number(1) }),
-                            Box::new(Expr { kind: Number(2) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            })
                         )
                     }),
-                    Box::new(Expr { kind: Number(3) }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(3)
+                    }),
                 )
             })
         );
@@ -898,14 +1540,25 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
-                    Box::new(Expr { kind: Number(1) }),
                     Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(1)
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Multiplication,
-                            Box::new(Expr { kind: Number(2) }),
-                            Box::new(Expr { kind: Number(3) })
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(2)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(3)
+                            })
                         )
                     }),
                 )
@@ -921,28 +1574,47 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Addition,
                             Box::new(Expr {
+                                span: Span::default(),
                                 kind: BinaryOp(
                                     Multiplication,
-                                    Box::new(Expr { kind: Number(1) }),
-                                    Box::new(Expr { kind: Number(2) }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(1)
+                                    }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(2)
+                                    }),
                                 )
                             }),
                             Box::new(Expr {
+                                span: Span::default(),
                                 kind: BinaryOp(
                                     Multiplication,
-                                    Box::new(Expr { kind: Number(3) }),
-                                    Box::new(Expr { kind: Number(4) }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(3)
+                                    }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(4)
+                                    }),
                                 )
                             })
                         )
                     }),
-                    Box::new(Expr { kind: Number(5) }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(5)
+                    }),
                 )
             })
         );
@@ -956,28 +1628,47 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Addition,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             Division,
                             Box::new(Expr {
+                                span: Span::default(),
                                 kind: BinaryOp(
                                     Multiplication,
-                                    Box::new(Expr { kind: Number(1) }),
                                     Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(1)
+                                    }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
                                         kind: BinaryOp(
                                             Addition,
-                                            Box::new(Expr { kind: Number(2) }),
-                                            Box::new(Expr { kind: Number(3) }),
+                                            Box::new(Expr {
+                                                span: Span::default(),
+                                                kind: Number(2)
+                                            }),
+                                            Box::new(Expr {
+                                                span: Span::default(),
+                                                kind: Number(3)
+                                            }),
                                         )
                                     }),
                                 )
                             }),
-                            Box::new(Expr { kind: Number(4) }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(4)
+                            }),
                         )
                     }),
-                    Box::new(Expr { kind: Number(5) }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(5)
+                    }),
                 )
             })
         );
@@ -991,28 +1682,47 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(Expr {
+                span: Span::default(),
                 kind: BinaryOp(
                     Implication,
                     Box::new(Expr {
+                        span: Span::default(),
                         kind: BinaryOp(
                             And,
                             Box::new(Expr {
+                                span: Span::default(),
                                 kind: BinaryOp(
                                     LessThan,
-                                    Box::new(Expr { kind: Number(1) }),
                                     Box::new(Expr {
+                                        span: Span::default(),
+                                        kind: Number(1)
+                                    }),
+                                    Box::new(Expr {
+                                        span: Span::default(),
                                         kind: BinaryOp(
                                             Multiplication,
-                                            Box::new(Expr { kind: Number(2) }),
-                                            Box::new(Expr { kind: Number(3) }),
+                                            Box::new(Expr {
+                                                span: Span::default(),
+                                                kind: Number(2)
+                                            }),
+                                            Box::new(Expr {
+                                                span: Span::default(),
+                                                kind: Number(3)
+                                            }),
                                         )
                                     }),
                                 )
                             }),
-                            Box::new(Expr { kind: Number(4) }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(4)
+                            }),
                         )
                     }),
-                    Box::new(Expr { kind: Number(5) })
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(5)
+                    })
                 )
             })
         );
@@ -1026,8 +1736,14 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(TypeRange {
-                min: Expr { kind: Number(0) },
-                max: Expr { kind: Number(20) },
+                min: Expr {
+                    span: Span::default(),
+                    kind: Number(0)
+                },
+                max: Expr {
+                    span: Span::default(),
+                    kind: Number(20)
+                },
             })
         );
     }
@@ -1040,8 +1756,14 @@ mod tests {
         assert_eq!(
             parser.parse(input),
             Ok(TypeRange {
-                min: Expr { kind: Number(0) },
-                max: Expr { kind: Number(20) },
+                min: Expr {
+                    span: Span::default(),
+                    kind: Number(0)
+                },
+                max: Expr {
+                    span: Span::default(),
+                    kind: Number(20)
+                },
             })
         );
     }
@@ -1057,9 +1779,14 @@ mod tests {
                 name: Identifier::Simple {
                     name: "health".to_string()
                 },
+                var_type: VarType::Int,
                 range: TypeRange {
-                    min: Expr { kind: Number(0) },
+                    min: Expr {
+                        span: Span::default(),
+                        kind: Number(0)
+                    },
                     max: Expr {
+                        span: Span::default(),
                         kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
                             owner: None,
                             name: "max_health".to_string(),
@@ -1068,18 +1795,24 @@ mod tests {
                 },
                 ir_range: 0..=0,
                 initial_value: Expr {
+                    span: Span::default(),
                     kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
                         owner: None,
                         name: "max_health".to_string(),
                     }))
                 },
+                initial_max: None,
                 ir_initial_value: 0,
+                ir_initial_max: 0,
+                range_policy: RangePolicy::Error,
                 next_value: Expr {
+                    span: Span::default(),
                     kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
                         owner: None,
                         name: "health".to_string(),
                     }))
-                }
+                },
+                array: None,
             })
         );
     }
@@ -1100,9 +1833,19 @@ mod tests {
             parser.parse(input),
             Ok(TypeRange {
                 min: Expr {
-                    kind: UnaryOp(Negation, Box::new(Expr { kind: Number(1) }))
+                    span: Span::default(),
+                    kind: UnaryOp(
+                        Negation,
+                        Box::new(Expr {
+                            span: Span::default(),
+                            kind: Number(1)
+                        })
+                    )
                 },
-                max: Expr { kind: Number(20) }
+                max: Expr {
+                    span: Span::default(),
+                    kind: Number(20)
+                }
             })
         );
     }
@@ -1116,11 +1859,303 @@ mod tests {
             parser.parse(input),
             Ok(TypeRange {
                 min: Expr {
-                    kind: UnaryOp(Negation, Box::new(Expr { kind: Number(20) }))
+                    span: Span::default(),
+                    kind: UnaryOp(
+                        Negation,
+                        Box::new(Expr {
+                            span: Span::default(),
+                            kind: Number(20)
+                        })
+                    )
                 },
                 max: Expr {
-                    kind: UnaryOp(Negation, Box::new(Expr { kind: Number(1) }))
+                    span: Span::default(),
+                    kind: UnaryOp(
+                        Negation,
+                        Box::new(Expr {
+                            span: Span::default(),
+                            kind: Number(1)
+                        })
+                    )
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_var_decl_bool_01() {
+        // Boolean variables have an implied range and can use boolean literals
+        let input = br"flag : bool init false; flag' = !flag";
+        let parser = var_decl();
+        let decl = parser.parse(input).unwrap();
+        assert_eq!(decl.var_type, VarType::Bool);
+        assert_eq!(
+            decl.range,
+            TypeRange {
+                min: Expr {
+                    span: Span::default(),
+                    kind: Number(0)
+                },
+                max: Expr {
+                    span: Span::default(),
+                    kind: Number(1)
+                },
+            }
+        );
+        assert_eq!(
+            decl.initial_value,
+            Expr {
+                span: Span::default(),
+                kind: Number(0)
+            }
+        );
+    }
+
+    #[test]
+    fn test_var_decl_enum_01() {
+        // Enum variables have an implied range
+        let input = br"phase : {idle, trying, critical} init idle; phase' = phase";
+        let parser = var_decl();
+        let decl = parser.parse(input).unwrap();
+        assert_eq!(
+            decl.var_type,
+            VarType::Enum(vec![
+                "idle".to_string(),
+                "trying".to_string(),
+                "critical".to_string()
+            ])
+        );
+        assert_eq!(
+            decl.range,
+            TypeRange {
+                min: Expr {
+                    span: Span::default(),
+                    kind: Number(0)
+                },
+                max: Expr {
+                    span: Span::default(),
+                    kind: Number(2)
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_var_decl_enum_02() {
+        // Enum variants must be unique
+        let input = br"phase : {idle, idle} init idle; phase' = phase";
+        let parser = var_decl();
+        assert!(parser.parse(input).is_err());
+    }
+
+    #[test]
+    fn test_var_decl_array_01() {
+        // Array variables have a size and name the index in the update
+        let input = br"board : [0 .. 2][n * n] init 0; board[c]' = board[c]";
+        let parser = var_decl();
+        let decl = parser.parse(input).unwrap();
+        assert_eq!(
+            decl.array,
+            Some(ArrayDecl {
+                size: Expr {
+                    span: Span::default(),
+                    kind: BinaryOp(
+                        Multiplication,
+                        Box::new(Expr {
+                            span: Span::default(),
+                            kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                                owner: None,
+                                name: "n".to_string()
+                            }))
+                        }),
+                        Box::new(Expr {
+                            span: Span::default(),
+                            kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                                owner: None,
+                                name: "n".to_string()
+                            }))
+                        })
+                    )
+                },
+                index_name: "c".to_string(),
+            })
+        );
+        assert_eq!(
+            decl.next_value,
+            Expr {
+                span: Span::default(),
+                kind: Index(
+                    Box::new(Identifier::OptionalOwner {
+                        owner: None,
+                        name: "board".to_string()
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                            owner: None,
+                            name: "c".to_string()
+                        }))
+                    })
+                )
+            }
+        );
+    }
+
+    #[test]
+    fn test_var_decl_array_02() {
+        // The update of an array must name the index, and only arrays have an index
+        let input1 = br"board : [0 .. 2][9] init 0; board' = 0";
+        assert!(var_decl().parse(input1).is_err());
+        let input2 = br"board : [0 .. 2] init 0; board[c]' = 0";
+        assert!(var_decl().parse(input2).is_err());
+    }
+
+    #[test]
+    fn test_index_01() {
+        // Indexing an owned identifier
+        let input = br"p1.board[ 2 ]";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: Index(
+                    Box::new(Identifier::OptionalOwner {
+                        owner: Some("p1".to_string()),
+                        name: "board".to_string()
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: Number(2)
+                    })
+                )
+            })
+        );
+    }
+
+    #[test]
+    fn test_transition_decl_updates_01() {
+        // The guard is separated from the first update
+        let input = br"[one] q0 && !done -> choice' = 1 & p2.seen' = true";
+        let decl = transition_decl().parse(input).unwrap();
+        assert!(matches!(decl.condition.kind, BinaryOp(And, _, _)));
+        assert_eq!(decl.updates.len(), 2);
+        assert_eq!(decl.updates[0].target.name(), "choice");
+        assert_eq!(decl.updates[0].value.kind, Number(1));
+        assert_eq!(
+            decl.updates[1].target,
+            Identifier::OptionalOwner {
+                owner: Some("p2".to_string()),
+                name: "seen".to_string()
+            }
+        );
+
+        // Without a guard, the action is always available
+        let decl = transition_decl().parse(br"[reset] x' = 0").unwrap();
+        assert_eq!(decl.condition.kind, Number(1));
+        assert_eq!(decl.updates.len(), 1);
+
+        // The guard must be followed by '->'
+        assert!(transition_decl().parse(br"[bad] a && x' = 0").is_err());
+    }
+
+    #[test]
+    fn test_var_decl_init_range_01() {
+        // A range of initial values
+        let decl = var_decl()
+            .parse(br"health : [0 .. 3] init 1 .. top")
+            .unwrap();
+        assert_eq!(decl.initial_value.kind, Number(1));
+        assert_eq!(
+            decl.initial_max.map(|max| max.kind),
+            Some(OwnedIdent(Box::new(Identifier::OptionalOwner {
+                owner: None,
+                name: "top".to_string()
+            })))
+        );
+        let decl = var_decl().parse(br"health : [0 .. 3] init 1").unwrap();
+        assert_eq!(decl.initial_max, None);
+    }
+
+    #[test]
+    fn test_var_decl_without_update_01() {
+        // Variables without an update keep their value
+        let decl = var_decl().parse(br"choice : [0 .. 2] init 0").unwrap();
+        assert_eq!(
+            decl.next_value.kind,
+            OwnedIdent(Box::new(Identifier::OptionalOwner {
+                owner: None,
+                name: "choice".to_string()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_define_decl_01() {
+        let input = br"define free(p, c) = p.pos != c";
+        let decl = define_decl().parse(input).unwrap();
+        assert_eq!(decl.name.name(), "free");
+        assert_eq!(decl.params, vec!["p".to_string(), "c".to_string()]);
+        assert!(matches!(decl.body.kind, BinaryOp(Inequality, _, _)));
+
+        let input = br"define always() = true";
+        let decl = define_decl().parse(input).unwrap();
+        assert!(decl.params.is_empty());
+    }
+
+    #[test]
+    fn test_call_01() {
+        // A call of a macro owned by a player
+        let input = br"p1.free ( 2, x + 1 )";
+        let parser = expr();
+        let res = parser.parse(input).unwrap();
+        if let Call(ident, args) = res.kind {
+            assert_eq!(
+                *ident,
+                Identifier::OptionalOwner {
+                    owner: Some("p1".to_string()),
+                    name: "free".to_string()
                 }
+            );
+            assert_eq!(args.len(), 2);
+        } else {
+            panic!("Expected a call, but found {:?}", res);
+        }
+    }
+
+    #[test]
+    fn test_boolean_01() {
+        // Boolean literals are numbers, but identifiers starting with true or false are not
+        let input = br"true && false || trueish";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: BinaryOp(
+                    Or,
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: BinaryOp(
+                            And,
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(0)
+                            })
+                        )
+                    }),
+                    Box::new(Expr {
+                        span: Span::default(),
+                        kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                            owner: None,
+                            name: "trueish".to_string()
+                        }))
+                    })
+                )
             })
         );
     }
@@ -1135,6 +2170,7 @@ mod tests {
             Ok(LabelDecl {
                 index: 0usize,
                 condition: Expr {
+                    span: Span::default(),
                     kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
                         owner: None,
                         name: "health".to_string(),
@@ -1158,7 +2194,10 @@ mod tests {
                 name: Identifier::Simple {
                     name: "max_health".to_string(),
                 },
-                definition: Expr { kind: Number(1) }
+                definition: Expr {
+                    span: Span::default(),
+                    kind: Number(1)
+                }
             })
         );
     }
@@ -1174,7 +2213,14 @@ mod tests {
                     name: "max_health".to_string(),
                 },
                 definition: Expr {
-                    kind: UnaryOp(Negation, Box::new(Expr { kind: Number(1) }))
+                    span: Span::default(),
+                    kind: UnaryOp(
+                        Negation,
+                        Box::new(Expr {
+                            span: Span::default(),
+                            kind: Number(1)
+                        })
+                    )
                 },
             })
         );
@@ -1205,6 +2251,7 @@ mod tests {
                     RelabelCase {
                         prev: "target".to_string(),
                         new: Expr {
+                            span: Span::default(),
                             kind: ExprKind::OwnedIdent(Box::from(Identifier::OptionalOwner {
                                 owner: None,
                                 name: "p2".to_string()
@@ -1214,6 +2261,7 @@ mod tests {
                     RelabelCase {
                         prev: "dmg".to_string(),
                         new: Expr {
+                            span: Span::default(),
                             kind: ExprKind::Number(2),
                         }
                     }
@@ -1239,7 +2287,8 @@ mod tests {
                 },
                 relabeling: Relabeling {
                     relabellings: vec![]
-                }
+                },
+                repeat: None,
             })
         );
     }
@@ -1263,13 +2312,73 @@ mod tests {
                     relabellings: vec![RelabelCase {
                         prev: "target".to_string(),
                         new: Expr {
+                            span: Span::default(),
                             kind: ExprKind::OwnedIdent(Box::from(Identifier::OptionalOwner {
                                 owner: None,
                                 name: "p2".to_string()
                             }))
                         }
                     }]
-                }
+                },
+                repeat: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_player_decl_03() {
+        // Player loop
+        let input = br"player p[i in 0 .. n - 1] = shooter [target=p[(i + 1) % n]]";
+        let parser = player_decl();
+        let decl = parser.parse(input).unwrap();
+        assert_eq!(
+            decl.repeat,
+            Some(PlayerLoop {
+                index_name: "i".to_string(),
+                range: TypeRange {
+                    min: Expr {
+                        span: Span::default(),
+                        kind: Number(0)
+                    },
+                    max: Expr {
+                        span: Span::default(),
+                        kind: BinaryOp(
+                            Subtraction,
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                                    owner: None,
+                                    name: "n".to_string()
+                                }))
+                            }),
+                            Box::new(Expr {
+                                span: Span::default(),
+                                kind: Number(1)
+                            })
+                        )
+                    },
+                },
+            })
+        );
+        assert!(matches!(
+            decl.relabeling.relabellings[0].new.kind,
+            ExprKind::Index(..)
+        ));
+    }
+
+    #[test]
+    fn test_ident_indexed_owner_01() {
+        // An owner can be a player declared in a loop
+        let input = br"p[12].health";
+        let parser = expr();
+        assert_eq!(
+            parser.parse(input),
+            Ok(Expr {
+                span: Span::default(),
+                kind: OwnedIdent(Box::new(Identifier::OptionalOwner {
+                    owner: Some("p[12]".to_string()),
+                    name: "health".to_string()
+                }))
             })
         );
     }
@@ -1285,7 +2394,11 @@ mod tests {
                 name: Identifier::Simple {
                     name: "shoot_right".to_string()
                 },
-                condition: Expr { kind: Number(1) },
+                condition: Expr {
+                    span: Span::default(),
+                    kind: Number(1)
+                },
+                updates: vec![],
             })
         );
     }
@@ -1381,13 +2494,18 @@ mod tests {
             parser.parse(input),
             Ok(Root {
                 decls: vec![Decl {
+                    span: Span::default(),
                     kind: Const(Box::new(ConstDecl {
                         name: Identifier::Simple {
                             name: "max_health".to_string(),
                         },
-                        definition: Expr { kind: Number(1) },
+                        definition: Expr {
+                            span: Span::default(),
+                            kind: Number(1)
+                        },
                     }))
-                }]
+                }],
+                imports: vec![],
             })
         );
     }
@@ -1409,4 +2527,28 @@ mod tests {
         assert!(parser.parse(input).is_ok());
         assert_eq!(parser.parse(input).unwrap().decls.len(), 2);
     }
+
+    #[test]
+    fn test_span_01() {
+        // Spans cover the source text of the node, including parentheses
+        let input = br"a + (b * 2)";
+        let parser = expr();
+        let res = parser.parse(input).unwrap();
+        assert_eq!(res.span, Span { begin: 0, end: 11 });
+        if let BinaryOp(_, lhs, rhs) = res.kind {
+            assert_eq!(lhs.span, Span { begin: 0, end: 1 });
+            assert_eq!(rhs.span, Span { begin: 4, end: 11 });
+        } else {
+            panic!("Expected a binary operator")
+        }
+    }
+
+    #[test]
+    fn test_span_02() {
+        // Declarations have spans too. The trailing semicolon is not included
+        let input = "const a = 1;\nlabel b = a > 0;\n";
+        let root = parse_lcgs(input).unwrap();
+        let span = root.decls[1].span;
+        assert_eq!(&input[span.begin..span.end], "label b = a > 0");
+    }
 }
@@ -22,7 +22,7 @@ pub enum Associativity {
 /// equivalent to "1 + (2 * 3)" and not "(1 + 2) * 3".
 pub fn precedence(op: &BinaryOpKind) -> Precedence {
     match op {
-        Multiplication | Division => Precedence(20, LeftToRight),
+        Multiplication | Division | Modulo => Precedence(20, LeftToRight),
         Addition | Subtraction => Precedence(19, LeftToRight),
         LessThan | GreaterThan | LessOrEqual | GreaterOrEqual => Precedence(18, LeftToRight),
         Equality | Inequality => Precedence(17, LeftToRight),
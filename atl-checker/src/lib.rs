@@ -11,6 +11,7 @@ pub mod atl;
 pub mod com;
 pub mod common;
 pub mod edg;
+pub mod global;
 pub mod lcgs;
 #[cfg(feature = "graph-printer")]
 pub mod printer;
@@ -1,6 +1,7 @@
 use atl_checker::atl::dependencygraph::{ATLDependencyGraph, ATLVertex};
 use atl_checker::atl::formula::Phi;
 use atl_checker::atl::gamestructure::EagerGameStructure;
+use atl_checker::atl::gamestructure::GameStructure;
 use atl_checker::edg::distributed_certain_zero;
 use atl_checker::lcgs::ir::intermediate::IntermediateLCGS;
 use atl_checker::lcgs::parse::parse_lcgs;
@@ -23,16 +24,25 @@ macro_rules! bench_json {
         fn $name(c: &mut Criterion) {
             c.bench_function(stringify!($name), |b| {
                 b.iter(|| {
-                    let game_structure: EagerGameStructure =
-                        serde_json::from_str(include_str!(concat!("json/", $model))).unwrap();
+                    let game_structure =
+                        EagerGameStructure::from_json(include_str!(concat!("json/", $model)))
+                            .unwrap();
                     let graph = ATLDependencyGraph { game_structure };
 
                     let formula: Arc<Phi> =
                         serde_json::from_str(include_str!(concat!("json/", $formula))).unwrap();
 
-                    let v0 = ATLVertex::FULL { state: 0, formula };
+                    let v0 = ATLVertex::FULL {
+                        state: graph.game_structure.initial_state(),
+                        formula,
+                    };
 
-                    distributed_certain_zero(graph, v0, num_cpus::get() as u64);
+                    distributed_certain_zero(
+                        graph,
+                        v0,
+                        num_cpus::get() as u64,
+                        BreadthFirstSearchBuilder,
+                    );
                 })
             });
         }
@@ -62,7 +72,7 @@ macro_rules! bench_lcgs {
                     let formula = load_formula(concat!(model_path_prefix!(), $formula));
 
                     let v0 = ATLVertex::FULL {
-                        state: graph.game_structure.initial_state_index(),
+                        state: graph.game_structure.initial_state(),
                         formula,
                     };
 
@@ -101,7 +111,7 @@ macro_rules! bench_lcgs_threads {
                             let formula = load_formula(concat!(model_path_prefix!(), $formula));
 
                             let v0 = ATLVertex::FULL {
-                                state: graph.game_structure.initial_state_index(),
+                                state: graph.game_structure.initial_state(),
                                 formula,
                             };
 
@@ -4,9 +4,11 @@ extern crate num_cpus;
 #[macro_use]
 extern crate tracing;
 
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{stdout, Read, Write};
+use std::path::Path;
 use std::process::exit;
 use std::sync::Arc;
 
@@ -16,11 +18,16 @@ use tracing::trace;
 
 use atl_checker::atl::dependencygraph::{ATLDependencyGraph, ATLVertex};
 use atl_checker::atl::formula::{ATLExpressionParser, Phi};
-use atl_checker::atl::gamestructure::{EagerGameStructure, GameStructure};
+use atl_checker::atl::gamestructure::statistics::Statistics;
+use atl_checker::atl::gamestructure::{reachable_states, EagerGameStructure, GameStructure};
 use atl_checker::edg::distributed_certain_zero;
 use atl_checker::lcgs::ast::DeclKind;
-use atl_checker::lcgs::ir::intermediate::IntermediateLCGS;
-use atl_checker::lcgs::ir::symbol_table::Owner;
+use atl_checker::lcgs::diagnostics::{render_parse_error, SourceMap};
+use atl_checker::lcgs::format::format_lcgs;
+use atl_checker::lcgs::ir::intermediate::{DeadlockPolicy, IntermediateLCGS};
+use atl_checker::lcgs::ir::symbol_table::{Owner, SymbolIdentifier};
+use atl_checker::lcgs::ir::type_checker::Severity;
+use atl_checker::lcgs::loader::load_lcgs as load_lcgs_with_imports;
 use atl_checker::lcgs::parse::parse_lcgs;
 #[cfg(feature = "graph-printer")]
 use atl_checker::printer::print_graph;
@@ -39,6 +46,13 @@ enum FormulaFormat {
     ATL,
 }
 
+/// The formats of results that the system supports
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 /// The model types that the system supports
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum ModelType {
@@ -80,11 +94,8 @@ fn main_inner() -> Result<(), String> {
             file.read_to_string(&mut content)
                 .map_err(|err| format!("Failed to read input model.\n{}", err))?;
 
-            let lcgs = parse_lcgs(&content)
-                .map_err(|err| format!("Failed to parse the LCGS program.\n{}", err))?;
-
-            let ir = IntermediateLCGS::create(lcgs)
-                .map_err(|err| format!("Invalid LCGS program.\n{}", err))?;
+            let lcgs_options = get_lcgs_options_from_args(index_args)?;
+            let (ir, _) = load_lcgs(input_model_path, &content, &lcgs_options)?;
 
             println!("Players:");
             for player in &ir.get_player() {
@@ -95,28 +106,241 @@ fn main_inner() -> Result<(), String> {
             for label_symbol in &ir.get_labels() {
                 let label_decl = ir.get_decl(&label_symbol).unwrap();
                 if let DeclKind::Label(label) = &label_decl.kind {
-                    if Owner::Global == label_symbol.owner {
-                        println!("{} : {}", &label_symbol.name, label.index)
-                    } else {
-                        println!("{} : {}", &label_symbol, label.index)
+                    println!("{} : {}", label_name(label_symbol), label.index)
+                }
+            }
+        }
+        ("fmt", Some(fmt_args)) => {
+            // Print the LCGS model in canonical form, or the expanded model
+
+            let input_model_path = fmt_args.value_of("input_model").unwrap();
+            let model_type = get_model_type_from_args(fmt_args)?;
+
+            if model_type != ModelType::LCGS {
+                return Err("The 'fmt' command is only valid for LCGS models".to_string());
+            }
+
+            let mut file = File::open(input_model_path)
+                .map_err(|err| format!("Failed to open input model.\n{}", err))?;
+            let mut content = String::new();
+            file.read_to_string(&mut content)
+                .map_err(|err| format!("Failed to read input model.\n{}", err))?;
+
+            let lcgs_options = get_lcgs_options_from_args(fmt_args)?;
+            if fmt_args.is_present("expand") {
+                let (ir, _) = load_lcgs(input_model_path, &content, &lcgs_options)?;
+                print!("{}", ir.format_expanded());
+            } else {
+                // Imports are kept as they are, such that each file can be formatted on its own
+                let mut lcgs = parse_lcgs(&content).map_err(|err| {
+                    format!(
+                        "Failed to parse the LCGS program.\n{}",
+                        render_parse_error(input_model_path, &content, &err)
+                    )
+                })?;
+                for (name, value) in &lcgs_options.const_overrides {
+                    lcgs.override_const(name, *value)?;
+                }
+                print!("{}", format_lcgs(&lcgs));
+            }
+        }
+        ("lint", Some(lint_args)) => {
+            // Report unused and suspicious declarations of the LCGS model
+
+            let input_model_path = lint_args.value_of("input_model").unwrap();
+            let model_type = get_model_type_from_args(lint_args)?;
+
+            if model_type != ModelType::LCGS {
+                return Err("The 'lint' command is only valid for LCGS models".to_string());
+            }
+
+            let mut file = File::open(input_model_path)
+                .map_err(|err| format!("Failed to open input model.\n{}", err))?;
+            let mut content = String::new();
+            file.read_to_string(&mut content)
+                .map_err(|err| format!("Failed to read input model.\n{}", err))?;
+
+            let lcgs_options = get_lcgs_options_from_args(lint_args)?;
+            let (ir, sources) = load_lcgs(input_model_path, &content, &lcgs_options)?;
+
+            // Labels can only be reported as unused if we know the formulas
+            let used_labels = match lint_args.values_of("formula") {
+                Some(formula_paths) => {
+                    let formula_format = get_formula_format_from_args(lint_args)?;
+                    let used = formula_paths
+                        .flat_map(|path| load_formula(path, formula_format, &ir).propositions())
+                        .collect::<HashSet<usize>>();
+                    Some(used)
+                }
+                None => None,
+            };
+
+            let lints = ir.lint(used_labels.as_ref());
+            for lint in &lints {
+                println!("{}", sources.render_warning(lint.span, &lint.msg));
+            }
+            println!("Found {} possible problem(s).", lints.len());
+        }
+        ("stats", Some(stats_args)) => {
+            // Explore the reachable states of the LCGS model and print their statistics
+
+            let input_model_path = stats_args.value_of("input_model").unwrap();
+            let model_type = get_model_type_from_args(stats_args)?;
+
+            if model_type != ModelType::LCGS {
+                return Err("The 'stats' command is only valid for LCGS models".to_string());
+            }
+
+            let mut file = File::open(input_model_path)
+                .map_err(|err| format!("Failed to open input model.\n{}", err))?;
+            let mut content = String::new();
+            file.read_to_string(&mut content)
+                .map_err(|err| format!("Failed to read input model.\n{}", err))?;
+
+            let lcgs_options = get_lcgs_options_from_args(stats_args)?;
+            let (ir, _) = load_lcgs(input_model_path, &content, &lcgs_options)?;
+            let initial_states = ir.initial_states();
+            let stats = Statistics::explore(&ir, &initial_states);
+
+            println!("Initial states: {}", initial_states.len());
+            println!("States: {}", stats.states);
+            println!(
+                "Transitions: {} ({:.2} per state)",
+                stats.transitions,
+                stats.transitions as f64 / stats.states as f64
+            );
+            println!(
+                "Distinct successors: {} ({:.2} per state)",
+                stats.edges,
+                stats.edges as f64 / stats.states as f64
+            );
+            println!("Maximum depth: {}", stats.depth);
+            println!("Deadlock states: {}", stats.deadlocks);
+
+            println!("\nMoves per player (min / average / max):");
+            for player in &ir.get_player() {
+                let moves = &stats.moves[player.index()];
+                println!(
+                    "{} : {} / {:.2} / {}",
+                    player.get_name(),
+                    moves.min,
+                    moves.average(stats.states),
+                    moves.max
+                )
+            }
+
+            println!("\nStates satisfying each label:");
+            for label_symbol in &ir.get_labels() {
+                let label_decl = ir.get_decl(label_symbol).unwrap();
+                if let DeclKind::Label(label) = &label_decl.kind {
+                    let count = stats.labels.get(&label.index).cloned().unwrap_or(0);
+                    println!(
+                        "{} : {} ({:.2}%)",
+                        label_name(label_symbol),
+                        count,
+                        100.0 * count as f64 / stats.states as f64
+                    )
+                }
+            }
+        }
+        ("export", Some(export_args)) => {
+            // Explore the reachable states of the LCGS model and write them in the JSON format
+            // of EagerGameStructure
+
+            let input_model_path = export_args.value_of("input_model").unwrap();
+            let model_type = get_model_type_from_args(export_args)?;
+
+            if model_type != ModelType::LCGS {
+                return Err("The 'export' command is only valid for LCGS models".to_string());
+            }
+
+            let mut file = File::open(input_model_path)
+                .map_err(|err| format!("Failed to open input model.\n{}", err))?;
+            let mut content = String::new();
+            file.read_to_string(&mut content)
+                .map_err(|err| format!("Failed to read input model.\n{}", err))?;
+
+            let lcgs_options = get_lcgs_options_from_args(export_args)?;
+            if lcgs_options.deadlock_policy == DeadlockPolicy::Allow {
+                return Err("Models cannot be exported with '--deadlock allow', since every player must have a move in JSON models. Use '--deadlock idle' or '--deadlock error' instead.".to_string());
+            }
+            let (ir, _) = load_lcgs(input_model_path, &content, &lcgs_options)?;
+            // JSON models have a single initial state
+            let initial_states = ir.initial_states();
+            if initial_states.len() > 1 {
+                return Err(format!(
+                    "The model has {} initial states, but JSON models have only one. Use --state to choose one.",
+                    initial_states.len()
+                ));
+            }
+            let mut eager = EagerGameStructure::from_game_structure(&ir, ir.initial_state());
+
+            // Include the label names such that formulas can refer to labels by name
+            let mut label_names = vec![];
+            for label_symbol in &ir.get_labels() {
+                let label_decl = ir.get_decl(label_symbol).unwrap();
+                if let DeclKind::Label(label) = &label_decl.kind {
+                    if label_names.len() <= label.index {
+                        label_names.resize(label.index + 1, String::new());
                     }
+                    label_names[label.index] = label_name(label_symbol);
                 }
             }
+            eager.label_names = Some(label_names);
+
+            let output: Box<dyn Write> = match export_args.value_of("output") {
+                Some(path) => Box::new(
+                    File::create(path)
+                        .map_err(|err| format!("Failed to create output file.\n{}", err))?,
+                ),
+                None => Box::new(stdout()),
+            };
+            serde_json::to_writer(output, &eager)
+                .map_err(|err| format!("Failed to write the exported model.\n{}", err))?;
         }
         ("solver", Some(solver_args)) => {
             let input_model_path = solver_args.value_of("input_model").unwrap();
             let model_type = get_model_type_from_args(&solver_args)?;
             let formula_path = solver_args.value_of("formula").unwrap();
             let formula_format = get_formula_format_from_args(&solver_args)?;
+            let lcgs_options = get_lcgs_options_from_args(solver_args)?;
 
             // Generic start function for use with `load` that start model checking with `distributed_certain_zero`
-            fn check_model<G>(graph: ATLDependencyGraph<G>, v0: ATLVertex, threads: u64)
+            fn check_model<G>(game_structure: G, formula: Phi, threads: u64)
             where
                 G: GameStructure + Send + Sync + Clone + Debug + 'static,
             {
-                let result =
-                    distributed_certain_zero(graph, v0, threads, BreadthFirstSearchBuilder);
-                println!("Result: {}", result);
+                println!(
+                    "Checking the formula: {}",
+                    formula.in_context_of(&game_structure)
+                );
+                let formula = Arc::from(formula);
+                let initial_states = game_structure.initial_states();
+                let graph = ATLDependencyGraph { game_structure };
+                if let [state] = initial_states[..] {
+                    let v0 = ATLVertex::FULL { state, formula };
+                    let result =
+                        distributed_certain_zero(graph, v0, threads, BreadthFirstSearchBuilder);
+                    println!("Result: {}", result);
+                } else {
+                    // Each initial state is checked from scratch with its own run of the
+                    // algorithm, so no work is shared between them, even for states that are
+                    // reachable from several of them.
+                    for state in initial_states {
+                        let v0 = ATLVertex::FULL {
+                            state,
+                            formula: formula.clone(),
+                        };
+                        let name = graph.game_structure.state_name(state);
+                        let result = distributed_certain_zero(
+                            graph.clone(),
+                            v0,
+                            threads,
+                            BreadthFirstSearchBuilder,
+                        );
+                        println!("Result in {}: {}", name, result);
+                    }
+                }
             }
 
             let threads = match solver_args.value_of("threads") {
@@ -129,47 +353,123 @@ fn main_inner() -> Result<(), String> {
                 input_model_path,
                 formula_path,
                 formula_format,
+                &lcgs_options,
+                |game_structure, formula| check_model(game_structure, formula, threads),
+                |game_structure, formula| check_model(game_structure, formula, threads),
+            )?
+        }
+        ("satisfaction", Some(satisfaction_args)) => {
+            // Find all reachable states where the formula holds
+            let input_model_path = satisfaction_args.value_of("input_model").unwrap();
+            let model_type = get_model_type_from_args(satisfaction_args)?;
+            let formula_path = satisfaction_args.value_of("formula").unwrap();
+            let formula_format = get_formula_format_from_args(satisfaction_args)?;
+            let output_format = get_output_format_from_args(satisfaction_args)?;
+            let lcgs_options = get_lcgs_options_from_args(satisfaction_args)?;
+
+            fn print_satisfaction_set<G: GameStructure>(
+                game_structure: G,
+                formula: Phi,
+                format: OutputFormat,
+                output: Option<&str>,
+            ) -> Result<(), String> {
+                let formula_name = formula.in_context_of(&game_structure).to_string();
+                let graph = ATLDependencyGraph { game_structure };
+                let states = reachable_states(
+                    &graph.game_structure,
+                    &graph.game_structure.initial_states(),
+                );
+                let satisfying: Vec<String> = graph
+                    .satisfaction_set(&states, Arc::from(formula))
+                    .into_iter()
+                    .map(|state| graph.game_structure.state_name(state))
+                    .collect();
+
+                let mut output: Box<dyn Write> = match output {
+                    Some(path) => Box::new(
+                        File::create(path)
+                            .map_err(|err| format!("Failed to create output file.\n{}", err))?,
+                    ),
+                    None => Box::new(stdout()),
+                };
+                let result = match format {
+                    OutputFormat::Text => {
+                        let mut text = format!(
+                            "{} of {} reachable states satisfy the formula: {}\n",
+                            satisfying.len(),
+                            states.len(),
+                            formula_name
+                        );
+                        for name in &satisfying {
+                            text.push_str(name);
+                            text.push('\n');
+                        }
+                        output.write_all(text.as_bytes())
+                    }
+                    OutputFormat::Json => {
+                        let json = serde_json::json!({
+                            "formula": formula_name,
+                            "reachable": states.len(),
+                            "states": satisfying,
+                        });
+                        writeln!(output, "{}", json)
+                    }
+                };
+                result.map_err(|err| format!("Failed to write the satisfaction set.\n{}", err))
+            }
+
+            load(
+                model_type,
+                input_model_path,
+                formula_path,
+                formula_format,
+                &lcgs_options,
                 |game_structure, formula| {
-                    println!(
-                        "Checking the formula: {}",
-                        formula.in_context_of(&game_structure)
-                    );
-                    let v0 = ATLVertex::FULL {
-                        state: 0,
-                        formula: Arc::from(formula),
-                    };
-                    let graph = ATLDependencyGraph { game_structure };
-                    check_model(graph, v0, threads);
+                    print_satisfaction_set(
+                        game_structure,
+                        formula,
+                        output_format,
+                        satisfaction_args.value_of("output"),
+                    )
                 },
                 |game_structure, formula| {
-                    println!(
-                        "Checking the formula: {}",
-                        formula.in_context_of(&game_structure)
-                    );
-                    let arc = Arc::from(formula);
-                    let graph = ATLDependencyGraph { game_structure };
-                    let v0 = ATLVertex::FULL {
-                        state: graph.game_structure.initial_state_index(),
-                        formula: arc,
-                    };
-                    check_model(graph, v0, threads);
+                    print_satisfaction_set(
+                        game_structure,
+                        formula,
+                        output_format,
+                        satisfaction_args.value_of("output"),
+                    )
                 },
-            )?
+            )??
         }
         ("analyse", Some(analyse_args)) => {
             let input_model_path = analyse_args.value_of("input_model").unwrap();
             let model_type = get_model_type_from_args(&analyse_args)?;
             let formula_path = analyse_args.value_of("formula").unwrap();
             let formula_format = get_formula_format_from_args(&analyse_args)?;
+            let lcgs_options = get_lcgs_options_from_args(analyse_args)?;
 
-            fn analyse_model<G>(graph: ATLDependencyGraph<G>, v0: ATLVertex)
+            fn analyse_model<G>(game_structure: G, formula: Phi)
             where
                 G: GameStructure + Send + Sync + Clone + Debug + 'static,
             {
-                let mss = minimum_solve_set(&graph, v0);
+                let formula = Arc::from(formula);
+                let initial_states = game_structure.initial_states();
+                let graph = ATLDependencyGraph { game_structure };
                 println!("Configuration: Minimum solve set size");
-                for (vertex, assignment) in mss {
-                    println!("{}: {}", vertex, assignment.len());
+                for &state in &initial_states {
+                    // The results are labelled per state, if there are several initial states
+                    if initial_states.len() > 1 {
+                        println!("Initial state {}:", graph.game_structure.state_name(state));
+                    }
+                    let v0 = ATLVertex::FULL {
+                        state,
+                        formula: formula.clone(),
+                    };
+                    let mss = minimum_solve_set(&graph, v0);
+                    for (vertex, assignment) in mss {
+                        println!("{}: {}", vertex, assignment.len());
+                    }
                 }
             }
 
@@ -178,22 +478,9 @@ fn main_inner() -> Result<(), String> {
                 input_model_path,
                 formula_path,
                 formula_format,
-                |game_structure, formula| {
-                    let v0 = ATLVertex::FULL {
-                        state: 0,
-                        formula: Arc::from(formula),
-                    };
-                    let graph = ATLDependencyGraph { game_structure };
-                    analyse_model(graph, v0);
-                },
-                |game_structure, formula| {
-                    let v0 = ATLVertex::FULL {
-                        state: game_structure.initial_state_index(),
-                        formula: Arc::from(formula),
-                    };
-                    let graph = ATLDependencyGraph { game_structure };
-                    analyse_model(graph, v0);
-                },
+                &lcgs_options,
+                analyse_model,
+                analyse_model,
             )?
         }
         ("graph", Some(graph_args)) => {
@@ -203,13 +490,23 @@ fn main_inner() -> Result<(), String> {
                 let model_type = get_model_type_from_args(&graph_args)?;
                 let formula_path = graph_args.value_of("formula").unwrap();
                 let formula_format = get_formula_format_from_args(&graph_args)?;
+                let lcgs_options = get_lcgs_options_from_args(&graph_args)?;
 
                 // Generic start function for use with `load` that starts the graph printer
                 fn print_model<G: GameStructure>(
-                    graph: ATLDependencyGraph<G>,
-                    v0: ATLVertex,
+                    game_structure: G,
+                    formula: Phi,
                     output: Option<&str>,
                 ) {
+                    println!(
+                        "Printing graph for: {}",
+                        formula.in_context_of(&game_structure)
+                    );
+                    let v0 = ATLVertex::FULL {
+                        state: game_structure.initial_state(),
+                        formula: Arc::from(formula),
+                    };
+                    let graph = ATLDependencyGraph { game_structure };
                     let output: Box<dyn Write> = match output {
                         Some(path) => {
                             let file = File::create(path).unwrap_or_else(|err| {
@@ -229,30 +526,12 @@ fn main_inner() -> Result<(), String> {
                     input_model_path,
                     formula_path,
                     formula_format,
+                    &lcgs_options,
                     |game_structure, formula| {
-                        println!(
-                            "Printing graph for: {}",
-                            formula.in_context_of(&game_structure)
-                        );
-                        let v0 = ATLVertex::FULL {
-                            state: 0,
-                            formula: Arc::from(formula),
-                        };
-                        let graph = ATLDependencyGraph { game_structure };
-                        print_model(graph, v0, graph_args.value_of("output"));
+                        print_model(game_structure, formula, graph_args.value_of("output"))
                     },
                     |game_structure, formula| {
-                        println!(
-                            "Printing graph for: {}",
-                            formula.in_context_of(&game_structure)
-                        );
-                        let arc = Arc::from(formula);
-                        let graph = ATLDependencyGraph { game_structure };
-                        let v0 = ATLVertex::FULL {
-                            state: graph.game_structure.initial_state_index(),
-                            formula: arc,
-                        };
-                        print_model(graph, v0, graph_args.value_of("output"));
+                        print_model(game_structure, formula, graph_args.value_of("output"))
                     },
                 )?
             }
@@ -262,6 +541,16 @@ fn main_inner() -> Result<(), String> {
     Ok(())
 }
 
+/// The name of a label as written in ATL formulas, i.e. "name" for global labels and
+/// "player.name" for labels of players
+fn label_name(symbol: &SymbolIdentifier) -> String {
+    if Owner::Global == symbol.owner {
+        symbol.name.clone()
+    } else {
+        symbol.to_string()
+    }
+}
+
 /// Reads a formula in JSON format from a file and returns the formula as a string
 /// and as a parsed Phi struct.
 /// This function will exit the program if it encounters an error.
@@ -325,12 +614,115 @@ fn get_formula_format_from_args(args: &ArgMatches) -> Result<FormulaFormat, Stri
     }
 }
 
+/// Determine the format of the results by reading the --output-format argument
+fn get_output_format_from_args(args: &ArgMatches) -> Result<OutputFormat, String> {
+    match args.value_of("output_format") {
+        None | Some("text") => Ok(OutputFormat::Text),
+        Some("json") => Ok(OutputFormat::Json),
+        Some(format) => Err(format!("Invalid output format '{}' specified with --output-format. Use either \"text\" or \"json\" [default is \"text\"].", format)),
+    }
+}
+
+/// Parse the constant overrides given with the repeatable --const argument. Each override
+/// must be on the form "name=value", where value is an integer.
+fn get_const_overrides_from_args(args: &ArgMatches) -> Result<Vec<(String, i32)>, String> {
+    let overrides = match args.values_of("const") {
+        Some(overrides) => overrides,
+        None => return Ok(vec![]),
+    };
+    overrides
+        .map(|arg| parse_assignment(arg, "constant override", "--const NAME=VALUE"))
+        .collect()
+}
+
+/// Determine the values of the state variables in the initial state of LCGS models by reading
+/// the --state argument, e.g. "--state billy.health=1,clayton.health=2"
+fn get_state_from_args(args: &ArgMatches) -> Result<Option<Vec<(String, i32)>>, String> {
+    match args.value_of("state") {
+        None => Ok(None),
+        Some(arg) => arg
+            .split(',')
+            .map(|value| parse_assignment(value, "state value", "--state NAME=VALUE,..."))
+            .collect::<Result<_, _>>()
+            .map(Some),
+    }
+}
+
+/// Parses an assignment of the form "NAME=VALUE", where the value is an integer. The kind and
+/// the usage of the argument are used in error messages.
+fn parse_assignment(arg: &str, kind: &str, usage: &str) -> Result<(String, i32), String> {
+    let (name, value) = arg.split_at(
+        arg.find('=')
+            .ok_or_else(|| format!("Invalid {} '{}'. Use the form \"{}\".", kind, arg, usage))?,
+    );
+    let value = value[1..].trim().parse::<i32>().map_err(|err| {
+        format!(
+            "Invalid value in {} '{}'. The value must be an integer.\n{}",
+            kind, arg, err
+        )
+    })?;
+    Ok((name.trim().to_string(), value))
+}
+
+/// Options for loading LCGS models given on the command line
+struct LcgsOptions {
+    const_overrides: Vec<(String, i32)>,
+    check_ranges: bool,
+    tighten_ranges: bool,
+    type_check: bool,
+    deadlock_policy: DeadlockPolicy,
+    state: Option<Vec<(String, i32)>>,
+    cone_of_influence: bool,
+    symmetry: bool,
+}
+
+impl LcgsOptions {
+    /// Returns true if any LCGS specific option is used
+    fn is_used(&self) -> bool {
+        !self.const_overrides.is_empty()
+            || self.check_ranges
+            || self.tighten_ranges
+            || self.deadlock_policy != DeadlockPolicy::default()
+            || self.state.is_some()
+            || self.cone_of_influence
+            || self.symmetry
+    }
+}
+
+fn get_lcgs_options_from_args(args: &ArgMatches) -> Result<LcgsOptions, String> {
+    Ok(LcgsOptions {
+        const_overrides: get_const_overrides_from_args(args)?,
+        check_ranges: args.is_present("check_ranges"),
+        tighten_ranges: args.is_present("tighten_ranges"),
+        type_check: !args.is_present("no_type_check"),
+        deadlock_policy: get_deadlock_policy_from_args(args)?,
+        state: get_state_from_args(args)?,
+        cone_of_influence: args.is_present("cone_of_influence"),
+        symmetry: args.is_present("symmetry"),
+    })
+}
+
+/// Determine the deadlock policy of LCGS models by reading the --deadlock argument
+fn get_deadlock_policy_from_args(args: &ArgMatches) -> Result<DeadlockPolicy, String> {
+    match args.value_of("deadlock") {
+        Some("allow") => Ok(DeadlockPolicy::Allow),
+        None | Some("idle") => Ok(DeadlockPolicy::Idle),
+        Some("error") => Ok(DeadlockPolicy::Error),
+        Some(policy) => Err(format!(
+            "Invalid deadlock policy '{}' specified with --deadlock. Use either \"allow\", \"idle\", or \"error\".",
+            policy
+        )),
+    }
+}
+
 /// Loads a model and a formula from files, and then call the handler function with the loaded model and formula.
+/// The LCGS options are applied to LCGS models before they are checked.
 fn load<R, J, L>(
     model_type: ModelType,
     game_structure_path: &str,
     formula_path: &str,
     formula_format: FormulaFormat,
+    lcgs_options: &LcgsOptions,
     handle_json: J,
     handle_lcgs: L,
 ) -> Result<R, String>
@@ -349,29 +741,196 @@ where
     // Depending on which model_type is specified, use the relevant parsing logic
     match model_type {
         ModelType::JSON => {
-            let game_structure = serde_json::from_str(content.as_str())
-                .map_err(|err| format!("Failed to deserialize input model.\n{}", err))?;
+            if lcgs_options.is_used() {
+                return Err(
+                    "Constants and model checks can only be used with LCGS models".to_string(),
+                );
+            }
+
+            let game_structure = EagerGameStructure::from_json(content.as_str())
+                .map_err(|err| format!("Failed to load input model.\n{}", err))?;
 
             let phi = load_formula(formula_path, formula_format, &game_structure);
 
             Ok(handle_json(game_structure, phi))
         }
         ModelType::LCGS => {
-            let lcgs = parse_lcgs(&content)
-                .map_err(|err| format!("Failed to parse the LCGS program.\n{}", err))?;
-
-            let game_structure = IntermediateLCGS::create(lcgs)
-                .map_err(|err| format!("Invalid LCGS program.\n{}", err))?;
+            if lcgs_options.deadlock_policy == DeadlockPolicy::Allow {
+                return Err("Formulas cannot be checked with '--deadlock allow', since deadlocked states have no moves. Use '--deadlock idle' or '--deadlock error' instead.".to_string());
+            }
+            let (mut game_structure, _) = load_lcgs(game_structure_path, &content, lcgs_options)?;
 
             let phi = load_formula(formula_path, formula_format, &game_structure);
 
+            if lcgs_options.cone_of_influence {
+                // Variables that cannot influence the labels of the formula are removed
+                let labels = phi.propositions().into_iter().collect();
+                game_structure = game_structure.cone_of_influence(&labels);
+            }
+            if lcgs_options.symmetry {
+                // States are canonicalized under the symmetries of the players
+                let reduced = game_structure.symmetry_reduction(&phi);
+                print_symmetric_initial_states(&game_structure, &reduced);
+                game_structure = reduced;
+            }
+
             Ok(handle_lcgs(game_structure, phi))
         }
     }
 }
 
+/// Prints the initial states that are left out by symmetry reduction, since the results of
+/// their canonical representative stand for them as well
+fn print_symmetric_initial_states(game_structure: &IntermediateLCGS, reduced: &IntermediateLCGS) {
+    for canonical in reduced.initial_states() {
+        let symmetric: Vec<String> = game_structure
+            .initial_states()
+            .into_iter()
+            .filter(|&state| state != canonical && reduced.canonical_state(state) == canonical)
+            .map(|state| game_structure.state_name(state))
+            .collect();
+        if !symmetric.is_empty() {
+            println!(
+                "Results in {} also hold in the symmetric initial states {}",
+                reduced.state_name(canonical),
+                symmetric.join(", ")
+            );
+        }
+    }
+}
+
+/// Parses the given LCGS program and the files it imports, overrides the given constants, and
+/// creates the intermediate representation. Errors are rendered with their location in the
+/// program. The program is type checked, unless disabled, and conflicting updates are detected.
+/// If requested, the reachable states are explored to detect updates that are out of range and
+/// divisions by zero, and the declared ranges are tightened by static analysis. The [SourceMap]
+/// of the files is returned along with the program, such that later diagnostics can be rendered.
+fn load_lcgs(
+    path: &str,
+    content: &str,
+    options: &LcgsOptions,
+) -> Result<(IntermediateLCGS, SourceMap), String> {
+    let (mut lcgs, sources) = load_lcgs_with_imports(Path::new(path), content, |path| {
+        std::fs::read_to_string(path)
+    })
+    .map_err(|err| format!("Failed to parse the LCGS program.\n{}", err))?;
+    for (name, value) in &options.const_overrides {
+        lcgs.override_const(name, *value)?;
+    }
+
+    let mut ir = IntermediateLCGS::create(lcgs)
+        .map_err(|err| format!("Invalid LCGS program.\n{}", err.render(&sources)))?;
+    ir.set_deadlock_policy(options.deadlock_policy);
+    if let Some(values) = &options.state {
+        let state = ir
+            .state_from_values(values)
+            .map_err(|err| format!("Invalid state given with --state.\n{}", err))?;
+        ir.set_initial_state(&state);
+    }
+    if options.type_check {
+        // Warnings are printed, while errors stop the program
+        let mut errors = vec![];
+        for diag in ir.type_check() {
+            match diag.severity {
+                Severity::Warning => {
+                    eprintln!("{}", sources.render_warning(diag.span, &diag.msg))
+                }
+                Severity::Error => errors.push(sources.render(diag.span, &diag.msg)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(format!(
+                "Type errors found in the LCGS program. Use --no-type-check to ignore them.\n{}",
+                errors.join("\n")
+            ));
+        }
+    }
+    if options.check_ranges {
+        ir.check_ranges()
+            .map_err(|violation| format!("Range violation found.\n{}", violation))?;
+        ir.check_division_by_zero()
+            .map_err(|division| sources.render(division.span, &division.to_string()))?;
+    }
+    // Conflicting updates are only possible if a variable is updated by actions of several
+    // players, so the reachable states are rarely explored to check for them
+    ir.check_update_conflicts()
+        .map_err(|conflict| format!("Update conflict found.\n{}", conflict))?;
+    match options.deadlock_policy {
+        DeadlockPolicy::Allow => {
+            if let Err(deadlock) = ir.check_deadlocks() {
+                eprintln!(
+                    "warning: Deadlock found. Formulas cannot be checked in deadlocked states. Use '--deadlock idle' to give players without actions an implicit idle action.\n{}\n",
+                    deadlock
+                );
+            }
+        }
+        DeadlockPolicy::Idle => {}
+        DeadlockPolicy::Error => ir
+            .check_deadlocks()
+            .map_err(|deadlock| format!("Deadlock found.\n{}", deadlock))?,
+    }
+    if options.tighten_ranges {
+        ir.tighten_ranges();
+    }
+    Ok((ir, sources))
+}
+
 /// Define and parse command line arguments
 fn parse_arguments() -> ArgMatches<'static> {
+    fn const_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("const")
+            .short("c")
+            .long("const")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Override a constant in an LCGS model, e.g. '--const max_health=3'. Can be repeated")
+    }
+
+    fn check_ranges_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("check_ranges")
+            .long("check-ranges")
+            .help("Explore all reachable states of an LCGS model first and report updates to values outside the range of a variable and divisions by zero")
+    }
+
+    fn tighten_ranges_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("tighten_ranges")
+            .long("tighten-ranges")
+            .help("Replace the declared ranges of the state variables of an LCGS model by the values found to be reachable by static analysis, which reduces the size of state indexes")
+    }
+
+    fn deadlock_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("deadlock")
+            .long("deadlock")
+            .takes_value(true)
+            .help("What happens in LCGS models when a player has no available actions {{allow, idle, error}}. With 'idle' the player gets an implicit action that does nothing. With 'allow' and 'error' all reachable states are explored first and deadlocks are reported as warnings and errors respectively. Formulas cannot be checked with 'allow' [default: idle]")
+    }
+
+    fn cone_of_influence_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("cone_of_influence")
+            .long("cone-of-influence")
+            .help("Remove the state variables of an LCGS model that cannot influence the labels of the formula, which reduces the number of states. Removed variables are not part of state names, and initial states that only differ in removed variables are checked once")
+    }
+
+    fn symmetry_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("symmetry")
+            .long("symmetry")
+            .help("Replace the states of an LCGS model by canonical representatives under the symmetries of players instantiated from the same template, which reduces the number of states. Symmetric initial states are checked once")
+    }
+
+    fn state_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("state")
+            .long("state")
+            .takes_value(true)
+            .help("Start an LCGS model in the given state instead of its initial states, e.g. '--state billy.health=1,clayton.health=2'. Variables not given have their initial value")
+    }
+
+    fn no_type_check_argument<'a>() -> Arg<'a, 'a> {
+        Arg::with_name("no_type_check")
+            .long("no-type-check")
+            .help("Do not type check LCGS models, which reports e.g. booleans compared to integers as errors and integers used as booleans as warnings. Useful for legacy models")
+    }
+
     fn build_common_arguments<'a>(builder: clap::App<'a, 'a>) -> App<'a, 'a> {
         builder
             .arg(
@@ -411,6 +970,20 @@ fn parse_arguments() -> ArgMatches<'static> {
                     .env("OUTPUT")
                     .help("The path to write output to"),
             )
+            .arg(const_argument())
+            .arg(check_ranges_argument())
+            .arg(tighten_ranges_argument())
+            .arg(deadlock_argument())
+            .arg(state_argument())
+            .arg(no_type_check_argument())
+    }
+
+    /// Adds the options for reducing the state space of LCGS models, for the commands that
+    /// only report results in the initial states
+    fn build_reduction_arguments<'a>(builder: clap::App<'a, 'a>) -> App<'a, 'a> {
+        builder
+            .arg(cone_of_influence_argument())
+            .arg(symmetry_argument())
     }
 
     let version_text = format!("{} ({})", VERSION, GIT_VERSION);
@@ -425,7 +998,7 @@ fn parse_arguments() -> ArgMatches<'static> {
                 .default_value("warn")
                 .help("Comma separated list of filter directives"),
         )
-        .subcommand(build_common_arguments(
+        .subcommand(build_reduction_arguments(build_common_arguments(
             SubCommand::with_name("solver").arg(
                 Arg::with_name("threads")
                     .short("r")
@@ -433,22 +1006,135 @@ fn parse_arguments() -> ArgMatches<'static> {
                     .env("THREADS")
                     .help("Number of threads to run solver on"),
             ),
-        ))
+        )))
         .subcommand(
-            SubCommand::with_name("index").arg(
-                Arg::with_name("input_model")
-                    .short("m")
-                    .long("model")
-                    .env("INPUT_MODEL")
-                    .required(true)
-                    .help("The input file to generate model from"),
-            ),
+            SubCommand::with_name("index")
+                .arg(
+                    Arg::with_name("input_model")
+                        .short("m")
+                        .long("model")
+                        .env("INPUT_MODEL")
+                        .required(true)
+                        .help("The input file to generate model from"),
+                )
+                .arg(const_argument())
+                .arg(check_ranges_argument())
+                .arg(tighten_ranges_argument())
+                .arg(deadlock_argument())
+                .arg(no_type_check_argument()),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Prints an LCGS model in canonical form. Comments are not preserved")
+                .arg(
+                    Arg::with_name("input_model")
+                        .short("m")
+                        .long("model")
+                        .env("INPUT_MODEL")
+                        .required(true)
+                        .help("The LCGS model to format"),
+                )
+                .arg(
+                    Arg::with_name("expand")
+                        .short("e")
+                        .long("expand")
+                        .help("Print the model after relabeling of templates, constant inlining, and expansion of arrays, macros, and imports. The result is meant for inspection and is not a valid LCGS model"),
+                )
+                .arg(const_argument())
+                .arg(no_type_check_argument()),
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Reports unused and suspicious declarations of an LCGS model")
+                .arg(
+                    Arg::with_name("input_model")
+                        .short("m")
+                        .long("model")
+                        .env("INPUT_MODEL")
+                        .required(true)
+                        .help("The LCGS model to lint"),
+                )
+                .arg(
+                    Arg::with_name("formula")
+                        .short("f")
+                        .long("formula")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A formula that is checked on the model. Labels not used by any of the given formulas are reported. Can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("formula_format")
+                        .short("y")
+                        .long("formula-format")
+                        .env("FORMULA_FORMAT")
+                        .help("The format of ATL formula files given {{json, text}}"),
+                )
+                .arg(const_argument())
+                .arg(no_type_check_argument()),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Explores all reachable states of an LCGS model and prints statistics about them")
+                .arg(
+                    Arg::with_name("input_model")
+                        .short("m")
+                        .long("model")
+                        .env("INPUT_MODEL")
+                        .required(true)
+                        .help("The LCGS model to explore"),
+                )
+                .arg(const_argument())
+                .arg(check_ranges_argument())
+                .arg(tighten_ranges_argument())
+                .arg(deadlock_argument())
+                .arg(state_argument())
+                .arg(no_type_check_argument()),
         )
-        .subcommand(build_common_arguments(SubCommand::with_name("analyse")));
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Explores all reachable states of an LCGS model and writes them as a JSON model. States are numbered in breadth-first order, so the initial state is 0, and players and labels keep the indexes shown by the 'index' command")
+                .arg(
+                    Arg::with_name("input_model")
+                        .short("m")
+                        .long("model")
+                        .env("INPUT_MODEL")
+                        .required(true)
+                        .help("The LCGS model to export"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .env("OUTPUT")
+                        .help("The path to write the JSON model to. Defaults to standard output"),
+                )
+                .arg(const_argument())
+                .arg(check_ranges_argument())
+                .arg(tighten_ranges_argument())
+                .arg(deadlock_argument())
+                .arg(state_argument())
+                .arg(no_type_check_argument()),
+        )
+        .subcommand(build_common_arguments(
+            SubCommand::with_name("satisfaction")
+                .about("Finds all reachable states of the model where the formula holds. The states are checked together, such that work is shared between them. The state space is not reduced, so every reachable state is listed with all its variables")
+                .arg(
+                    Arg::with_name("output_format")
+                        .long("output-format")
+                        .takes_value(true)
+                        .help("The format of the list of states {{text, json}} [default: text]"),
+                ),
+        ))
+        .subcommand(build_reduction_arguments(build_common_arguments(
+            SubCommand::with_name("analyse"),
+        )));
 
     if cfg!(feature = "graph-printer") {
-        app.subcommand(build_common_arguments(SubCommand::with_name("graph")))
-            .get_matches()
+        app.subcommand(build_reduction_arguments(build_common_arguments(
+            SubCommand::with_name("graph"),
+        )))
+        .get_matches()
     } else {
         app.get_matches()
     }